@@ -0,0 +1,139 @@
+//! Split-fill aggregation
+//!
+//! One large whale order is often matched against many resting orders, so the
+//! same transaction emits several `OrdersFilled` logs for the same token.
+//! Copying each log on its own sizes every copy off a fraction of the trade and
+//! puts it in the wrong tier. `FillAggregator` groups fills by
+//! `(tx_hash, token_id)` for a short window and hands back one logical trade
+//! with total shares, total USDC and VWAP.
+//!
+//! The aggregator is generic over the event it carries and hands back every
+//! merged fill: the bot sizes the first one with the totals and keeps the log
+//! keys of the rest for reorg tracking.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// `(tx_hash, token_id)`
+pub type AggregateKey = (String, String);
+
+/// Totals of the fills merged into one trade
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aggregate {
+    pub fills: usize,
+    pub shares: f64,
+    pub usd: f64,
+}
+
+impl Aggregate {
+    /// Volume-weighted average price across the merged fills
+    pub fn vwap(&self) -> f64 {
+        if self.shares > 0.0 { self.usd / self.shares } else { 0.0 }
+    }
+}
+
+struct Group<T> {
+    first_seen: Instant,
+    items: Vec<T>,
+    totals: Aggregate,
+}
+
+pub struct FillAggregator<T> {
+    window: Duration,
+    groups: HashMap<AggregateKey, Group<T>>,
+}
+
+impl<T> FillAggregator<T> {
+    /// Fills of the same key arriving within `window` of the first are merged.
+    /// A zero window still merges fills added before the next `take_due`.
+    pub fn new(window: Duration) -> Self {
+        Self { window, groups: HashMap::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Add one fill
+    pub fn add(&mut self, key: AggregateKey, shares: f64, usd: f64, item: T, now: Instant) {
+        let group = self.groups.entry(key).or_insert_with(|| Group {
+            first_seen: now,
+            items: Vec::new(),
+            totals: Aggregate { fills: 0, shares: 0.0, usd: 0.0 },
+        });
+        group.items.push(item);
+        group.totals.fills += 1;
+        group.totals.shares += shares;
+        group.totals.usd += usd;
+    }
+
+    /// When the oldest open group closes
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.groups.values().map(|g| g.first_seen + self.window).min()
+    }
+
+    /// Remove and return groups whose window has closed, oldest first, each
+    /// with its fills in arrival order. Fills of a key arriving after its group
+    /// was taken start a new group.
+    pub fn take_due(&mut self, now: Instant) -> Vec<(Vec<T>, Aggregate)> {
+        let due: Vec<AggregateKey> = self.groups.iter()
+            .filter(|(_, g)| now.saturating_duration_since(g.first_seen) >= self.window)
+            .map(|(k, _)| k.clone())
+            .collect();
+
+        let mut out: Vec<(Instant, Vec<T>, Aggregate)> = due.into_iter()
+            .filter_map(|k| self.groups.remove(&k))
+            .map(|g| (g.first_seen, g.items, g.totals))
+            .collect();
+        out.sort_by_key(|(seen, _, _)| *seen);
+        out.into_iter().map(|(_, items, totals)| (items, totals)).collect()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(tx: &str, token: &str) -> AggregateKey {
+        (tx.to_string(), token.to_string())
+    }
+
+    #[test]
+    fn test_merges_fills_of_one_trade() {
+        let t0 = Instant::now();
+        let window = Duration::from_millis(200);
+        let mut agg = FillAggregator::new(window);
+        agg.add(key("0xa", "1"), 100.0, 50.0, "first", t0);
+        agg.add(key("0xa", "1"), 300.0, 165.0, "second", t0 + Duration::from_millis(5));
+        // Different token in the same tx is a separate trade
+        agg.add(key("0xa", "2"), 10.0, 2.0, "other", t0 + Duration::from_millis(10));
+
+        assert!(agg.take_due(t0 + Duration::from_millis(100)).is_empty());
+        assert_eq!(agg.next_deadline(), Some(t0 + window));
+
+        let due = agg.take_due(t0 + window + Duration::from_millis(10));
+        assert_eq!(due.len(), 2);
+        let (items, totals) = &due[0];
+        assert_eq!(items, &vec!["first", "second"]);
+        assert_eq!(totals.fills, 2);
+        assert_eq!(totals.shares, 400.0);
+        assert!((totals.vwap() - 0.5375).abs() < 1e-9);
+        assert_eq!(due[1].0, vec!["other"]);
+        assert!(agg.is_empty());
+    }
+
+    #[test]
+    fn test_zero_window_flushes_immediately() {
+        let t0 = Instant::now();
+        let mut agg = FillAggregator::new(Duration::ZERO);
+        agg.add(key("0xb", "1"), 10.0, 5.0, 1, t0);
+        agg.add(key("0xb", "1"), 10.0, 6.0, 2, t0);
+        let due = agg.take_due(t0);
+        assert_eq!(due, vec![(vec![1, 2], Aggregate { fills: 2, shares: 20.0, usd: 11.0 })]);
+        assert_eq!(agg.next_deadline(), None);
+    }
+}
@@ -0,0 +1,371 @@
+//! USDC and Conditional Token approvals
+//!
+//! Before the CLOB can settle our orders the funder must have approved every
+//! Polymarket spender twice: USDC `approve` (collateral for buys) and
+//! Conditional Tokens `setApprovalForAll` (outcome tokens for sells). The
+//! spenders are the CTF exchange, the neg-risk exchange and the neg-risk
+//! adapter. Approvals are set from the signer EOA directly, or through
+//! `safe::SafeExecutor` when the funder is a Gnosis Safe.
+
+use anyhow::{Result, anyhow};
+use alloy::primitives::{Address, B256, U256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::safe::{SafeCall, SafeExecutor};
+
+pub const USDC_ADDRESS: &str = "0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174";
+pub const CONDITIONAL_TOKENS: &str = "0x4d97dcd97ec945f40cf65f87097ace5ea0476045";
+pub const CTF_EXCHANGE: &str = "0x4bFb41d5B3570DeFd03C39a9A4D8dE6Bd8B8982E";
+pub const NEG_RISK_EXCHANGE: &str = "0xC5d563A36AE78145C45a50134d48A1215220f80a";
+pub const NEG_RISK_ADAPTER: &str = "0xd91E80cF2E7be2e162c6513ceD06f1dD0dA35296";
+
+/// Every contract that must be able to move the funder's USDC and outcome tokens
+pub const REQUIRED_SPENDERS: [(&str, &str); 3] = [
+    ("CTF Exchange", CTF_EXCHANGE),
+    ("Neg Risk Exchange", NEG_RISK_EXCHANGE),
+    ("Neg Risk Adapter", NEG_RISK_ADAPTER),
+];
+
+const USDC_DECIMALS: u32 = 6;
+
+// RPC retry: exponential backoff with ±20% jitter, transient errors only
+const MAX_RETRIES: u32 = 5;
+const INITIAL_RETRY_DELAY_SECS: u64 = 2;
+const MAX_RETRY_DELAY_SECS: u64 = 60;
+
+// Sent transactions are never re-sent; their receipt is polled instead
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(3);
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(180);
+
+sol! {
+    #[sol(rpc)]
+    interface IERC20 {
+        function approve(address spender, uint256 value) external returns (bool);
+        function allowance(address owner, address spender) external view returns (uint256);
+        function balanceOf(address account) external view returns (uint256);
+    }
+
+    #[sol(rpc)]
+    interface IERC1155 {
+        function setApprovalForAll(address operator, bool approved) external;
+        function isApprovedForAll(address account, address operator) external view returns (bool);
+    }
+}
+
+// ============================================================================
+// Status
+// ============================================================================
+
+#[derive(Debug, Clone)]
+pub struct SpenderApproval {
+    pub name: &'static str,
+    pub spender: Address,
+    pub usdc_allowance: U256,
+    pub ctf_approved: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MissingApproval {
+    UsdcAllowance { name: &'static str, spender: Address, allowance: U256, required: U256 },
+    ConditionalTokens { name: &'static str, spender: Address },
+}
+
+impl fmt::Display for MissingApproval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MissingApproval::UsdcAllowance { name, spender, allowance, required } => write!(
+                f,
+                "USDC allowance for {} ({}) is {} USDC, needs at least {} USDC",
+                name, spender, format_usdc(*allowance), format_usdc(*required)
+            ),
+            MissingApproval::ConditionalTokens { name, spender } => write!(
+                f,
+                "Conditional Tokens not approved for {} ({})",
+                name, spender
+            ),
+        }
+    }
+}
+
+/// On-chain approval state of one owner against all required spenders.
+#[derive(Debug, Clone)]
+pub struct ApprovalStatus {
+    pub owner: Address,
+    pub usdc_balance: U256,
+    pub spenders: Vec<SpenderApproval>,
+}
+
+impl ApprovalStatus {
+    pub async fn fetch(rpc_url: &str, owner: Address) -> Result<Self> {
+        let provider = ProviderBuilder::new().connect_http(rpc_url.parse()?);
+        let usdc = IERC20::new(Address::from_str(USDC_ADDRESS)?, provider.clone());
+        let ctf = IERC1155::new(Address::from_str(CONDITIONAL_TOKENS)?, provider);
+
+        let usdc_balance = retry_rpc("USDC balance", || async {
+            Ok(usdc.balanceOf(owner).call().await?)
+        }).await?;
+
+        let mut spenders = Vec::with_capacity(REQUIRED_SPENDERS.len());
+        for (name, address) in REQUIRED_SPENDERS {
+            let spender = Address::from_str(address)?;
+            let usdc_allowance = retry_rpc("USDC allowance", || async {
+                Ok(usdc.allowance(owner, spender).call().await?)
+            }).await?;
+            let ctf_approved = retry_rpc("isApprovedForAll", || async {
+                Ok(ctf.isApprovedForAll(owner, spender).call().await?)
+            }).await?;
+            spenders.push(SpenderApproval { name, spender, usdc_allowance, ctf_approved });
+        }
+
+        Ok(Self { owner, usdc_balance, spenders })
+    }
+
+    /// Allowance each spender needs: enough to spend the whole current balance,
+    /// and never less than `floor_usd` (e.g. the largest order we may place).
+    pub fn required_allowance(&self, floor_usd: f64) -> U256 {
+        let floor = U256::from((floor_usd.max(0.0) * 10f64.powi(USDC_DECIMALS as i32)).ceil() as u64);
+        self.usdc_balance.max(floor).max(U256::from(1u64))
+    }
+
+    pub fn missing(&self, floor_usd: f64) -> Vec<MissingApproval> {
+        let required = self.required_allowance(floor_usd);
+        let mut out = Vec::new();
+        for s in &self.spenders {
+            if s.usdc_allowance < required {
+                out.push(MissingApproval::UsdcAllowance {
+                    name: s.name,
+                    spender: s.spender,
+                    allowance: s.usdc_allowance,
+                    required,
+                });
+            }
+            if !s.ctf_approved {
+                out.push(MissingApproval::ConditionalTokens { name: s.name, spender: s.spender });
+            }
+        }
+        out
+    }
+}
+
+/// USDC balance of `owner` (6 decimals)
+pub async fn usdc_balance(rpc_url: &str, owner: Address) -> Result<U256> {
+    let provider = ProviderBuilder::new().connect_http(rpc_url.parse()?);
+    let usdc = IERC20::new(Address::from_str(USDC_ADDRESS)?, provider);
+    retry_rpc("USDC balance", || async {
+        Ok(usdc.balanceOf(owner).call().await?)
+    }).await
+}
+
+// ============================================================================
+// Setting Approvals
+// ============================================================================
+
+/// Calls that fix `missing`. USDC is approved for `U256::MAX` so the allowance
+/// never has to be topped up.
+pub fn approval_calls(missing: &[MissingApproval]) -> Result<Vec<SafeCall>> {
+    let usdc = Address::from_str(USDC_ADDRESS)?;
+    let ctf = Address::from_str(CONDITIONAL_TOKENS)?;
+    Ok(missing.iter().map(|m| match m {
+        MissingApproval::UsdcAllowance { name, spender, .. } => SafeCall::new(
+            usdc,
+            IERC20::approveCall { spender: *spender, value: U256::MAX }.abi_encode(),
+            format!("USDC approved for {}", name),
+        ),
+        MissingApproval::ConditionalTokens { name, spender } => SafeCall::new(
+            ctf,
+            IERC1155::setApprovalForAllCall { operator: *spender, approved: true }.abi_encode(),
+            format!("Conditional Tokens approved for {}", name),
+        ),
+    }).collect())
+}
+
+/// Submit `calls` as `owner`: sent directly when the owner is the signer,
+/// otherwise executed through the owner Safe (`batch` uses one MultiSend
+/// transaction). Returns transaction hashes in submission order.
+pub async fn apply_approvals(
+    signer: &PrivateKeySigner,
+    owner: Address,
+    rpc_url: &str,
+    calls: &[SafeCall],
+    batch: bool,
+) -> Result<Vec<B256>> {
+    if calls.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if owner != signer.address() {
+        let executor = SafeExecutor::new(owner, signer.clone(), rpc_url);
+        executor.check_owner().await?;
+        return executor.execute(calls, batch).await;
+    }
+
+    let provider = ProviderBuilder::new()
+        .wallet(signer.clone())
+        .connect_http(rpc_url.parse()?);
+    let usdc = IERC20::new(Address::from_str(USDC_ADDRESS)?, provider.clone());
+    let ctf = IERC1155::new(Address::from_str(CONDITIONAL_TOKENS)?, provider);
+
+    let mut hashes = Vec::with_capacity(calls.len());
+    for call in calls {
+        let pending = if call.to == *usdc.address() {
+            let args = IERC20::approveCall::abi_decode(&call.data)?;
+            usdc.approve(args.spender, args.value).send().await
+        } else {
+            let args = IERC1155::setApprovalForAllCall::abi_decode(&call.data)?;
+            ctf.setApprovalForAll(args.operator, args.approved).send().await
+        }
+        .map_err(|e| anyhow!("{} failed: {}", call.description, e))?;
+        hashes.push(wait_for_receipt(rpc_url, *pending.tx_hash(), &call.description).await?);
+    }
+    Ok(hashes)
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+fn is_transient(error: &str) -> bool {
+    let e = error.to_lowercase();
+    e.contains("rate limit") || e.contains("too many requests") || e.contains("-32090")
+        || e.contains("429") || e.contains("timeout") || e.contains("timed out")
+        || e.contains("connection") || e.contains("reset")
+}
+
+/// Retry transient RPC failures with exponential backoff and jitter. Anything
+/// else (reverts, bad input) fails immediately. Only for reads: a send that
+/// timed out may still have been broadcast.
+pub(crate) async fn retry_rpc<T, F, Fut>(description: &str, mut f: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut delay = INITIAL_RETRY_DELAY_SECS;
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Ok(v) => return Ok(v),
+            Err(e) if attempt < MAX_RETRIES && is_transient(&e.to_string()) => {
+                let jitter = (delay as f64 * 0.2) * (rand::random::<f64>() * 2.0 - 1.0);
+                let wait = (delay as f64 + jitter).max(1.0);
+                eprintln!(
+                    "   ⏳ {} failed ({}), retry {}/{} in {:.0}s",
+                    description, e, attempt + 1, MAX_RETRIES, wait
+                );
+                tokio::time::sleep(Duration::from_secs_f64(wait)).await;
+                delay = (delay * 2).min(MAX_RETRY_DELAY_SECS);
+                attempt += 1;
+            }
+            Err(e) => return Err(anyhow!("{} failed: {}", description, e)),
+        }
+    }
+}
+
+/// Wait until `hash` is mined by polling its receipt. A timed-out wait never
+/// re-sends the transaction.
+pub(crate) async fn wait_for_receipt(rpc_url: &str, hash: B256, description: &str) -> Result<B256> {
+    let provider = ProviderBuilder::new().connect_http(rpc_url.parse()?);
+    let deadline = tokio::time::Instant::now() + RECEIPT_TIMEOUT;
+    loop {
+        let receipt = retry_rpc("transaction receipt", || async {
+            Ok(provider.get_transaction_receipt(hash).await?)
+        }).await?;
+        if let Some(receipt) = receipt {
+            if !receipt.status() {
+                return Err(anyhow!("{} reverted in {}", description, hash));
+            }
+            return Ok(hash);
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Err(anyhow!(
+                "{} not mined after {}s - check {} before retrying",
+                description, RECEIPT_TIMEOUT.as_secs(), hash
+            ));
+        }
+        tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+    }
+}
+
+pub fn format_usdc(value: U256) -> String {
+    if value == U256::MAX {
+        return "unlimited".to_string();
+    }
+    let divisor = U256::from(10u64.pow(USDC_DECIMALS));
+    let whole = value / divisor;
+    let remainder = format!("{:0>width$}", value % divisor, width = USDC_DECIMALS as usize);
+    let trimmed = remainder.trim_end_matches('0');
+    if trimmed.is_empty() {
+        format!("{}", whole)
+    } else {
+        format!("{}.{}", whole, trimmed)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(balance_usdc: u64, allowances: [u64; 3], approved: [bool; 3]) -> ApprovalStatus {
+        ApprovalStatus {
+            owner: Address::ZERO,
+            usdc_balance: U256::from(balance_usdc * 1_000_000),
+            spenders: REQUIRED_SPENDERS.into_iter().enumerate().map(|(i, (name, addr))| SpenderApproval {
+                name,
+                spender: Address::from_str(addr).unwrap(),
+                usdc_allowance: U256::from(allowances[i] * 1_000_000),
+                ctf_approved: approved[i],
+            }).collect(),
+        }
+    }
+
+    #[test]
+    fn test_allowance_must_cover_balance_and_floor() {
+        let s = status(500, [1_000, 400, 1_000], [true; 3]);
+        let missing = s.missing(100.0);
+        assert_eq!(missing.len(), 1);
+        assert!(matches!(missing[0], MissingApproval::UsdcAllowance { name: "Neg Risk Exchange", .. }));
+
+        // Floor above the balance wins
+        assert_eq!(s.missing(2_000.0).len(), 3);
+    }
+
+    #[test]
+    fn test_zero_allowance_missing_even_with_empty_wallet() {
+        let s = status(0, [0, 0, 0], [true, true, false]);
+        let missing = s.missing(0.0);
+        assert_eq!(missing.len(), 4);
+        assert_eq!(
+            missing[3].to_string(),
+            format!("Conditional Tokens not approved for Neg Risk Adapter ({})", Address::from_str(NEG_RISK_ADAPTER).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_approval_calls_target_token_contracts() {
+        let s = status(10, [0, 1_000, 1_000], [false, true, true]);
+        let calls = approval_calls(&s.missing(0.0)).unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].to, Address::from_str(USDC_ADDRESS).unwrap());
+        assert_eq!(calls[1].to, Address::from_str(CONDITIONAL_TOKENS).unwrap());
+        let decoded = IERC1155::setApprovalForAllCall::abi_decode(&calls[1].data).unwrap();
+        assert_eq!(decoded.operator, Address::from_str(CTF_EXCHANGE).unwrap());
+        assert!(decoded.approved);
+    }
+
+    #[test]
+    fn test_format_usdc() {
+        assert_eq!(format_usdc(U256::from(1_500_000u64)), "1.5");
+        assert_eq!(format_usdc(U256::from(2_000_000u64)), "2");
+        assert_eq!(format_usdc(U256::MAX), "unlimited");
+    }
+}
@@ -0,0 +1,547 @@
+//! Live-vs-simulated copy trading audit
+//!
+//! Replays every whale event recorded in `matches_optimized.csv` through the bot's
+//! sizing rules and compares the result with what the order worker actually
+//! reported in the `order_status` column. Each event gets a [`Finding`], and the
+//! run ends with an [`AuditSummary`] that breaks down where money leaked.
+//!
+//! Sizing goes through [`crate::sizing`], the same code as the order worker.
+//! The risk guard is stateful and time-based, so it is not replayed: a
+//! `RISK_BLOCKED` row is reported as a deliberate miss rather than a mismatch.
+
+use anyhow::{Result, anyhow};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::market_category;
+use crate::settings::Config;
+use crate::sizing::{self, WhaleFill};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// `handle_event` sleeps this long after the order reply before writing the CSV
+/// row, so it is subtracted from the row timestamp when measuring latency.
+pub const POST_TRADE_LOG_DELAY_MS: i64 = 2_800;
+
+/// Relative difference between simulated and filled shares tolerated before an
+/// event is flagged as a size mismatch.
+pub const SIZE_TOLERANCE: f64 = 0.05;
+
+const CSV_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+const BLOCK_BATCH_SIZE: usize = 50;
+
+// ============================================================================
+// CSV Input
+// ============================================================================
+
+/// One row of `matches_optimized.csv` (columns the audit does not need are ignored).
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditRow {
+    pub timestamp: String,
+    pub block: u64,
+    pub clob_asset_id: String,
+    pub usd_value: f64,
+    pub shares: f64,
+    pub price_per_share: f64,
+    pub direction: String,
+    pub order_status: String,
+    #[serde(default)]
+    pub tx_hash: String,
+    #[serde(default)]
+    pub is_live: bool,
+    /// `MAKER` or `TAKER`; empty in logs written before the column existed
+    #[serde(default)]
+    pub whale_role: String,
+}
+
+impl AuditRow {
+    #[inline]
+    pub fn side_is_buy(&self) -> bool {
+        self.direction.starts_with("BUY")
+    }
+
+    #[inline]
+    pub fn whale_is_maker(&self) -> bool {
+        self.whale_role == "MAKER"
+    }
+
+    /// Row timestamp in unix milliseconds (the CSV is written in UTC).
+    pub fn logged_at_ms(&self) -> Option<i64> {
+        NaiveDateTime::parse_from_str(self.timestamp.trim(), CSV_TIMESTAMP_FORMAT)
+            .ok()
+            .map(|t| t.and_utc().timestamp_millis())
+    }
+}
+
+/// Load all parseable rows. Returns the rows and the number of malformed lines skipped.
+pub fn load_rows(path: &Path) -> Result<(Vec<AuditRow>, usize)> {
+    if !path.exists() {
+        return Err(anyhow!("{} not found - run the bot first to record trades", path.display()));
+    }
+    // Rows gained columns over time; older logs keep their shorter header
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+    let mut rows = Vec::new();
+    let mut skipped = 0;
+    for result in reader.deserialize::<AuditRow>() {
+        match result {
+            Ok(row) => rows.push(row),
+            Err(_) => skipped += 1,
+        }
+    }
+    Ok((rows, skipped))
+}
+
+// ============================================================================
+// Actual Outcome (parsed from order_status)
+// ============================================================================
+
+/// What the order worker reported for an event.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActualOutcome {
+    /// `200 OK [...] | filled/requested filled @ price | ...`
+    Filled { filled: f64, requested: f64, fill_price: f64, resubmitted: bool },
+    /// `FAILED [...] | ...` - order reached the CLOB but was rejected
+    Rejected { requested: f64, reason: String },
+    RiskBlocked(String),
+    RiskBookFail(String),
+    SkippedBelowMin,
+    SkippedInvalidSize,
+    /// `SKIPPED_LATE (Ns old)` - recovered by backfill after a reconnect, past the staleness cutoff
+    SkippedLate(u64),
+    /// `SKIPPED_PASSIVE` - whale was the resting maker and `PASSIVE_FILLS=IGNORE`
+    SkippedPassive,
+    /// `SKIPPED_CATEGORY:<name>_<LIVE|NOT_LIVE>` - category rules exclude the market's live state
+    SkippedCategory(String),
+    /// `SKIPPED_SLIPPAGE:<reason>` - book moved away from the whale or was too thin
+    SkippedSlippage(String),
+    /// `SKIPPED_DISABLED` / `MOCK_ONLY` - the bot was not trading, nothing to audit.
+    /// Also `REORGED` / `REORG_UNWIND` rows, which follow up an earlier event.
+    NotTrading,
+    ExecFail(String),
+    /// `QUEUE_ERR`, `WORKER_TIMEOUT`, `WORKER_DROPPED`
+    Pipeline(String),
+    Unknown(String),
+}
+
+impl ActualOutcome {
+    pub fn filled_shares(&self) -> f64 {
+        match self {
+            ActualOutcome::Filled { filled, .. } => *filled,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Remove ANSI color codes written by the console formatter.
+pub fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip "[...m"
+            for n in chars.by_ref() {
+                if n.is_ascii_alphabetic() { break; }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Parse the `filled/requested filled @ price` segment of an executed status.
+fn parse_fill_segment(status: &str) -> Option<(f64, f64, f64)> {
+    let seg = status.split(" | ").find(|p| p.contains(" filled @ "))?;
+    let (amounts, price) = seg.split_once(" filled @ ")?;
+    let (filled, requested) = amounts.trim().split_once('/')?;
+    Some((
+        filled.trim().parse().ok()?,
+        requested.trim().parse().ok()?,
+        price.trim().parse().ok()?,
+    ))
+}
+
+pub fn parse_order_status(raw: &str) -> ActualOutcome {
+    let status = strip_ansi(raw);
+    let status = status.trim();
+
+    if status.starts_with("200 OK") {
+        return match parse_fill_segment(status) {
+            Some((filled, requested, fill_price)) => ActualOutcome::Filled {
+                filled,
+                requested,
+                fill_price,
+                resubmitted: status.contains("UNDERFILL"),
+            },
+            None => ActualOutcome::Unknown(status.to_string()),
+        };
+    }
+    if status.starts_with("FAILED") {
+        let requested = parse_fill_segment(status).map(|(_, r, _)| r).unwrap_or(0.0);
+        let reason = status.rsplit(" | ").next().unwrap_or(status).to_string();
+        return ActualOutcome::Rejected { requested, reason };
+    }
+    if let Some(reason) = status.strip_prefix("RISK_BLOCKED:") {
+        return ActualOutcome::RiskBlocked(reason.trim().to_string());
+    }
+    if let Some(reason) = status.strip_prefix("RISK_BOOK_FAIL:") {
+        return ActualOutcome::RiskBookFail(reason.trim().to_string());
+    }
+    if status.starts_with("SKIPPED_BELOW_MIN") {
+        return ActualOutcome::SkippedBelowMin;
+    }
+    if status.starts_with("SKIPPED_INVALID_SIZE") {
+        return ActualOutcome::SkippedInvalidSize;
+    }
+    if let Some(rest) = status.strip_prefix("SKIPPED_LATE") {
+        let age = rest.trim().trim_start_matches('(').split('s').next().and_then(|s| s.parse().ok()).unwrap_or(0);
+        return ActualOutcome::SkippedLate(age);
+    }
+    if status.starts_with("SKIPPED_PASSIVE") {
+        return ActualOutcome::SkippedPassive;
+    }
+    if let Some(reason) = status.strip_prefix("SKIPPED_CATEGORY:") {
+        return ActualOutcome::SkippedCategory(reason.trim().to_string());
+    }
+    if let Some(reason) = status.strip_prefix("SKIPPED_SLIPPAGE:") {
+        return ActualOutcome::SkippedSlippage(reason.trim().to_string());
+    }
+    if status.starts_with("SKIPPED_DISABLED") || status.starts_with("MOCK_ONLY") || status.starts_with("REORG") {
+        return ActualOutcome::NotTrading;
+    }
+    if let Some(reason) = status.strip_prefix("EXEC_FAIL:") {
+        return ActualOutcome::ExecFail(reason.trim().to_string());
+    }
+    if status.starts_with("QUEUE_ERR") || status.starts_with("WORKER_") {
+        return ActualOutcome::Pipeline(status.to_string());
+    }
+    ActualOutcome::Unknown(status.to_string())
+}
+
+// ============================================================================
+// Simulated Outcome (bot sizing rules replayed)
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SimulatedOrder {
+    Trade { shares: f64, limit_price: f64 },
+    /// The bot would not trade; the status it would have reported
+    Skip(String),
+}
+
+/// Size a copy order the way `process_order` in the main bot does, without the
+/// risk guard, slippage check and portfolio limits.
+pub fn simulate_order(config: &Config, row: &AuditRow) -> SimulatedOrder {
+    let fill = WhaleFill {
+        token_id: &row.clob_asset_id,
+        side_is_buy: row.side_is_buy(),
+        shares: row.shares,
+        price: row.price_per_share,
+        passive: row.whale_is_maker(),
+        is_live: Some(row.is_live),
+    };
+    let category = market_category::category_for_token(&row.clob_asset_id);
+    let sized = sizing::order_terms(config, category, &fill).and_then(|terms| {
+        sizing::copy_shares(config, category, &fill, terms.limit_price).map(|shares| (shares, terms.limit_price))
+    });
+    match sized {
+        Ok((shares, limit_price)) => SimulatedOrder::Trade { shares, limit_price },
+        Err(status) => SimulatedOrder::Skip(status),
+    }
+}
+
+// ============================================================================
+// Per-Event Diff
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Finding {
+    /// Simulation and live agree (traded the expected size, or both skipped)
+    Match,
+    /// Simulation traded, live did not; the string is the live cause
+    Missed(String),
+    /// Both traded but filled size differs from simulated size beyond SIZE_TOLERANCE
+    SizeMismatch,
+    /// Live traded although the simulation would have skipped
+    Unexpected,
+    /// Bot was not trading (disabled / mock) - excluded from totals
+    Excluded,
+}
+
+impl Finding {
+    pub fn label(&self) -> &str {
+        match self {
+            Finding::Match => "OK",
+            Finding::Missed(_) => "MISSED",
+            Finding::SizeMismatch => "SIZE",
+            Finding::Unexpected => "UNEXPECTED",
+            Finding::Excluded => "EXCLUDED",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EventAudit {
+    pub row: AuditRow,
+    pub actual: ActualOutcome,
+    pub simulated: SimulatedOrder,
+    pub finding: Finding,
+    /// Filled minus simulated shares (negative = under-sized)
+    pub size_gap_shares: f64,
+    /// Per-share price paid vs the whale, positive = worse than the whale
+    pub slippage_per_share: f64,
+    /// slippage_per_share * filled shares
+    pub slippage_usd: f64,
+    /// Whale block timestamp to our order reply, when the block time is known
+    pub latency_ms: Option<i64>,
+}
+
+fn missed_cause(actual: &ActualOutcome) -> String {
+    match actual {
+        ActualOutcome::RiskBlocked(r) => format!("risk guard ({})", r),
+        ActualOutcome::RiskBookFail(_) => "book fetch failed".to_string(),
+        ActualOutcome::SkippedBelowMin => "below minimum (live)".to_string(),
+        ActualOutcome::SkippedInvalidSize => "invalid size".to_string(),
+        ActualOutcome::SkippedLate(age) => format!("seen late after reconnect ({}s)", age),
+        ActualOutcome::SkippedPassive => "passive fill ignored".to_string(),
+        ActualOutcome::SkippedSlippage(r) => format!("slippage guard ({})", r),
+        ActualOutcome::SkippedCategory(r) => format!("category rule ({})", r),
+        ActualOutcome::Rejected { .. } => "rejected by CLOB".to_string(),
+        ActualOutcome::ExecFail(r) if r.contains("INSUFFICIENT_BALANCE") => "insufficient balance/allowance".to_string(),
+        ActualOutcome::ExecFail(_) => "execution error".to_string(),
+        ActualOutcome::Pipeline(_) => "queue/worker timeout".to_string(),
+        ActualOutcome::Filled { .. } => "zero fill".to_string(),
+        ActualOutcome::NotTrading | ActualOutcome::Unknown(_) => "unknown status".to_string(),
+    }
+}
+
+/// Diff one event. `block_ts` is the whale block timestamp in unix seconds.
+pub fn audit_event(row: AuditRow, simulated: SimulatedOrder, block_ts: Option<i64>) -> EventAudit {
+    let actual = parse_order_status(&row.order_status);
+    let filled = actual.filled_shares();
+
+    let finding = match (&actual, &simulated) {
+        (ActualOutcome::NotTrading, _) => Finding::Excluded,
+        (_, SimulatedOrder::Skip(_)) if filled > 0.0 => Finding::Unexpected,
+        (_, SimulatedOrder::Skip(_)) => Finding::Match,
+        (_, SimulatedOrder::Trade { .. }) if filled <= 0.0 => Finding::Missed(missed_cause(&actual)),
+        (_, SimulatedOrder::Trade { shares, .. }) => {
+            if ((filled - shares) / shares).abs() > SIZE_TOLERANCE {
+                Finding::SizeMismatch
+            } else {
+                Finding::Match
+            }
+        }
+    };
+
+    let sim_shares = match &simulated {
+        SimulatedOrder::Trade { shares, .. } => *shares,
+        SimulatedOrder::Skip(_) => 0.0,
+    };
+    let size_gap_shares = if finding == Finding::Excluded { 0.0 } else { filled - sim_shares };
+
+    let slippage_per_share = match &actual {
+        ActualOutcome::Filled { fill_price, filled, .. } if *filled > 0.0 => {
+            if row.side_is_buy() { *fill_price - row.price_per_share } else { row.price_per_share - *fill_price }
+        }
+        _ => 0.0,
+    };
+
+    let latency_ms = match (block_ts, row.logged_at_ms()) {
+        (Some(b), Some(logged)) if finding != Finding::Excluded => Some(logged - POST_TRADE_LOG_DELAY_MS - b * 1000),
+        _ => None,
+    };
+
+    EventAudit {
+        slippage_usd: slippage_per_share * filled,
+        row,
+        actual,
+        simulated,
+        finding,
+        size_gap_shares,
+        slippage_per_share,
+        latency_ms,
+    }
+}
+
+// ============================================================================
+// Summary
+// ============================================================================
+
+#[derive(Debug, Default)]
+pub struct AuditSummary {
+    pub events: usize,
+    pub excluded: usize,
+    pub matched: usize,
+    pub missed: usize,
+    pub size_mismatches: usize,
+    pub unexpected: usize,
+    /// Simulated notional that was never traded, by live cause
+    pub missed_usd_by_cause: HashMap<String, f64>,
+    /// Notional we fell short of the simulated size on partial fills
+    pub undersized_usd: f64,
+    /// Notional traded beyond the simulated size (including unexpected trades)
+    pub oversized_usd: f64,
+    /// Total price paid vs the whale across fills (positive = leak)
+    pub slippage_usd: f64,
+    pub latencies_ms: Vec<i64>,
+}
+
+impl AuditSummary {
+    pub fn from_events(events: &[EventAudit]) -> Self {
+        let mut s = AuditSummary { events: events.len(), ..Default::default() };
+        for e in events {
+            let price = e.row.price_per_share;
+            match &e.finding {
+                Finding::Excluded => { s.excluded += 1; continue; }
+                Finding::Match => s.matched += 1,
+                Finding::Missed(cause) => {
+                    s.missed += 1;
+                    *s.missed_usd_by_cause.entry(cause.clone()).or_insert(0.0) += -e.size_gap_shares * price;
+                }
+                Finding::SizeMismatch => s.size_mismatches += 1,
+                Finding::Unexpected => s.unexpected += 1,
+            }
+            if !matches!(e.finding, Finding::Missed(_)) {
+                if e.size_gap_shares < 0.0 {
+                    s.undersized_usd += -e.size_gap_shares * price;
+                } else {
+                    s.oversized_usd += e.size_gap_shares * price;
+                }
+            }
+            s.slippage_usd += e.slippage_usd;
+            if let Some(l) = e.latency_ms {
+                s.latencies_ms.push(l);
+            }
+        }
+        s.latencies_ms.sort_unstable();
+        s
+    }
+
+    pub fn missed_usd(&self) -> f64 {
+        self.missed_usd_by_cause.values().sum()
+    }
+
+    /// Latency percentile (0.0..=1.0) in milliseconds
+    pub fn latency_percentile(&self, p: f64) -> Option<i64> {
+        if self.latencies_ms.is_empty() { return None; }
+        let idx = ((self.latencies_ms.len() - 1) as f64 * p).round() as usize;
+        self.latencies_ms.get(idx).copied()
+    }
+}
+
+// ============================================================================
+// Block Timestamps
+// ============================================================================
+
+/// Fetch block timestamps (unix seconds) via batched `eth_getBlockByNumber`.
+/// Blocks that fail to resolve are simply absent from the map.
+pub async fn fetch_block_timestamps(client: &reqwest::Client, rpc_url: &str, blocks: &[u64]) -> HashMap<u64, i64> {
+    let mut out = HashMap::with_capacity(blocks.len());
+    for chunk in blocks.chunks(BLOCK_BATCH_SIZE) {
+        let batch: Vec<serde_json::Value> = chunk.iter().enumerate().map(|(i, b)| serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "eth_getBlockByNumber",
+            "params": [format!("0x{:x}", b), false],
+            "id": i
+        })).collect();
+
+        let Ok(resp) = client.post(rpc_url).json(&batch).send().await else { continue };
+        let Ok(results) = resp.json::<Vec<serde_json::Value>>().await else { continue };
+        for r in results {
+            let block = &r["result"];
+            let number = block["number"].as_str().and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok());
+            let ts = block["timestamp"].as_str().and_then(|s| i64::from_str_radix(s.trim_start_matches("0x"), 16).ok());
+            if let (Some(n), Some(t)) = (number, ts) {
+                out.insert(n, t);
+            }
+        }
+    }
+    out
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(direction: &str, price: f64, status: &str) -> AuditRow {
+        AuditRow {
+            timestamp: "2025-01-01 00:00:10.000".into(),
+            block: 1,
+            clob_asset_id: "123".into(),
+            usd_value: 100.0 * price,
+            shares: 100.0,
+            price_per_share: price,
+            direction: direction.into(),
+            order_status: status.into(),
+            tx_hash: "0xabc".into(),
+            is_live: false,
+            whale_role: "TAKER".into(),
+        }
+    }
+
+    #[test]
+    fn test_parse_filled_status_with_colors() {
+        let raw = "200 OK [FIXED($1.00)] | \x1b[32m10.00/10.00\x1b[0m filled @ \x1b[38;5;213m0.52\x1b[0m | \x1b[31mwhale 100.0\x1b[0m @ 0.50";
+        assert_eq!(
+            parse_order_status(raw),
+            ActualOutcome::Filled { filled: 10.0, requested: 10.0, fill_price: 0.52, resubmitted: false }
+        );
+    }
+
+    #[test]
+    fn test_parse_non_fill_statuses() {
+        assert_eq!(parse_order_status("RISK_BLOCKED:SEQ_LARGE"), ActualOutcome::RiskBlocked("SEQ_LARGE".into()));
+        assert_eq!(parse_order_status("SKIPPED_BELOW_MIN ($0.50 < $1.00)"), ActualOutcome::SkippedBelowMin);
+        assert_eq!(parse_order_status("MOCK_ONLY"), ActualOutcome::NotTrading);
+        assert_eq!(parse_order_status("SKIPPED_LATE (45s old)"), ActualOutcome::SkippedLate(45));
+        assert_eq!(parse_order_status("SKIPPED_PASSIVE"), ActualOutcome::SkippedPassive);
+        assert_eq!(parse_order_status("SKIPPED_SLIPPAGE:PRICE_MOVED"), ActualOutcome::SkippedSlippage("PRICE_MOVED".into()));
+        assert_eq!(parse_order_status("WORKER_TIMEOUT"), ActualOutcome::Pipeline("WORKER_TIMEOUT".into()));
+        assert!(matches!(parse_order_status("EXEC_FAIL: boom"), ActualOutcome::ExecFail(_)));
+        assert!(matches!(
+            parse_order_status("FAILED [FIXED($1.00)] | 0.00/5.00 filled @ 0.40 | whale 10.0 @ 0.40 | no match"),
+            ActualOutcome::Rejected { requested, .. } if requested == 5.0
+        ));
+    }
+
+    #[test]
+    fn test_audit_event_slippage_and_latency() {
+        let r = row("BUY_FILL", 0.50, "200 OK [x] | 10.00/10.00 filled @ 0.52 | whale 100.0 @ 0.50");
+        let sim = SimulatedOrder::Trade { shares: 10.0, limit_price: 0.51 };
+        let block_ts = r.logged_at_ms().unwrap() / 1000 - 5;
+        let e = audit_event(r, sim, Some(block_ts));
+        assert_eq!(e.finding, Finding::Match);
+        assert!((e.slippage_per_share - 0.02).abs() < 1e-9);
+        assert!((e.slippage_usd - 0.2).abs() < 1e-9);
+        assert_eq!(e.latency_ms, Some(5_000 - POST_TRADE_LOG_DELAY_MS));
+    }
+
+    #[test]
+    fn test_audit_event_findings() {
+        let sim = SimulatedOrder::Trade { shares: 10.0, limit_price: 0.51 };
+        let missed = audit_event(row("BUY_FILL", 0.5, "RISK_BLOCKED:DEPTH"), sim.clone(), None);
+        assert_eq!(missed.finding, Finding::Missed("risk guard (DEPTH)".into()));
+
+        let under = audit_event(row("BUY_FILL", 0.5, "200 OK [x] | 4.00/10.00 filled @ 0.50 | whale 1.0 @ 0.50"), sim, None);
+        assert_eq!(under.finding, Finding::SizeMismatch);
+        assert!((under.size_gap_shares + 6.0).abs() < 1e-9);
+
+        let unexpected = audit_event(row("SELL_FILL", 0.5, "200 OK [x] | 4.00/4.00 filled @ 0.50 | whale 1.0 @ 0.50"), SimulatedOrder::Skip("SKIPPED_BELOW_MIN".into()), None);
+        assert_eq!(unexpected.finding, Finding::Unexpected);
+
+        let summary = AuditSummary::from_events(&[missed, under, unexpected]);
+        assert_eq!(summary.missed, 1);
+        assert!((summary.missed_usd() - 5.0).abs() < 1e-9);
+        assert!((summary.undersized_usd - 3.0).abs() < 1e-9);
+        assert!((summary.oversized_usd - 2.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,128 @@
+//! Gap backfill after WebSocket reconnects
+//!
+//! A log subscription only delivers logs emitted while it is connected. After a
+//! reconnect the bot asks `eth_getLogs` for the blocks it may have missed, with
+//! the same `OrdersFilled` / whale topic filter as the live subscription, and
+//! feeds the recovered events back through the normal pipeline marked as late.
+//! Whether a late event is still copied is decided by its age against
+//! `BACKFILL_MAX_AGE_SECS`.
+
+use anyhow::Result;
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::research::rpc_call;
+use crate::settings::{MONITORED_ADDRESSES, ORDERS_FILLED_EVENT_SIGNATURE, TARGET_TOPIC_HEX};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Approximate Polygon block time, used to turn durations into block counts
+pub const POLYGON_BLOCK_TIME_SECS: u64 = 2;
+/// Largest gap that is backfilled (~1h). Older blocks are far past any
+/// sensible staleness cutoff, so only the most recent part is fetched.
+pub const MAX_BACKFILL_BLOCKS: u64 = 1_800;
+const LOG_CHUNK_BLOCKS: u64 = 500;
+const MIN_LOG_CHUNK_BLOCKS: u64 = 10;
+
+// ============================================================================
+// Block Ranges
+// ============================================================================
+
+/// Block range to backfill when everything up to `from - 1` is known to be
+/// covered and the chain head is `head`. Returns None when there is no gap.
+/// Gaps longer than `MAX_BACKFILL_BLOCKS` are trimmed to the newest blocks.
+pub fn gap_range(from: u64, head: u64) -> Option<(u64, u64)> {
+    if from > head {
+        return None;
+    }
+    let start = from.max(head.saturating_sub(MAX_BACKFILL_BLOCKS - 1));
+    Some((start, head))
+}
+
+/// Last block a dropped session can be trusted to have delivered. A dead
+/// connection is only noticed after `ping_timeout` without messages, so the
+/// blocks in that window are treated as missed.
+pub fn covered_before_disconnect(head: u64, ping_timeout: Duration) -> u64 {
+    head.saturating_sub(ping_timeout.as_secs().div_ceil(POLYGON_BLOCK_TIME_SECS))
+}
+
+/// Age of a block in seconds, from its timestamp when known, otherwise
+/// estimated from its distance to `head`.
+pub fn block_age_secs(block: u64, head: u64, block_ts: Option<i64>, now_ts: i64) -> u64 {
+    match block_ts {
+        Some(ts) => (now_ts - ts).max(0) as u64,
+        None => head.saturating_sub(block) * POLYGON_BLOCK_TIME_SECS,
+    }
+}
+
+// ============================================================================
+// Log Fetching
+// ============================================================================
+
+/// Fetch the whale's raw `OrdersFilled` logs in `[from, to]`, in chain order.
+/// Entries have the same shape as the `result` of a logs subscription message.
+pub async fn fetch_whale_logs(client: &reqwest::Client, rpc_url: &str, from: u64, to: u64) -> Result<Vec<Value>> {
+    let mut logs = Vec::new();
+    let mut start = from;
+    let mut chunk = LOG_CHUNK_BLOCKS;
+
+    while start <= to {
+        let end = (start + chunk - 1).min(to);
+        let params = serde_json::json!([{
+            "fromBlock": format!("0x{:x}", start),
+            "toBlock": format!("0x{:x}", end),
+            "address": MONITORED_ADDRESSES,
+            "topics": [[ORDERS_FILLED_EVENT_SIGNATURE], Value::Null, TARGET_TOPIC_HEX.as_str()],
+        }]);
+
+        match rpc_call(client, rpc_url, "eth_getLogs", params).await {
+            Ok(result) => {
+                if let Value::Array(entries) = result {
+                    logs.extend(entries);
+                }
+                start = end + 1;
+                chunk = LOG_CHUNK_BLOCKS;
+            }
+            Err(_) if chunk > MIN_LOG_CHUNK_BLOCKS => {
+                // Provider result caps - retry with a smaller range
+                chunk /= 2;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(logs)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gap_range() {
+        assert_eq!(gap_range(101, 100), None);
+        assert_eq!(gap_range(100, 100), Some((100, 100)));
+        assert_eq!(gap_range(90, 100), Some((90, 100)));
+        let head = 1_000_000;
+        assert_eq!(gap_range(1, head), Some((head - MAX_BACKFILL_BLOCKS + 1, head)));
+    }
+
+    #[test]
+    fn test_covered_before_disconnect() {
+        assert_eq!(covered_before_disconnect(1_000, Duration::from_secs(300)), 850);
+        assert_eq!(covered_before_disconnect(1_000, Duration::from_secs(3)), 998);
+        assert_eq!(covered_before_disconnect(10, Duration::from_secs(300)), 0);
+    }
+
+    #[test]
+    fn test_block_age() {
+        assert_eq!(block_age_secs(90, 100, Some(1_000), 1_045), 45);
+        assert_eq!(block_age_secs(90, 100, None, 1_045), 20);
+        assert_eq!(block_age_secs(100, 100, Some(2_000), 1_000), 0);
+    }
+}
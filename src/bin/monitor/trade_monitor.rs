@@ -18,6 +18,7 @@ use std::io::Write;
 use std::path::Path;
 use std::time::Duration;
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use pm_whale_follower::preflight::{self, Section};
 
 // --- Constants ---
 const ORDERS_FILLED_SIG: &str =
@@ -93,6 +94,9 @@ async fn main() -> Result<()> {
     dotenv().ok();
     ensure_csv()?;
 
+    // Fills come from dRPC, so only the CLOB (used for book snapshots) is checked
+    preflight::require(&[Section::Clob]).await?;
+
     let drpc_key = env::var("DRPC_API_KEY")
         .context("DRPC_API_KEY env var is required")?;
 
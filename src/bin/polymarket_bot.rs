@@ -15,6 +15,9 @@ use alloy::providers::ProviderBuilder;
 use alloy::signers::local::PrivateKeySigner;
 use alloy::sol;
 use serde::Deserialize;
+use pm_whale_follower::preflight::{self, PreflightOptions};
+use pm_whale_follower::providers;
+use pm_whale_follower::kill_switch;
 use pm_whale_follower::settings::{Config, CopyStrategy};
 
 #[derive(Parser)]
@@ -64,7 +67,11 @@ enum SetupCommand {
     /// Interactive setup wizard for configuration
     Setup,
     /// Validate configuration, check balances, and test connectivity
-    SystemStatus,
+    SystemStatus {
+        /// Print the preflight report as JSON (for scripts)
+        #[arg(long)]
+        json: bool,
+    },
     /// Print all available commands and usage
     Help,
 }
@@ -73,17 +80,19 @@ enum SetupCommand {
 enum MainCommand {
     /// Start the copy trading bot main loop
     Run,
+    /// Lift a kill switch halt so the bot places buys again
+    ClearHalt,
 }
 
 #[derive(Subcommand)]
 enum WalletCommand {
     /// Check proxy wallet (Gnosis Safe) balance and positions
     CheckProxyWallet,
-    /// Compare two wallet addresses
+    /// Compare two wallet addresses (e.g. our funder vs the whale we copy)
     CheckBothWallets {
-        /// First wallet address
+        /// First wallet address (usually ours)
         address1: String,
-        /// Second wallet address
+        /// Second wallet address (usually the whale)
         address2: String,
     },
     /// View comprehensive wallet statistics
@@ -99,7 +108,11 @@ enum WalletCommand {
     /// Check and set token allowance
     CheckAllowance,
     /// Set ERC1155 token allowance
-    SetTokenAllowance,
+    SetTokenAllowance {
+        /// Batch the Safe approvals into a single MultiSend transaction
+        #[arg(long)]
+        multisend: bool,
+    },
     /// Find and analyze EOA wallet
     FindMyEoa,
     /// Find Gnosis Safe proxy wallet
@@ -130,13 +143,82 @@ enum PositionCommand {
 #[derive(Subcommand)]
 enum ResearchCommand {
     /// Find best performing traders
-    FindBestTraders,
+    FindBestTraders {
+        /// Number of recent blocks to scan (~2s per block on Polygon)
+        #[arg(long, default_value_t = 5000)]
+        blocks: u64,
+        /// Number of traders to show
+        #[arg(long, default_value_t = 25)]
+        top: usize,
+        /// Minimum number of fills for a trader to be ranked
+        #[arg(long, default_value_t = 5)]
+        min_trades: usize,
+        /// Sort key: pnl, roi, winrate, volume
+        #[arg(long, default_value = "pnl")]
+        sort: String,
+        /// Export ranked traders to this CSV (addresses also written to a .txt next to it)
+        #[arg(long)]
+        export: Option<String>,
+        /// Ignore the fill cache and rescan from scratch
+        #[arg(long)]
+        refresh: bool,
+    },
     /// Find low-risk traders with good metrics
-    FindLowRiskTraders,
+    FindLowRiskTraders {
+        /// Minimum number of fills
+        #[arg(long, default_value_t = 10)]
+        min_trades: usize,
+        /// Minimum Sharpe-like ratio (mean / stddev of per-close returns)
+        #[arg(long)]
+        min_sharpe: Option<f64>,
+        /// Maximum drawdown of cumulative realised P&L, in USD
+        #[arg(long)]
+        max_drawdown: Option<f64>,
+        /// Minimum average hold time, in minutes
+        #[arg(long)]
+        min_hold_mins: Option<f64>,
+        /// Maximum share of volume (0-1) traded in the late window before resolution
+        #[arg(long)]
+        max_late_share: Option<f64>,
+        /// Late window before resolution, in minutes
+        #[arg(long, default_value_t = 10)]
+        late_window_mins: i64,
+        /// Maximum average next-block adverse price move, in cents
+        #[arg(long)]
+        max_move_cents: Option<f64>,
+        /// Number of traders to show
+        #[arg(long, default_value_t = 25)]
+        top: usize,
+        /// Export screened traders to this CSV (addresses also written to a .txt next to it)
+        #[arg(long)]
+        export: Option<String>,
+    },
     /// Scan and analyze top traders
     ScanBestTraders,
     /// Scan traders from active markets
-    ScanFromMarkets,
+    ScanFromMarkets {
+        /// Gamma tag slug to scan (repeatable, one category per tag)
+        #[arg(long)]
+        tag: Vec<String>,
+        /// Market/event slug pattern with * wildcards (repeatable)
+        #[arg(long)]
+        slug: Vec<String>,
+        /// Market category from CATEGORY_RULES to scan, e.g. tennis (repeatable)
+        #[arg(long)]
+        category: Vec<String>,
+        /// Number of recent blocks of fills to use
+        #[arg(long, default_value_t = 20000)]
+        blocks: u64,
+        /// Minimum number of markets a trader must be active in
+        #[arg(long, default_value_t = 3)]
+        min_markets: usize,
+        /// Traders to show per category
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+        /// Export all categories to this CSV (addresses also written to a .txt next to it)
+        #[arg(long)]
+        export: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -164,7 +246,17 @@ enum SimulationCommand {
     /// Aggregate trading results across strategies
     Aggregate,
     /// Audit copy trading algorithm performance
-    Audit,
+    Audit {
+        /// Trade log to audit (defaults to matches_optimized.csv)
+        #[arg(long)]
+        csv: Option<String>,
+        /// Show every event, not just the ones that differ from the simulation
+        #[arg(long)]
+        all: bool,
+        /// Skip block timestamp lookups (no latency figures, no RPC calls)
+        #[arg(long)]
+        no_latency: bool,
+    },
     /// Fetch and cache historical trade data
     FetchHistorical {
         /// Number of days to fetch (optional)
@@ -182,12 +274,12 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.group {
-        CommandGroup::Setup { command } => handle_setup(command),
+        CommandGroup::Setup { command } => handle_setup(command).await,
         CommandGroup::Main { command } => handle_main(command),
         CommandGroup::Wallet { command } => handle_wallet(command).await,
         CommandGroup::Position { command } => handle_position(command).await,
-        CommandGroup::Research { command } => handle_research(command),
-        CommandGroup::Simulation { command } => handle_simulation(command),
+        CommandGroup::Research { command } => handle_research(command).await,
+        CommandGroup::Simulation { command } => handle_simulation(command).await,
     }
 }
 
@@ -208,13 +300,11 @@ fn prompt_password(prompt: &str) -> Result<String> {
 }
 
 fn is_valid_ethereum_address(addr: &str) -> bool {
-    let clean = addr.trim().trim_start_matches("0x");
-    clean.len() == 40 && clean.chars().all(|c| c.is_ascii_hexdigit())
+    preflight::validate_address("address", addr).is_ok()
 }
 
 fn is_valid_private_key(key: &str) -> bool {
-    let clean = key.trim().trim_start_matches("0x");
-    clean.len() == 64 && clean.chars().all(|c| c.is_ascii_hexdigit())
+    preflight::validate_private_key(key).is_ok()
 }
 
 fn run_setup_wizard() -> Result<()> {
@@ -558,13 +648,20 @@ fn run_setup_wizard() -> Result<()> {
     Ok(())
 }
 
-fn run_system_status() -> Result<()> {
+async fn run_system_status(json: bool) -> Result<()> {
+    if json {
+        let max_order_usd = Config::from_env().map(|c| c.max_order_size_usd).unwrap_or(100.0);
+        let report = preflight::run(&PreflightOptions::trading(max_order_usd)).await;
+        println!("{}", report.to_json());
+        return report.gate();
+    }
+
     println!("📊 System Status Check");
     println!("{}", "=".repeat(70));
     println!();
     
     // Load and display configuration
-    match Config::from_env() {
+    let max_order_usd = match Config::from_env() {
         Ok(config) => {
             println!("✅ Configuration loaded successfully\n");
             
@@ -634,43 +731,24 @@ fn run_system_status() -> Result<()> {
             };
             println!("  Private Key: {} (masked)", key_display);
             println!();
+            config.max_order_size_usd
         }
         Err(e) => {
             println!("❌ Configuration Error:");
             println!("   {}\n", e);
             return Err(anyhow!("Configuration validation failed"));
         }
-    }
+    };
     
     // Run validation checks
     println!("{}", "-".repeat(70));
-    println!("RUNNING VALIDATION CHECKS");
+    println!("RUNNING PREFLIGHT CHECKS");
     println!("{}", "-".repeat(70));
     println!();
     
-    println!("Checking configuration format...");
-    let status1 = std::process::Command::new("cargo")
-        .args(&["run", "--release", "--bin", "validate_setup"])
-        .status()?;
-    if !status1.success() {
-        return Err(anyhow::anyhow!("Configuration validation failed"));
-    }
-    
-    println!("\nChecking balance...");
-    let status2 = std::process::Command::new("cargo")
-        .args(&["run", "--release", "--bin", "check_balance"])
-        .status()?;
-    if !status2.success() {
-        return Err(anyhow::anyhow!("Balance check failed"));
-    }
-    
-    println!("\nTesting connections...");
-    let status3 = std::process::Command::new("cargo")
-        .args(&["run", "--release", "--bin", "test_connection"])
-        .status()?;
-    if !status3.success() {
-        return Err(anyhow::anyhow!("Connection test failed"));
-    }
+    let report = preflight::run(&PreflightOptions::trading(max_order_usd)).await;
+    report.print();
+    report.gate()?;
     
     println!("\n{}", "=".repeat(70));
     println!("✅ All checks passed! System is ready.");
@@ -680,13 +758,13 @@ fn run_system_status() -> Result<()> {
     Ok(())
 }
 
-fn handle_setup(cmd: SetupCommand) -> Result<()> {
+async fn handle_setup(cmd: SetupCommand) -> Result<()> {
     match cmd {
         SetupCommand::Setup => {
             run_setup_wizard()
         }
-        SetupCommand::SystemStatus => {
-            run_system_status()
+        SetupCommand::SystemStatus { json } => {
+            run_system_status(json).await
         }
         SetupCommand::Help => {
             print_help();
@@ -709,6 +787,13 @@ fn handle_main(cmd: MainCommand) -> Result<()> {
             }
             Ok(())
         }
+        MainCommand::ClearHalt => {
+            match kill_switch::clear_halt(kill_switch::HALT_FILE)? {
+                Some(state) => println!("✅ Cleared kill switch halt from {} ({}). Buys resume on the next bot start.", state.halted_at, state.reason),
+                None => println!("No kill switch halt is active."),
+            }
+            Ok(())
+        }
     }
 }
 
@@ -724,12 +809,7 @@ async fn handle_wallet(cmd: WalletCommand) -> Result<()> {
             Ok(())
         }
         WalletCommand::CheckBothWallets { address1, address2 } => {
-            println!("🔍 Comparing Wallets");
-            println!("===================\n");
-            println!("⚠️  TODO: Implement wallet comparison");
-            println!("   Compare: {} vs {}", address1, address2);
-            println!("   Should show: balance, positions, activity comparison\n");
-            Ok(())
+            check_both_wallets(&address1, &address2).await
         }
         WalletCommand::CheckMyStats => {
             check_my_stats().await
@@ -761,16 +841,8 @@ async fn handle_wallet(cmd: WalletCommand) -> Result<()> {
                 .status()?;
             Ok(())
         }
-        WalletCommand::SetTokenAllowance => {
-            println!("🔧 Setting Token Allowance");
-            println!("==========================\n");
-            println!("⚠️  TODO: Implement ERC1155 token allowance setting");
-            println!("   This should set allowance for Conditional Tokens\n");
-            // For now, delegate to approve_tokens
-            std::process::Command::new("cargo")
-                .args(&["run", "--release", "--bin", "approve_tokens"])
-                .status()?;
-            Ok(())
+        WalletCommand::SetTokenAllowance { multisend } => {
+            set_token_allowance(multisend).await
         }
         WalletCommand::FindMyEoa => {
             find_my_eoa().await
@@ -813,29 +885,23 @@ async fn handle_position(cmd: PositionCommand) -> Result<()> {
     }
 }
 
-fn handle_research(cmd: ResearchCommand) -> Result<()> {
+async fn handle_research(cmd: ResearchCommand) -> Result<()> {
     match cmd {
-        ResearchCommand::FindBestTraders => {
-            println!("🏆 Find Best Traders");
-            println!("===================\n");
-            println!("⚠️  TODO: Implement find best traders");
-            println!("   Required logic:");
-            println!("   1. Query Polymarket leaderboards/API");
-            println!("   2. Calculate performance metrics (ROI, win rate, P&L)");
-            println!("   3. Rank traders by performance");
-            println!("   4. Display ranking table\n");
-            Ok(())
+        ResearchCommand::FindBestTraders { blocks, top, min_trades, sort, export, refresh } => {
+            find_best_traders(blocks, top, min_trades, &sort, export.as_deref(), refresh).await
         }
-        ResearchCommand::FindLowRiskTraders => {
-            println!("🛡️  Find Low-Risk Traders");
-            println!("========================\n");
-            println!("⚠️  TODO: Implement find low-risk traders");
-            println!("   Required logic:");
-            println!("   1. Query trader performance data");
-            println!("   2. Calculate risk metrics (Sharpe ratio, drawdown, etc.)");
-            println!("   3. Filter by risk criteria");
-            println!("   4. Display conservative performers\n");
-            Ok(())
+        ResearchCommand::FindLowRiskTraders {
+            min_trades, min_sharpe, max_drawdown, min_hold_mins, max_late_share, late_window_mins, max_move_cents, top, export,
+        } => {
+            let filter = pm_whale_follower::research::RiskFilter {
+                min_trades,
+                min_sharpe,
+                max_drawdown_usd: max_drawdown,
+                min_hold_mins,
+                max_late_share,
+                max_move_cents,
+            };
+            find_low_risk_traders(&filter, late_window_mins, top, export.as_deref()).await
         }
         ResearchCommand::ScanBestTraders => {
             println!("🔍 Scan Best Traders");
@@ -848,21 +914,23 @@ fn handle_research(cmd: ResearchCommand) -> Result<()> {
             println!("   4. Generate report\n");
             Ok(())
         }
-        ResearchCommand::ScanFromMarkets => {
-            println!("📈 Scan Traders from Markets");
-            println!("============================\n");
-            println!("⚠️  TODO: Implement scan traders from markets");
-            println!("   Required logic:");
-            println!("   1. Scan active markets");
-            println!("   2. Extract trader addresses");
-            println!("   3. Analyze activity");
-            println!("   4. Generate trader list\n");
-            Ok(())
+        ResearchCommand::ScanFromMarkets { tag, slug, category, blocks, min_markets, top, export } => {
+            use pm_whale_follower::research::MarketSelector;
+            let mut selectors: Vec<MarketSelector> = tag.into_iter().map(MarketSelector::Tag).collect();
+            selectors.extend(slug.into_iter().map(MarketSelector::Slug));
+            for c in &category {
+                selectors.push(MarketSelector::category_from_str(c)?);
+            }
+            if selectors.is_empty() {
+                // Default to every category in the rules
+                selectors = MarketSelector::all_categories();
+            }
+            scan_from_markets(&selectors, blocks, min_markets, top, export.as_deref()).await
         }
     }
 }
 
-fn handle_simulation(cmd: SimulationCommand) -> Result<()> {
+async fn handle_simulation(cmd: SimulationCommand) -> Result<()> {
     match cmd {
         SimulationCommand::SimulateProfitability { trader_address } => {
             println!("📊 Simulate Profitability");
@@ -918,16 +986,8 @@ fn handle_simulation(cmd: SimulationCommand) -> Result<()> {
             println!("   4. Save to strategy_factory_results/\n");
             Ok(())
         }
-        SimulationCommand::Audit => {
-            println!("🔍 Audit Copy Trading");
-            println!("====================\n");
-            println!("⚠️  TODO: Implement copy trading audit");
-            println!("   Required logic:");
-            println!("   1. Simulate trading with bot's algorithm");
-            println!("   2. Compare with actual bot performance");
-            println!("   3. Identify discrepancies");
-            println!("   4. Generate audit report\n");
-            Ok(())
+        SimulationCommand::Audit { csv, all, no_latency } => {
+            run_audit(csv.as_deref().unwrap_or(CSV_FILE), all, no_latency).await
         }
         SimulationCommand::FetchHistorical { days, force } => {
             println!("📥 Fetch Historical Trades");
@@ -953,21 +1013,23 @@ fn print_help() {
     println!("📋 Setup & Configuration:");
     println!("  cargo run --release setup setup               - Interactive setup wizard");
     println!("  cargo run --release setup system-status       - Validate config, check balances, connectivity");
+    println!("  cargo run --release setup system-status --json - Same checks as a JSON report");
     println!("  cargo run --release setup help                - Print this help message\n");
     
     println!("🚀 Main Bot:");
-    println!("  cargo run --release main run                  - Start the copy trading bot\n");
+    println!("  cargo run --release main run                  - Start the copy trading bot");
+    println!("  cargo run --release main clear-halt           - Lift a kill switch halt\n");
     
     println!("💼 Wallet Management:");
     println!("  cargo run --release wallet check-proxy-wallet         - Check Gnosis Safe balance/positions");
-    println!("  cargo run --release wallet check-both-wallets <a1> <a2> - Compare two wallets");
+    println!("  cargo run --release wallet check-both-wallets <a1> <a2> - Compare two wallets (tracking error)");
     println!("  cargo run --release wallet check-my-stats             - View wallet statistics");
     println!("  cargo run --release wallet check-recent-activity      - View recent trades");
     println!("  cargo run --release wallet check-positions-detailed   - View detailed positions");
     println!("  cargo run --release wallet check-pnl-discrepancy      - Analyze P&L discrepancies");
     println!("  cargo run --release wallet verify-allowance           - Verify token allowance");
     println!("  cargo run --release wallet check-allowance            - Check and set allowance");
-    println!("  cargo run --release wallet set-token-allowance        - Set ERC1155 allowance");
+    println!("  cargo run --release wallet set-token-allowance [--multisend] - Set ERC1155 allowance (Safe aware)");
     println!("  cargo run --release wallet find-my-eoa                - Find EOA wallet");
     println!("  cargo run --release wallet find-gnosis-safe-proxy     - Find Gnosis Safe proxy\n");
     
@@ -979,10 +1041,10 @@ fn print_help() {
     println!("  cargo run --release position redeem-resolved          - Redeem resolved positions\n");
     
     println!("🔍 Trader Research:");
-    println!("  cargo run --release research find-best-traders        - Find top performers");
-    println!("  cargo run --release research find-low-risk-traders    - Find low-risk traders");
+    println!("  cargo run --release research find-best-traders [--blocks N] [--sort pnl|roi|winrate|volume] [--export F]");
+    println!("  cargo run --release research find-low-risk-traders [--min-sharpe X] [--max-drawdown USD] [--max-late-share F]");
     println!("  cargo run --release research scan-best-traders        - Scan top traders");
-    println!("  cargo run --release research scan-from-markets        - Scan from markets\n");
+    println!("  cargo run --release research scan-from-markets [--tag T] [--slug P] [--category NAME]\n");
     
     println!("📊 Simulation & Backtesting:");
     println!("  cargo run --release simulation simulate-profitability [trader]");
@@ -990,7 +1052,7 @@ fn print_help() {
     println!("  cargo run --release simulation run [preset]           - Run batch simulations");
     println!("  cargo run --release simulation compare [mode]         - Compare results");
     println!("  cargo run --release simulation aggregate              - Aggregate results");
-    println!("  cargo run --release simulation audit                  - Audit algorithm");
+    println!("  cargo run --release simulation audit [--csv F] [--all] - Audit live vs simulated trades");
     println!("  cargo run --release simulation fetch-historical [--force] [--days N]\n");
     
     println!("For detailed help on a command, use:");
@@ -1001,7 +1063,6 @@ fn print_help() {
 
 const USDC_ADDRESS: &str = "0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174";
 const CSV_FILE: &str = "matches_optimized.csv";
-const DEFAULT_RPC_URL: &str = "https://polygon-rpc.com";
 
 sol! {
     #[sol(rpc)]
@@ -1029,6 +1090,134 @@ struct CsvRow {
     clob_asset_id: Option<String>,
 }
 
+async fn set_token_allowance(multisend: bool) -> Result<()> {
+    use pm_whale_follower::approvals::{self, ApprovalStatus, MissingApproval};
+
+    println!("🔧 Setting Token Allowance");
+    println!("==========================\n");
+
+    let private_key = env::var("PRIVATE_KEY")
+        .map_err(|_| anyhow!("PRIVATE_KEY environment variable not set. Add it to your .env file."))?;
+    let signer: PrivateKeySigner = private_key.parse()
+        .map_err(|e| anyhow!("Failed to parse private key: {}", e))?;
+    let funder_address = env::var("FUNDER_ADDRESS")
+        .map(|addr| addr.trim().strip_prefix("0x").unwrap_or(&addr).to_string())
+        .ok()
+        .and_then(|addr| Address::from_str(&addr).ok())
+        .unwrap_or_else(|| signer.address());
+
+    println!("📝 Signer Wallet: {}", signer.address());
+    println!("🏦 Funder Address: {}\n", funder_address);
+
+    let rpc_url = providers::rpc_http_url();
+    let status = ApprovalStatus::fetch(&rpc_url, funder_address).await?;
+    for s in &status.spenders {
+        println!("   Conditional Tokens approved for {}: {}", s.name, s.ctf_approved);
+    }
+    println!();
+
+    // ERC1155 only; USDC allowances are handled by the approve_tokens binary
+    let missing: Vec<MissingApproval> = status.missing(0.0).into_iter()
+        .filter(|m| matches!(m, MissingApproval::ConditionalTokens { .. }))
+        .collect();
+    if missing.is_empty() {
+        println!("✅ ERC1155 allowances are already set. No action needed.\n");
+        return Ok(());
+    }
+
+    if funder_address != signer.address() {
+        println!("🏦 Executing as Gnosis Safe transaction(s)...");
+    }
+    let calls = approvals::approval_calls(&missing)?;
+    let hashes = approvals::apply_approvals(&signer, funder_address, &rpc_url, &calls, multisend).await?;
+    for hash in hashes {
+        println!("   ✅ Transaction: {:?}", hash);
+    }
+
+    println!("\n✅ ERC1155 allowances set. Use 'wallet verify-allowance' to double-check.\n");
+    Ok(())
+}
+
+async fn check_both_wallets(address1: &str, address2: &str) -> Result<()> {
+    use pm_whale_follower::wallet_compare::{self, WalletSnapshot};
+
+    println!("🔍 Comparing Wallets");
+    println!("===================\n");
+
+    let parse = |a: &str| -> Result<Address> {
+        let a = a.trim();
+        let a = if a.starts_with("0x") { a.to_string() } else { format!("0x{}", a) };
+        Address::from_str(&a).map_err(|e| anyhow!("Invalid address '{}': {}", a, e))
+    };
+    let (addr_a, addr_b) = (parse(address1)?, parse(address2)?);
+
+    let rpc_url = providers::rpc_http_url();
+    let provider = ProviderBuilder::new().connect_http(rpc_url.parse()?);
+    let usdc = IERC20::new(Address::from_str(USDC_ADDRESS)?, provider);
+    let client = reqwest::Client::new();
+    let now = chrono::Utc::now().timestamp();
+
+    let mut snapshots = Vec::with_capacity(2);
+    for addr in [addr_a, addr_b] {
+        let addr_str = format!("{:#x}", addr);
+        println!("📥 Loading {}...", addr_str);
+        let balance = usdc.balanceOf(addr).call().await?;
+        let balance: f64 = format_units(balance, 6).parse().unwrap_or(0.0);
+        let positions = wallet_compare::fetch_open_positions(&client, &addr_str).await?;
+        let trades = wallet_compare::fetch_trades_since(&client, &addr_str, now - 30 * 86_400).await?;
+        snapshots.push(WalletSnapshot::from_parts(&addr_str, balance, positions, &trades, now));
+    }
+    let (a, b) = (&snapshots[0], &snapshots[1]);
+    println!();
+
+    println!("{:-<100}", "");
+    println!("{:<28} {:<35} {:<35}", "", "Wallet A", "Wallet B");
+    println!("{:-<100}", "");
+    println!("{:<28} {:<35} {:<35}", "Address", a.address, b.address);
+    println!("{:<28} ${:<34.2} ${:<34.2}", "USDC balance", a.usdc_balance, b.usdc_balance);
+    println!("{:<28} {:<35} {:<35}", "Open positions", a.positions.len(), b.positions.len());
+    println!("{:<28} ${:<34.2} ${:<34.2}", "Mark value", a.mark_value(), b.mark_value());
+    println!("{:<28} ${:<34.2} ${:<34.2}", "Volume (7d)", a.volume_7d, b.volume_7d);
+    println!("{:<28} ${:<34.2} ${:<34.2}", "Volume (30d)", a.volume_30d, b.volume_30d);
+    println!("{:<28} {:<35} {:<35}", "Trades (30d)", a.trades_30d, b.trades_30d);
+    println!("{:<28} ${:<+34.2} ${:<+34.2}", "Realised P&L (30d)", a.realised_pnl_30d, b.realised_pnl_30d);
+    println!("{:-<100}", "");
+
+    let cmp = wallet_compare::compare(a, b);
+
+    println!("\n🔗 Overlapping Tokens ({}):", cmp.overlaps.len());
+    if cmp.overlaps.is_empty() {
+        println!("   None - the wallets hold no positions in common");
+    } else {
+        println!("{:-<130}", "");
+        println!("{:<40} {:<8} {:<12} {:<12} {:<10} {:<10} {:<12} {:<12} {:<8}",
+                 "Market", "Outcome", "Size A", "Size B", "Avg A", "Avg B", "Value A", "Value B", "A/B");
+        println!("{:-<130}", "");
+        for o in &cmp.overlaps {
+            let title = if o.title.chars().count() > 38 { format!("{}...", o.title.chars().take(35).collect::<String>()) } else { o.title.clone() };
+            let ratio = if o.size_b > 0.0 { format!("{:.4}", o.size_a / o.size_b) } else { "-".to_string() };
+            println!("{:<40} {:<8} {:<12.2} {:<12.2} {:<10.4} {:<10.4} ${:<11.2} ${:<11.2} {:<8}",
+                     title, o.outcome, o.size_a, o.size_b, o.avg_price_a, o.avg_price_b, o.value_a, o.value_b, ratio);
+        }
+        println!("{:-<130}", "");
+    }
+
+    println!("\n📐 Tracking:");
+    println!("   Only in A: {} position(s) | Only in B: {} position(s)", cmp.only_a, cmp.only_b);
+    println!("   Coverage of B's mark value: {:.1}%", cmp.coverage_of_b * 100.0);
+    println!("   Tracking error (weight L2 distance): {:.4}", cmp.tracking_error);
+    if let Some(ratio) = cmp.median_size_ratio {
+        println!("   Median size ratio A/B: {:.4}", ratio);
+    }
+
+    println!("\n💡 Tips:");
+    println!("   - Tracking error 0 means identical portfolio mix, ~1.41 means no overlap at all");
+    println!("   - Compare 'Avg A' with 'Avg B' to see the entry price we pay versus the whale");
+    println!("   - Positions the whale holds but we don't (Only in B) are usually missed or skipped trades\n");
+
+    Ok(())
+}
+
 async fn check_my_stats() -> Result<()> {
     dotenvy::dotenv().ok();
     
@@ -1050,23 +1239,7 @@ async fn check_my_stats() -> Result<()> {
     println!("📝 Wallet Address: {}\n", funder_address);
 
     // Get RPC URL
-    let rpc_url = if let Ok(key) = env::var("ALCHEMY_API_KEY") {
-        let key = key.trim();
-        if !key.is_empty() && key != "your_alchemy_api_key_here" {
-            format!("https://polygon-mainnet.g.alchemy.com/v2/{}", key)
-        } else {
-            DEFAULT_RPC_URL.to_string()
-        }
-    } else if let Ok(key) = env::var("CHAINSTACK_API_KEY") {
-        let key = key.trim();
-        if !key.is_empty() && key != "your_chainstack_api_key_here" {
-            format!("https://polygon-mainnet.gateway.pokt.network/v1/lb/{}", key)
-        } else {
-            DEFAULT_RPC_URL.to_string()
-        }
-    } else {
-        DEFAULT_RPC_URL.to_string()
-    };
+    let rpc_url = providers::rpc_http_url();
 
     // Get balances
     let provider = ProviderBuilder::new()
@@ -1711,25 +1884,9 @@ async fn find_my_eoa() -> Result<()> {
     println!("   Network: Polygon (Chain ID: 137)\n");
     
     // Get RPC URL
-    let rpc_url = if let Ok(key) = env::var("ALCHEMY_API_KEY") {
-        let key = key.trim();
-        if !key.is_empty() && key != "your_alchemy_api_key_here" {
-            format!("https://polygon-mainnet.g.alchemy.com/v2/{}", key)
-        } else {
-            DEFAULT_RPC_URL.to_string()
-        }
-    } else if let Ok(key) = env::var("CHAINSTACK_API_KEY") {
-        let key = key.trim();
-        if !key.is_empty() && key != "your_chainstack_api_key_here" {
-            format!("https://polygon-mainnet.gateway.pokt.network/v1/lb/{}", key)
-        } else {
-            DEFAULT_RPC_URL.to_string()
-        }
-    } else {
-        DEFAULT_RPC_URL.to_string()
-    };
+    let rpc_url = providers::rpc_http_url();
     
-    println!("🌐 Using RPC: {}", if rpc_url.contains("alchemy") { "Alchemy" } else if rpc_url.contains("chainstack") { "Chainstack" } else { "Public RPC" });
+    println!("🌐 Using RPC: {}", providers::rpc_http_label());
     
     // Get provider
     let provider = ProviderBuilder::new()
@@ -1854,23 +2011,7 @@ async fn find_gnosis_safe_proxy() -> Result<()> {
         println!("   All orders will be placed from the Gnosis Safe address\n");
         
         // Get RPC URL
-        let rpc_url = if let Ok(key) = env::var("ALCHEMY_API_KEY") {
-            let key = key.trim();
-            if !key.is_empty() && key != "your_alchemy_api_key_here" {
-                format!("https://polygon-mainnet.g.alchemy.com/v2/{}", key)
-            } else {
-                DEFAULT_RPC_URL.to_string()
-            }
-        } else if let Ok(key) = env::var("CHAINSTACK_API_KEY") {
-            let key = key.trim();
-            if !key.is_empty() && key != "your_chainstack_api_key_here" {
-                format!("https://polygon-mainnet.gateway.pokt.network/v1/lb/{}", key)
-            } else {
-                DEFAULT_RPC_URL.to_string()
-            }
-        } else {
-            DEFAULT_RPC_URL.to_string()
-        };
+        let rpc_url = providers::rpc_http_url();
         
         // Get provider
         let provider = ProviderBuilder::new()
@@ -2738,6 +2879,269 @@ async fn redeem_resolved_positions() -> Result<()> {
     Ok(())
 }
 
+async fn scan_from_markets(
+    selectors: &[pm_whale_follower::research::MarketSelector],
+    blocks: u64,
+    min_markets: usize,
+    top: usize,
+    export: Option<&str>,
+) -> Result<()> {
+    use pm_whale_follower::research;
+
+    println!("📈 Scan Traders from Markets");
+    println!("============================\n");
+
+    pm_whale_follower::market_cache::init_caches();
+    let client = reqwest::Client::new();
+    let fills = research::sync_fill_cache(&client, &providers::rpc_http_url(), blocks, false).await?;
+    println!("📊 {} fill(s) in the last {} blocks\n", fills.len(), blocks);
+
+    let mut exported = Vec::new();
+    for selector in selectors {
+        let label = selector.label();
+        let tokens = match research::select_tokens(&client, selector, &fills).await {
+            Ok(t) => t,
+            Err(e) => {
+                println!("❌ {}: failed to load markets - {}\n", label, e);
+                continue;
+            }
+        };
+        let ranked = research::scan_category(&label, &fills, &tokens, min_markets);
+
+        println!("🏷️  {} ({} token(s), {} qualifying trader(s))", label, tokens.len(), ranked.len());
+        if ranked.is_empty() {
+            println!("   No profitable traders active in {}+ markets\n", min_markets);
+            continue;
+        }
+        println!("{:-<110}", "");
+        println!("{:<4} {:<44} {:<9} {:<8} {:<12} {:<8} {:<12}",
+                 "#", "Trader", "Markets", "Early", "Realised", "Win %", "Volume");
+        println!("{:-<110}", "");
+        for (i, t) in ranked.iter().take(top).enumerate() {
+            println!("{:<4} {:<44} {:<9} {:<8} ${:<+11.2} {:<7.1}% ${:<11.2}",
+                     i + 1, t.address, t.markets, t.early_markets, t.realised_pnl, t.win_rate * 100.0, t.volume_usd);
+        }
+        println!("{:-<110}\n", "");
+        exported.extend(ranked.into_iter().take(top));
+    }
+
+    if let Some(path) = export {
+        let path = Path::new(path);
+        research::export_traders(path, &exported, |t| t.address.as_str())?;
+        println!("💾 Exported {} row(s) to {}\n", exported.len(), path.display());
+    }
+
+    println!("💡 Tips:");
+    println!("   - Early = first buy among the first {:.0}% of buyers in that market", research::EARLY_ENTRY_FRACTION * 100.0);
+    println!("   - Cache categories only cover tokens already seen in the fill window");
+    println!("   - Widen --blocks to catch traders who only play a few matches a week\n");
+
+    Ok(())
+}
+
+async fn find_low_risk_traders(filter: &pm_whale_follower::research::RiskFilter, late_window_mins: i64, top: usize, export: Option<&str>) -> Result<()> {
+    use pm_whale_follower::research;
+
+    println!("🛡️  Find Low-Risk Traders");
+    println!("========================\n");
+
+    let fills = research::load_fill_cache()?;
+    if fills.is_empty() {
+        println!("   No cached fills in {}.", research::FILLS_CACHE_FILE);
+        println!("   Run 'cargo run --release research find-best-traders' first to build the cache.\n");
+        return Ok(());
+    }
+    println!("💾 Loaded {} cached fill(s)", fills.len());
+
+    let token_ids: std::collections::HashSet<&str> = fills.iter().map(|f| f.token_id.as_str()).collect();
+    println!("📅 Resolving end times for {} market token(s)...", token_ids.len());
+    let client = reqwest::Client::new();
+    let end_times = research::load_market_end_times(&client, &token_ids).await?;
+
+    let stats = research::compute_risk_stats(&fills, &end_times, late_window_mins * 60);
+    let total = stats.len();
+    let screened = research::screen_traders(stats, filter);
+    println!("🔎 {} of {} trader(s) pass the filters\n", screened.len(), total);
+    if screened.is_empty() {
+        println!("   Try relaxing the thresholds or scanning a larger window.\n");
+        return Ok(());
+    }
+
+    println!("{:-<130}", "");
+    println!("{:<4} {:<44} {:<7} {:<7} {:<12} {:<8} {:<12} {:<10} {:<8} {:<10}",
+             "#", "Trader", "Fills", "Closes", "Realised", "Sharpe", "Max DD", "Hold min", "Late %", "Move ¢");
+    println!("{:-<130}", "");
+    for (i, t) in screened.iter().take(top).enumerate() {
+        println!("{:<4} {:<44} {:<7} {:<7} ${:<+11.2} {:<8.2} ${:<11.2} {:<10.1} {:<7.1}% {:<+10.2}",
+                 i + 1, t.address, t.trades, t.closed_trades, t.realised_pnl, t.sharpe,
+                 t.max_drawdown_usd, t.avg_hold_mins, t.late_volume_share * 100.0, t.next_block_move_cents);
+    }
+    println!("{:-<130}", "");
+
+    if let Some(path) = export {
+        let path = Path::new(path);
+        research::export_traders(path, &screened[..screened.len().min(top)], |t| t.address.as_str())?;
+        println!("\n💾 Exported to {} (addresses for TARGET_WHALE_ADDRESS in {})",
+                 path.display(), path.with_extension("txt").display());
+    }
+
+    println!("\n💡 Tips:");
+    println!("   - Move ¢ is how far the price runs in the block after their fill; we always copy a block late");
+    println!("   - A high Late % means they trade into resolution, where copies rarely fill in time");
+    println!("   - Metrics cover the cached window only; refresh it with find-best-traders --blocks N\n");
+
+    Ok(())
+}
+
+async fn find_best_traders(blocks: u64, top: usize, min_trades: usize, sort: &str, export: Option<&str>, refresh: bool) -> Result<()> {
+    use pm_whale_follower::research::{self, RankBy};
+
+    println!("🏆 Find Best Traders");
+    println!("===================\n");
+
+    let rank_by = RankBy::from_str(sort)?;
+    let client = reqwest::Client::new();
+    let fills = research::sync_fill_cache(&client, &providers::rpc_http_url(), blocks, refresh).await?;
+    println!("📊 {} fill(s) in the last {} blocks\n", fills.len(), blocks);
+
+    let ranked = research::rank_traders(research::compute_trader_stats(&fills), rank_by, min_trades);
+    if ranked.is_empty() {
+        println!("   No traders with at least {} fills. Try a larger --blocks window.\n", min_trades);
+        return Ok(());
+    }
+
+    println!("{:-<130}", "");
+    println!("{:<4} {:<44} {:<8} {:<12} {:<12} {:<9} {:<9} {:<10} {:<8} {:<8}",
+             "#", "Trader", "Fills", "Volume", "Realised", "ROI", "Win %", "Avg Size", "Markets", "Top Mkt");
+    println!("{:-<130}", "");
+    for (i, t) in ranked.iter().take(top).enumerate() {
+        println!("{:<4} {:<44} {:<8} ${:<11.2} ${:<+11.2} {:<+8.1}% {:<8.1}% ${:<9.2} {:<8} {:<7.0}%",
+                 i + 1, t.address, t.trades, t.volume_usd, t.realised_pnl, t.roi * 100.0,
+                 t.win_rate * 100.0, t.avg_trade_usd, t.markets, t.concentration * 100.0);
+    }
+    println!("{:-<130}", "");
+    println!("   Ranked {} of {} trader(s) by {}\n", ranked.len().min(top), ranked.len(), sort);
+
+    if let Some(path) = export {
+        let path = Path::new(path);
+        research::export_traders(path, &ranked[..ranked.len().min(top)], |t| t.address.as_str())?;
+        println!("💾 Exported to {} (addresses for TARGET_WHALE_ADDRESS in {})\n",
+                 path.display(), path.with_extension("txt").display());
+    }
+
+    println!("💡 Tips:");
+    println!("   - P&L is realised only: open positions bought in the window are not marked");
+    println!("   - High concentration (Top Mkt) means one market drove most of the volume");
+    println!("   - Fills are cached in {}, re-runs only fetch new blocks\n", research::FILLS_CACHE_FILE);
+
+    Ok(())
+}
+
+async fn run_audit(csv_path: &str, show_all: bool, no_latency: bool) -> Result<()> {
+    use pm_whale_follower::audit::{self, Finding, SimulatedOrder};
+
+    println!("🔍 Audit Copy Trading");
+    println!("====================\n");
+
+    let config = Config::from_env()?;
+    pm_whale_follower::market_cache::init_caches();
+
+    let (rows, skipped) = audit::load_rows(Path::new(csv_path))?;
+    println!("📄 Loaded {} event(s) from {}", rows.len(), csv_path);
+    if skipped > 0 {
+        println!("   ⚠️  Skipped {} malformed row(s)", skipped);
+    }
+    if rows.is_empty() {
+        println!("\n   Nothing to audit yet.\n");
+        return Ok(());
+    }
+
+    // Block timestamps for whale-block -> order latency
+    let block_times = if no_latency {
+        std::collections::HashMap::new()
+    } else {
+        let mut blocks: Vec<u64> = rows.iter().map(|r| r.block).filter(|b| *b > 0).collect();
+        blocks.sort_unstable();
+        blocks.dedup();
+        println!("⏱️  Fetching timestamps for {} block(s)...", blocks.len());
+        let client = reqwest::Client::new();
+        audit::fetch_block_timestamps(&client, &providers::rpc_http_url(), &blocks).await
+    };
+    println!();
+
+    let events: Vec<audit::EventAudit> = rows.into_iter().map(|row| {
+        let simulated = audit::simulate_order(&config, &row);
+        let block_ts = block_times.get(&row.block).copied();
+        audit::audit_event(row, simulated, block_ts)
+    }).collect();
+
+    println!("📋 Per-Event Diff{}:\n", if show_all { "" } else { " (differences only, use --all for every event)" });
+    println!("{:-<140}", "");
+    println!("{:<23} {:<10} {:<10} {:<10} {:<10} {:<10} {:<10} {:<9} {:<10} {}",
+             "Timestamp", "Block", "Side", "Whale @", "Sim Sh", "Live Sh", "Fill @", "Slip", "Latency", "Finding");
+    println!("{:-<140}", "");
+    for e in &events {
+        if !show_all && matches!(e.finding, Finding::Match | Finding::Excluded) {
+            continue;
+        }
+        let sim_shares = match e.simulated {
+            SimulatedOrder::Trade { shares, .. } => format!("{:.2}", shares),
+            SimulatedOrder::Skip(_) => "skip".to_string(),
+        };
+        let fill_price = match &e.actual {
+            audit::ActualOutcome::Filled { fill_price, .. } => format!("{:.2}", fill_price),
+            _ => "-".to_string(),
+        };
+        let latency = e.latency_ms.map(|l| format!("{}ms", l)).unwrap_or_else(|| "-".to_string());
+        let detail = match &e.finding {
+            Finding::Missed(cause) => format!("MISSED: {}", cause),
+            other => other.label().to_string(),
+        };
+        println!("{:<23} {:<10} {:<10} {:<10.4} {:<10} {:<10.2} {:<10} {:<+9.4} {:<10} {}",
+                 e.row.timestamp, e.row.block, e.row.direction, e.row.price_per_share,
+                 sim_shares, e.actual.filled_shares(), fill_price, e.slippage_per_share, latency, detail);
+    }
+    println!("{:-<140}", "");
+
+    let summary = audit::AuditSummary::from_events(&events);
+    let audited = summary.events - summary.excluded;
+
+    println!("\n📊 Audit Summary:");
+    println!("   Events audited: {} ({} excluded - bot disabled or mock mode)", audited, summary.excluded);
+    println!("   Matched simulation: {}", summary.matched);
+    println!("   Missed trades: {}", summary.missed);
+    println!("   Size mismatches: {}", summary.size_mismatches);
+    println!("   Unexpected trades: {}", summary.unexpected);
+
+    if let (Some(p50), Some(p95), Some(max)) = (
+        summary.latency_percentile(0.5),
+        summary.latency_percentile(0.95),
+        summary.latency_percentile(1.0),
+    ) {
+        println!("\n⏱️  Latency (whale block -> order reply):");
+        println!("   p50: {}ms | p95: {}ms | max: {}ms", p50, p95, max);
+    }
+
+    println!("\n💸 Where Money Leaked:");
+    let mut causes: Vec<(&String, &f64)> = summary.missed_usd_by_cause.iter().collect();
+    causes.sort_by(|a, b| b.1.partial_cmp(a.1).unwrap_or(std::cmp::Ordering::Equal));
+    println!("   Missed notional: ${:.2}", summary.missed_usd());
+    for (cause, usd) in causes {
+        println!("      • {:<35} ${:.2}", cause, usd);
+    }
+    println!("   Under-sized fills: ${:.2}", summary.undersized_usd);
+    println!("   Over-sized / unexpected fills: ${:.2}", summary.oversized_usd);
+    println!("   Slippage vs whale: ${:+.2} (positive = paid worse than the whale)", summary.slippage_usd);
+
+    println!("\n💡 Tips:");
+    println!("   - Fill prices in the log are rounded to 2 decimals, so small slippage is approximate");
+    println!("   - Resubmitted underfills (UNDERFILL) are not logged separately and show as under-sized");
+    println!("   - RISK_BLOCKED misses are intentional; tune CB_* settings if they dominate\n");
+
+    Ok(())
+}
+
+
 fn format_units(value: U256, decimals: u32) -> String {
     let divisor = U256::from(10u64.pow(decimals));
     let whole = value / divisor;
@@ -1,109 +1,41 @@
 //! Test connection utility
 //! Run with: cargo run --release --bin test_connection
 //!
-//! Tests RPC, WebSocket, and API connectivity
+//! Tests RPC, the provider WebSocket the bot subscribes on, the CLOB API and
+//! local clock skew. Add `--json` for a machine-readable report.
 
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 use dotenvy::dotenv;
-use std::env;
-use tokio_tungstenite::connect_async;
-
-const DEFAULT_RPC_URL: &str = "https://polygon-rpc.com";
-// WebSocket URL for Polymarket CLOB - note: this is for testing connectivity only
-// The actual bot uses RPC provider WebSocket for blockchain events
-const CLOB_WS_URL: &str = "wss://clob.polymarket.com";
-const CLOB_API_BASE: &str = "https://clob.polymarket.com";
+use pm_whale_follower::preflight::{self, PreflightOptions, Section};
+use pm_whale_follower::providers;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenv().ok();
 
-    println!("🔌 Connection Tester");
-    println!("====================\n");
-
-    let mut all_ok = true;
-
-    // Test RPC
-    println!("1️⃣  Testing RPC connection...");
-    match test_rpc().await {
-        Ok(chain_id) => {
-            println!("   ✅ RPC: Connected (Chain ID: {})", chain_id);
-        }
-        Err(e) => {
-            println!("   ❌ RPC: Failed - {}", e);
-            all_ok = false;
-        }
-    }
-
-    // Test CLOB API
-    println!("\n2️⃣  Testing CLOB API...");
-    match test_clob_api().await {
-        Ok(()) => {
-            println!("   ✅ CLOB API: Accessible");
-        }
-        Err(e) => {
-            println!("   ❌ CLOB API: Failed - {}", e);
-            all_ok = false;
-        }
+    let json = preflight::json_requested();
+    if !json {
+        println!("🔌 Connection Tester");
+        println!("====================\n");
+        println!("🌐 Using RPC: {}\n", providers::rpc_http_label());
     }
 
-    // Test WebSocket
-    println!("\n3️⃣  Testing WebSocket connection...");
-    match test_websocket().await {
-        Ok(()) => {
-            println!("   ✅ WebSocket: Connected");
-        }
-        Err(e) => {
-            // WebSocket may fail due to protocol requirements, but if server responds, connectivity is OK
-            let error_str = e.to_string();
-            if error_str.contains("protocol error") || error_str.contains("upgrade failed") {
-                println!("   ⚠️  WebSocket: Server reachable but protocol negotiation failed");
-                println!("      (This is OK - bot uses RPC provider WebSocket for blockchain events)");
-            } else {
-                println!("   ❌ WebSocket: Failed - {}", e);
-                all_ok = false;
-            }
-        }
-    }
-
-    // Test configuration
-    println!("\n4️⃣  Checking configuration...");
-    let mut config_ok = true;
-
-    if env::var("PRIVATE_KEY").is_err() {
-        println!("   ❌ PRIVATE_KEY: Not set");
-        config_ok = false;
-    } else {
-        println!("   ✅ PRIVATE_KEY: Set");
-    }
-
-    if env::var("FUNDER_ADDRESS").is_err() {
-        println!("   ❌ FUNDER_ADDRESS: Not set");
-        config_ok = false;
-    } else {
-        println!("   ✅ FUNDER_ADDRESS: Set");
-    }
-
-    if env::var("TARGET_WHALE_ADDRESS").is_err() {
-        println!("   ❌ TARGET_WHALE_ADDRESS: Not set");
-        config_ok = false;
-    } else {
-        println!("   ✅ TARGET_WHALE_ADDRESS: Set");
-    }
-
-    if env::var("ALCHEMY_API_KEY").is_ok() || env::var("CHAINSTACK_API_KEY").is_ok() {
-        println!("   ✅ API Key: Set");
-    } else {
-        println!("   ⚠️  API Key: Not set (using public RPC)");
-    }
+    let report = preflight::run(&PreflightOptions::only(&[
+        Section::Config,
+        Section::Rpc,
+        Section::WebSocket,
+        Section::Clob,
+        Section::ClockSkew,
+    ])).await;
 
-    if !config_ok {
-        all_ok = false;
+    if json {
+        println!("{}", report.to_json());
+        return report.gate();
     }
 
-    // Summary
+    report.print();
     println!("\n{}", "=".repeat(50));
-    if all_ok {
+    if report.ok {
         println!("✅ All connection tests passed!");
     } else {
         println!("❌ Some connection tests failed. Check errors above.");
@@ -112,96 +44,3 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
-
-async fn test_rpc() -> Result<u64> {
-    let rpc_url = get_rpc_url();
-    
-    // Test by getting chain ID via RPC call
-    let client = reqwest::Client::new();
-    let chain_id_result = client
-        .post(&rpc_url)
-        .json(&serde_json::json!({
-            "jsonrpc": "2.0",
-            "method": "eth_chainId",
-            "params": [],
-            "id": 1
-        }))
-        .send()
-        .await?;
-    
-    let chain_id_json: serde_json::Value = chain_id_result.json().await?;
-    let chain_id_hex = chain_id_json["result"].as_str().unwrap_or("0x89"); // Default to Polygon (137)
-    let chain_id = u64::from_str_radix(chain_id_hex.strip_prefix("0x").unwrap_or(chain_id_hex), 16)?;
-    
-    // Test block number query as additional verification
-    let block_result = client
-        .post(&rpc_url)
-        .json(&serde_json::json!({
-            "jsonrpc": "2.0",
-            "method": "eth_blockNumber",
-            "params": [],
-            "id": 2
-        }))
-        .send()
-        .await?;
-    
-    let _block_json: serde_json::Value = block_result.json().await?;
-    
-    Ok(chain_id)
-}
-
-async fn test_clob_api() -> Result<()> {
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(10))
-        .build()?;
-
-    // Test CLOB API by checking if it's accessible
-    let url = format!("{}/book?token_id=0", CLOB_API_BASE);
-    let resp = client.get(&url).send().await?;
-
-    if resp.status().is_server_error() {
-        return Err(anyhow!("Server error: {} - API may be down", resp.status()));
-    }
-
-    Ok(())
-}
-
-async fn test_websocket() -> Result<()> {
-    match connect_async(CLOB_WS_URL).await {
-        Ok((_ws_stream, response)) => {
-            let status = response.status();
-            drop(_ws_stream);
-            
-            if status.as_u16() == 101 {
-                Ok(())
-            } else {
-                Err(anyhow!("protocol error: Server responded but upgrade failed (HTTP {}, expected 101)", status))
-            }
-        }
-        Err(e) => {
-            let error_msg = e.to_string();
-            if error_msg.contains("HTTP error: 200") {
-                return Err(anyhow!("protocol error: Server responded but upgrade failed"));
-            }
-            Err(anyhow!("connection failed: {}", error_msg))
-        }
-    }
-}
-
-fn get_rpc_url() -> String {
-    if let Ok(key) = env::var("ALCHEMY_API_KEY") {
-        let key = key.trim();
-        if !key.is_empty() && key != "your_alchemy_api_key_here" {
-            return format!("https://polygon-mainnet.g.alchemy.com/v2/{}", key);
-        }
-    }
-
-    if let Ok(key) = env::var("CHAINSTACK_API_KEY") {
-        let key = key.trim();
-        if !key.is_empty() && key != "your_chainstack_api_key_here" {
-            return format!("https://polygon-mainnet.gateway.pokt.network/v1/lb/{}", key);
-        }
-    }
-
-    DEFAULT_RPC_URL.to_string()
-}
@@ -5,6 +5,9 @@
 
 use anyhow::Result;
 use dotenvy::dotenv;
+use pm_whale_follower::market_category::{self, MarketFacts, OTHER};
+use pm_whale_follower::preflight::{self, Section};
+use std::collections::BTreeMap;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -13,6 +16,8 @@ async fn main() -> Result<()> {
     println!("🔄 Market Cache Refresher");
     println!("=========================\n");
 
+    preflight::require(&[Section::Clob]).await?;
+
     println!("Loading caches...");
     pm_whale_follower::market_cache::init_caches();
 
@@ -26,15 +31,22 @@ async fn main() -> Result<()> {
     let caches = pm_whale_follower::market_cache::global_caches();
     let neg_risk_count = caches.neg_risk.read().unwrap().len();
     let slug_count = caches.slugs.read().unwrap().len();
-    let atp_count = caches.atp_tokens.read().unwrap().len();
-    let ligue1_count = caches.ligue1_tokens.read().unwrap().len();
     let live_count = caches.live_status.read().unwrap().len();
 
+    // Cached tokens per category, classified by slug alone
+    let rules = market_category::rules();
+    let mut per_category: BTreeMap<&str, usize> = BTreeMap::new();
+    for slug in caches.slugs.read().unwrap().values() {
+        let facts = MarketFacts { slug: Some(slug.as_str()), ..Default::default() };
+        *per_category.entry(rules.classify(&facts).name.as_str()).or_default() += 1;
+    }
+
     println!("\n📈 Cache Statistics:");
     println!("   Neg Risk: {} tokens", neg_risk_count);
     println!("   Slugs: {} tokens", slug_count);
-    println!("   ATP Tokens: {} tokens", atp_count);
-    println!("   Ligue1 Tokens: {} tokens", ligue1_count);
+    for (name, count) in per_category.iter().filter(|(name, _)| **name != OTHER) {
+        println!("   {}: {} tokens", name, count);
+    }
     println!("   Live Status: {} tokens", live_count);
 
     if neg_risk_count == 0 && slug_count == 0 {
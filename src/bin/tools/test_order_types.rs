@@ -9,6 +9,7 @@
 use anyhow::{Result, anyhow};
 use dotenvy::dotenv;
 use pm_whale_follower::{ApiCreds, OrderArgs, RustClobClient, PreparedCreds};
+use pm_whale_follower::preflight::{self, Section};
 use std::path::Path;
 
 const CLOB_API_BASE: &str = "https://clob.polymarket.com";
@@ -60,6 +61,14 @@ fn main() -> Result<()> {
     println!("🧪 FAK Response Test");
     println!("====================\n");
 
+    // This places a real order: config, CLOB, credentials and clock must be sane
+    tokio::runtime::Runtime::new()?.block_on(preflight::require(&[
+        Section::Config,
+        Section::Clob,
+        Section::ApiCreds,
+        Section::ClockSkew,
+    ]))?;
+
     // Build client
     let mut client = RustClobClient::new(CLOB_API_BASE, 137, &private_key, &funder)?
         .with_cache_path(".clob_market_cache.json");
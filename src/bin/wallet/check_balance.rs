@@ -11,9 +11,10 @@ use alloy::primitives::{Address, U256};
 use alloy::providers::ProviderBuilder;
 use alloy::signers::local::PrivateKeySigner;
 use alloy::sol;
+use pm_whale_follower::preflight::{self, Section};
+use pm_whale_follower::providers;
 
 const USDC_ADDRESS: &str = "0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174";
-const DEFAULT_RPC_URL: &str = "https://polygon-rpc.com";
 
 sol! {
     #[sol(rpc)]
@@ -29,6 +30,8 @@ async fn main() -> Result<()> {
     println!("💰 Wallet Balance Checker");
     println!("=========================\n");
 
+    preflight::require(&[Section::Config, Section::Rpc]).await?;
+
     // Load funder address (Gnosis Safe) if provided, otherwise use signer address
     let private_key = env::var("PRIVATE_KEY")
         .map_err(|_| anyhow!("PRIVATE_KEY environment variable not set. Add it to your .env file."))?;
@@ -52,26 +55,9 @@ async fn main() -> Result<()> {
         println!("\n");
     }
 
-    // Get RPC URL
-    let rpc_url = if let Ok(key) = env::var("ALCHEMY_API_KEY") {
-        let key = key.trim();
-        if !key.is_empty() && key != "your_alchemy_api_key_here" {
-            format!("https://polygon-mainnet.g.alchemy.com/v2/{}", key)
-        } else {
-            DEFAULT_RPC_URL.to_string()
-        }
-    } else if let Ok(key) = env::var("CHAINSTACK_API_KEY") {
-        let key = key.trim();
-        if !key.is_empty() && key != "your_chainstack_api_key_here" {
-            format!("https://polygon-mainnet.gateway.pokt.network/v1/lb/{}", key)
-        } else {
-            DEFAULT_RPC_URL.to_string()
-        }
-    } else {
-        DEFAULT_RPC_URL.to_string()
-    };
+    let rpc_url = providers::rpc_http_url();
 
-    println!("🌐 Using RPC: {}\n", if rpc_url.contains("alchemy") { "Alchemy" } else if rpc_url.contains("chainstack") { "Chainstack" } else { "Public RPC" });
+    println!("🌐 Using RPC: {}\n", providers::rpc_http_label());
 
     // Setup provider with wallet
     let provider = ProviderBuilder::new()
@@ -7,6 +7,7 @@ use anyhow::{Result, anyhow};
 use dotenvy::dotenv;
 use std::env;
 use reqwest::Client;
+use pm_whale_follower::preflight::{self, Section};
 
 const CLOB_API_BASE: &str = "https://clob.polymarket.com";
 const GAMMA_API_BASE: &str = "https://gamma-api.polymarket.com";
@@ -26,6 +27,8 @@ async fn main() -> Result<()> {
     let token_id = &args[1];
     println!("📊 Market Information Checker");
     println!("=============================\n");
+
+    preflight::require(&[Section::Clob]).await?;
     println!("Token ID: {}\n", token_id);
 
     let client = Client::builder()
@@ -83,13 +86,10 @@ async fn main() -> Result<()> {
         println!("   Live (cached): {}", if is_live { "Yes" } else { "No" });
     }
 
-    let atp_buffer = pm_whale_follower::market_cache::get_atp_token_buffer(token_id);
-    let ligue1_buffer = pm_whale_follower::market_cache::get_ligue1_token_buffer(token_id);
-    if atp_buffer > 0.0 {
-        println!("   ATP Buffer: {:.2}%", atp_buffer * 100.0);
-    }
-    if ligue1_buffer > 0.0 {
-        println!("   Ligue1 Buffer: {:.2}%", ligue1_buffer * 100.0);
+    let category = pm_whale_follower::market_category::category_for_token(token_id);
+    println!("   Category: {}", category.name);
+    if category.buffer > 0.0 {
+        println!("   Category Buffer: {:.2}%", category.buffer * 100.0);
     }
 
     println!();
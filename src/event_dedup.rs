@@ -0,0 +1,201 @@
+//! Deduplication of log events arriving from redundant subscriptions
+//!
+//! `pm_bot` can subscribe to the same `OrdersFilled` filter on several
+//! providers at once. Every copy of a log is keyed by `(tx_hash, log_index)`;
+//! the first arrival is acted on and later copies only feed per-provider
+//! latency statistics, so we can see which node delivers first and how far
+//! behind the others are.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long a key is remembered. Far longer than any provider lag we expect,
+/// short enough to keep the map small.
+pub const DEDUP_WINDOW: Duration = Duration::from_secs(600);
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+pub type EventKey = (String, u64);
+
+/// Outcome of observing one delivery of an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arrival {
+    /// First copy seen: act on it
+    First,
+    /// Another provider already delivered it this long ago
+    Duplicate { lag: Duration },
+    /// Same provider delivered the same log twice (e.g. after a reconnect)
+    Repeat,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProviderLatency {
+    /// Unique events this provider delivered
+    pub delivered: u64,
+    /// Events this provider delivered before any other
+    pub first: u64,
+    pub total_lag: Duration,
+    pub max_lag: Duration,
+}
+
+impl ProviderLatency {
+    /// Mean lag behind the fastest provider, counting first arrivals as zero
+    pub fn avg_lag(&self) -> Duration {
+        if self.delivered == 0 {
+            return Duration::ZERO;
+        }
+        self.total_lag / self.delivered as u32
+    }
+}
+
+struct Seen {
+    first_at: Instant,
+    providers: Vec<usize>,
+}
+
+/// Tracks which events have been seen and how quickly each provider delivered them.
+pub struct EventDeduper {
+    names: Vec<String>,
+    stats: Vec<ProviderLatency>,
+    seen: HashMap<EventKey, Seen>,
+    unique: u64,
+    last_prune: Instant,
+}
+
+impl EventDeduper {
+    pub fn new() -> Self {
+        Self {
+            names: Vec::new(),
+            stats: Vec::new(),
+            seen: HashMap::new(),
+            unique: 0,
+            last_prune: Instant::now(),
+        }
+    }
+
+    fn provider_index(&mut self, provider: &str) -> usize {
+        match self.names.iter().position(|n| n == provider) {
+            Some(i) => i,
+            None => {
+                self.names.push(provider.to_string());
+                self.stats.push(ProviderLatency::default());
+                self.names.len() - 1
+            }
+        }
+    }
+
+    /// Record a delivery of `key` by `provider` received at `now`.
+    pub fn observe(&mut self, key: EventKey, provider: &str, now: Instant) -> Arrival {
+        if now.saturating_duration_since(self.last_prune) >= PRUNE_INTERVAL {
+            self.prune(now);
+        }
+        let idx = self.provider_index(provider);
+
+        match self.seen.get_mut(&key) {
+            None => {
+                self.seen.insert(key, Seen { first_at: now, providers: vec![idx] });
+                self.unique += 1;
+                let s = &mut self.stats[idx];
+                s.delivered += 1;
+                s.first += 1;
+                Arrival::First
+            }
+            Some(entry) if entry.providers.contains(&idx) => Arrival::Repeat,
+            Some(entry) => {
+                entry.providers.push(idx);
+                let lag = now.saturating_duration_since(entry.first_at);
+                let s = &mut self.stats[idx];
+                s.delivered += 1;
+                s.total_lag += lag;
+                s.max_lag = s.max_lag.max(lag);
+                Arrival::Duplicate { lag }
+            }
+        }
+    }
+
+    fn prune(&mut self, now: Instant) {
+        self.seen.retain(|_, s| now.saturating_duration_since(s.first_at) < DEDUP_WINDOW);
+        self.last_prune = now;
+    }
+
+    /// Unique events observed so far
+    pub fn unique(&self) -> u64 {
+        self.unique
+    }
+
+    pub fn stats(&self) -> impl Iterator<Item = (&str, &ProviderLatency)> {
+        self.names.iter().map(String::as_str).zip(self.stats.iter())
+    }
+
+    /// One line per provider, fastest (most first arrivals) first
+    pub fn report(&self) -> Vec<String> {
+        let mut rows: Vec<_> = self.stats().collect();
+        rows.sort_by(|a, b| b.1.first.cmp(&a.1.first));
+        rows.into_iter()
+            .map(|(name, s)| format!(
+                "{:<14} seen {:>5}/{:<5} first {:>5} | avg lag {:>6.1}ms | max lag {:>7.1}ms",
+                name, s.delivered, self.unique, s.first,
+                s.avg_lag().as_secs_f64() * 1000.0, s.max_lag.as_secs_f64() * 1000.0,
+            ))
+            .collect()
+    }
+}
+
+impl Default for EventDeduper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(tx: &str, idx: u64) -> EventKey {
+        (tx.to_string(), idx)
+    }
+
+    #[test]
+    fn test_first_arrival_wins() {
+        let t0 = Instant::now();
+        let mut d = EventDeduper::new();
+        assert_eq!(d.observe(key("0xa", 1), "alchemy", t0), Arrival::First);
+        let lag = Duration::from_millis(120);
+        assert_eq!(d.observe(key("0xa", 1), "quicknode", t0 + lag), Arrival::Duplicate { lag });
+        assert_eq!(d.observe(key("0xa", 1), "alchemy", t0 + lag), Arrival::Repeat);
+        // Same tx, different log is a different event
+        assert_eq!(d.observe(key("0xa", 2), "quicknode", t0 + lag), Arrival::First);
+        assert_eq!(d.unique(), 2);
+    }
+
+    #[test]
+    fn test_latency_stats() {
+        let t0 = Instant::now();
+        let mut d = EventDeduper::new();
+        d.observe(key("0xa", 0), "a", t0);
+        d.observe(key("0xa", 0), "b", t0 + Duration::from_millis(100));
+        d.observe(key("0xb", 0), "a", t0);
+        d.observe(key("0xb", 0), "b", t0 + Duration::from_millis(300));
+
+        let stats: HashMap<&str, &ProviderLatency> = d.stats().collect();
+        assert_eq!(stats["a"].first, 2);
+        assert_eq!(stats["a"].avg_lag(), Duration::ZERO);
+        assert_eq!(stats["b"].first, 0);
+        assert_eq!(stats["b"].delivered, 2);
+        assert_eq!(stats["b"].avg_lag(), Duration::from_millis(200));
+        assert_eq!(stats["b"].max_lag, Duration::from_millis(300));
+        assert!(d.report()[0].starts_with("a "));
+    }
+
+    #[test]
+    fn test_prunes_old_keys() {
+        let t0 = Instant::now();
+        let mut d = EventDeduper::new();
+        d.observe(key("0xa", 0), "a", t0);
+        let later = t0 + DEDUP_WINDOW + PRUNE_INTERVAL;
+        assert_eq!(d.observe(key("0xa", 0), "b", later), Arrival::First);
+    }
+}
@@ -0,0 +1,150 @@
+//! Account-level kill switch
+//!
+//! Halts all new buys when the day's realised plus marked-to-market P&L falls
+//! below `KILL_DAILY_LOSS_USD`, or the funder's USDC balance drops under
+//! `KILL_MIN_USDC_BALANCE`. Sells keep working so positions can still be
+//! closed. The halt is written to `HALT_FILE` and survives restarts; it is
+//! only lifted by `polymarket-bot main clear-halt`.
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::settings::Config;
+
+pub const HALT_FILE: &str = ".kill_switch.json";
+/// How often positions are marked and the limits evaluated
+pub const KILL_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KillSwitchLimits {
+    pub daily_loss_usd: Option<f64>,
+    pub min_usdc_balance: Option<f64>,
+}
+
+impl KillSwitchLimits {
+    pub fn from_config(cfg: &Config) -> Self {
+        Self {
+            daily_loss_usd: cfg.kill_daily_loss_usd,
+            min_usdc_balance: cfg.kill_min_usdc_balance,
+        }
+    }
+
+    pub fn any(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// Reason to halt, if a limit is crossed. `usdc_balance` is None when the
+    /// balance could not be read, which never trips the switch on its own.
+    pub fn breach(&self, daily_pnl: f64, usdc_balance: Option<f64>) -> Option<String> {
+        if let Some(max) = self.daily_loss_usd {
+            if -daily_pnl >= max {
+                return Some(format!("daily P&L ${:.2} crossed loss limit ${:.2}", daily_pnl, max));
+            }
+        }
+        if let (Some(floor), Some(balance)) = (self.min_usdc_balance, usdc_balance) {
+            if balance < floor {
+                return Some(format!("USDC balance ${:.2} below floor ${:.2}", balance, floor));
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HaltState {
+    pub reason: String,
+    /// RFC 3339, UTC
+    pub halted_at: String,
+}
+
+pub struct KillSwitch {
+    path: PathBuf,
+    state: Option<HaltState>,
+}
+
+impl KillSwitch {
+    /// Load the persisted halt, if any. An unreadable file counts as halted so
+    /// a corrupt state never silently re-enables trading.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let state = match fs::read_to_string(&path) {
+            Ok(s) => Some(serde_json::from_str(&s).unwrap_or_else(|e| HaltState {
+                reason: format!("unreadable halt file: {}", e),
+                halted_at: Utc::now().to_rfc3339(),
+            })),
+            Err(_) => None,
+        };
+        Self { path, state }
+    }
+
+    pub fn state(&self) -> Option<&HaltState> {
+        self.state.as_ref()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.state.is_some()
+    }
+
+    /// Halt and persist. Returns false if already halted (the first reason is kept).
+    pub fn halt(&mut self, reason: &str) -> Result<bool> {
+        if self.state.is_some() {
+            return Ok(false);
+        }
+        let state = HaltState { reason: reason.to_string(), halted_at: Utc::now().to_rfc3339() };
+        // Halted in memory even if the file cannot be written
+        self.state = Some(state.clone());
+        fs::write(&self.path, serde_json::to_string_pretty(&state)?)?;
+        Ok(true)
+    }
+}
+
+/// Remove a persisted halt. Returns the state that was cleared, if any.
+pub fn clear_halt(path: impl AsRef<Path>) -> Result<Option<HaltState>> {
+    let state = KillSwitch::load(&path).state;
+    if state.is_some() {
+        fs::remove_file(path)?;
+    }
+    Ok(state)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breach() {
+        let limits = KillSwitchLimits { daily_loss_usd: Some(50.0), min_usdc_balance: Some(20.0) };
+        assert_eq!(limits.breach(-49.0, Some(100.0)), None);
+        assert!(limits.breach(-50.0, Some(100.0)).unwrap().contains("loss limit"));
+        assert!(limits.breach(10.0, Some(19.0)).unwrap().contains("below floor"));
+        assert_eq!(limits.breach(10.0, None), None);
+        assert_eq!(KillSwitchLimits::default().breach(-1e9, Some(0.0)), None);
+    }
+
+    #[test]
+    fn test_halt_persists_until_cleared() {
+        let path = std::env::temp_dir().join(format!("kill_switch_test_{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut ks = KillSwitch::load(&path);
+        assert!(!ks.is_halted());
+        assert!(ks.halt("daily loss").unwrap());
+        assert!(!ks.halt("balance").unwrap());
+
+        // A restart sees the same halt
+        let reloaded = KillSwitch::load(&path);
+        assert_eq!(reloaded.state().unwrap().reason, "daily loss");
+
+        assert_eq!(clear_halt(&path).unwrap().unwrap().reason, "daily loss");
+        assert!(!KillSwitch::load(&path).is_halted());
+        assert_eq!(clear_halt(&path).unwrap(), None);
+    }
+}
@@ -24,12 +24,30 @@ pub use profiler::{ops, PROFILER};
 pub mod display;
 pub mod risk_guard;
 pub mod market_cache;
-pub mod tennis_markets;
-pub mod soccer_markets;
 pub mod settings;
 pub mod models;
 pub mod orders;
 pub mod strategy;
+pub mod audit;
+pub mod research;
+pub mod wallet_compare;
+pub mod safe;
+pub mod approvals;
+pub mod preflight;
+pub mod providers;
+pub mod event_dedup;
+pub mod backfill;
+pub mod reorg;
+pub mod mempool;
+pub mod aggregation;
+pub mod portfolio;
+pub mod kill_switch;
+pub mod market_category;
+pub mod slippage;
+pub mod sizing;
+pub mod market_data;
+pub mod user_stream;
+pub mod market_meta;
 
 #[cfg(test)]
 mod resubmit_tests;
@@ -14,26 +14,45 @@ use std::fmt::Write as _;
 use std::fs::{File, OpenOptions};
 use std::io::Write;
 use std::path::Path;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, oneshot};
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 
 mod models;
 
 use pm_whale_follower::display;
-use pm_whale_follower::risk_guard::{RiskGuard, RiskGuardConfig, SafetyDecision, TradeSide, calc_liquidity_depth};
+use pm_whale_follower::risk_guard::{RiskGuard, SafetyDecision, TradeSide, calc_liquidity_depth};
 use pm_whale_follower::settings::*;
 use pm_whale_follower::market_cache;
-use pm_whale_follower::tennis_markets;
-use pm_whale_follower::soccer_markets;
+use pm_whale_follower::market_category;
+use pm_whale_follower::market_data;
+use pm_whale_follower::market_meta;
+use pm_whale_follower::user_stream::{self, LedgerAction, TradeTracker, UserEvent};
+use pm_whale_follower::slippage::{self, SlippageCheck, SlippageLimits};
 use pm_whale_follower::orders;
-use pm_whale_follower::strategy;
+use pm_whale_follower::preflight;
+use pm_whale_follower::providers::{self, ProviderPool};
+use pm_whale_follower::event_dedup::{Arrival, EventDeduper, EventKey};
+use pm_whale_follower::{audit, backfill, research};
+use pm_whale_follower::reorg::{CopyOutcome, RecentEvents, ReorgedEvent, Removal, RESTORE_GRACE};
+use pm_whale_follower::aggregation::{Aggregate, FillAggregator};
+use pm_whale_follower::portfolio::{self, PortfolioLimits, PortfolioRisk};
+use pm_whale_follower::wallet_compare;
+use pm_whale_follower::kill_switch::{self, KillSwitch, KillSwitchLimits};
+use pm_whale_follower::approvals;
+use pm_whale_follower::mempool::{self, Confirmation, PendingTracker, PENDING_CONFIRM_TIMEOUT};
+use pm_whale_follower::sizing::{self, OrderTerms, WhaleFill};
 use polymarket_client_sdk::clob::types::OrderType;
 use polymarket_client_sdk::types::Decimal;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use models::*;
 
-const GAMMA_API_BASE: &str = "https://gamma-api.polymarket.com";
+/// How often per-provider arrival latency is printed when subscriptions are redundant
+const LATENCY_REPORT_INTERVAL: Duration = Duration::from_secs(600);
+/// Price concession when unwinding a trade whose source log was reorged out
+const REORG_UNWIND_BUFFER: f64 = 0.02;
+/// How often the order worker drops breaker state of idle tokens
+const GUARD_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
 
 // ============================================================================
 // Thread-local buffers 
@@ -55,6 +74,20 @@ struct OrderEngine {
     #[allow(dead_code)]
     resubmit_tx: mpsc::UnboundedSender<ResubmitRequest>,
     enable_trading: bool,
+    /// Backfilled events older than this are logged but not copied
+    max_late_secs: u64,
+    /// Events inside the reorg window and what we did with them
+    recent: Arc<Mutex<RecentEvents>>,
+    /// (private key, funder) for unwind orders when REORG_UNWIND is on
+    unwind_creds: Option<(Arc<String>, Arc<String>)>,
+    /// Portfolio-level limits; None when no limit or kill switch is configured
+    portfolio: Option<Arc<Mutex<PortfolioRisk>>>,
+    /// Persistent halt of new buys
+    kill_switch: Arc<Mutex<KillSwitch>>,
+    /// Fills reach the portfolio from the user channel instead of order replies
+    user_stream: bool,
+    /// Exposure reserved for a buy while its order is in flight (MAX_ORDER_SIZE_USD)
+    reserve_usd: f64,
 }
 
 impl OrderEngine {
@@ -91,8 +124,22 @@ async fn main() -> Result<()> {
     // Start background cache refresh task
     let _cache_refresh_handle = market_cache::spawn_cache_refresh_task();
 
+    // Per-token market metadata, fetched in batches off the hot path
+    let meta_count = market_meta::load(market_meta::META_FILE);
+    let _meta_handle = market_meta::spawn_meta_task(market_meta::META_FILE);
+    if meta_count > 0 {
+        println!("🗂️ Loaded metadata for {} tokens from {}", meta_count, market_meta::META_FILE);
+    }
+
+    // Order books for held and recently traded tokens
+    let _market_stream_handle = market_data::spawn_market_stream();
+
+    // Before Config::from_env so `--preflight --json` reports config problems
+    // as structured checks instead of a bare error
+    preflight::trading_gate().await?;
+
     let cfg = Config::from_env()?;
-    
+
     let (client, creds) = build_worker_state(
         cfg.private_key.clone(),
         cfg.funder_address.clone(),
@@ -101,7 +148,6 @@ async fn main() -> Result<()> {
     ).await?;
     
     let prepared_creds = PreparedCreds::from_api_creds(&creds)?;
-    let risk_config = cfg.risk_guard_config();
 
     let (order_tx, order_rx) = mpsc::channel(1024);
     let (resubmit_tx, resubmit_rx) = mpsc::unbounded_channel::<ResubmitRequest>();
@@ -112,14 +158,49 @@ async fn main() -> Result<()> {
     let funder_address_arc = Arc::new(cfg.funder_address.clone());
 
     let cfg_arc = Arc::new(cfg.clone());
-    start_order_worker(order_rx, client_arc.clone(), private_key_arc.clone(), funder_address_arc.clone(), cfg_arc.clone(), risk_config, resubmit_tx.clone());
+    start_order_worker(order_rx, client_arc.clone(), private_key_arc.clone(), funder_address_arc.clone(), cfg_arc.clone(), resubmit_tx.clone());
 
     tokio::spawn(resubmit_worker(resubmit_rx, client_arc, creds_arc));
 
+    let portfolio_limits = PortfolioLimits::from_config(&cfg);
+    let kill_limits = KillSwitchLimits::from_config(&cfg);
+    let portfolio = (portfolio_limits.any() || kill_limits.any())
+        .then(|| Arc::new(Mutex::new(PortfolioRisk::new(portfolio_limits))));
+    if let Some(p) = &portfolio {
+        tokio::spawn(sync_portfolio(p.clone(), cfg.funder_address.clone()));
+    }
+
+    let kill_switch = Arc::new(Mutex::new(KillSwitch::load(kill_switch::HALT_FILE)));
+    if let Some(state) = kill_switch.lock().unwrap().state() {
+        println!(
+            "\x1b[31m🛑 Kill switch active since {}: {}. New buys are halted; run `polymarket-bot main clear-halt` to resume.\x1b[0m",
+            state.halted_at, state.reason
+        );
+    }
+    if let (true, Some(p)) = (kill_limits.any(), &portfolio) {
+        tokio::spawn(run_kill_switch(kill_switch.clone(), p.clone(), kill_limits, cfg.funder_address.clone()));
+    }
+
+    // Our own order and trade events, live trading only
+    let user_stream = cfg.enable_trading && !cfg.mock_trading;
+    if user_stream {
+        let (user_tx, user_rx) = mpsc::unbounded_channel::<UserEvent>();
+        user_stream::spawn_user_stream(creds.clone(), user_tx);
+        tokio::spawn(run_user_events(user_rx, portfolio.clone()));
+    }
+
     let order_engine = OrderEngine {
         tx: order_tx,
         resubmit_tx,
         enable_trading: cfg.enable_trading,
+        max_late_secs: cfg.backfill_max_age_secs,
+        recent: Arc::new(Mutex::new(RecentEvents::new())),
+        unwind_creds: (cfg.reorg_unwind && cfg.enable_trading && !cfg.mock_trading)
+            .then(|| (private_key_arc.clone(), funder_address_arc.clone())),
+        portfolio,
+        kill_switch,
+        user_stream,
+        reserve_usd: cfg.max_order_size_usd,
     };
 
     println!(
@@ -127,20 +208,208 @@ async fn main() -> Result<()> {
         cfg.enable_trading, cfg.mock_trading
     );
 
+    let subscriptions = cfg.ws_subscriptions;
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel::<ProviderEvent>();
+    // Each subscription starts on a different provider and fails over to one no other slot is on
+    for pool in ProviderPool::for_slots(cfg.ws_providers.clone(), subscriptions) {
+        tokio::spawn(supervise_subscription(SubscriptionKind::Logs, pool, event_tx.clone()));
+    }
+    if cfg.event_source == EventSource::Mempool {
+        let mempool_providers = mempool::mempool_providers(&cfg.ws_providers);
+        println!("🔮 Mempool event source on {} (reconciled against confirmed logs)", mempool_providers[0].name);
+        tokio::spawn(supervise_subscription(SubscriptionKind::Mempool, ProviderPool::new(mempool_providers), event_tx.clone()));
+    }
+    drop(event_tx);
+
+    if subscriptions > 1 {
+        let names: Vec<&str> = cfg.ws_providers.iter().take(subscriptions).map(|p| p.name.as_str()).collect();
+        println!("🛰️ Redundant log subscriptions: {}", names.join(", "));
+    }
+
+    let http_client = reqwest::Client::builder().no_proxy().build()?;
+    let mut dedup = EventDeduper::new();
+    let mut last_report = Instant::now();
+    let mut pending = PendingTracker::new();
+    let mut expiry_check = tokio::time::interval(Duration::from_secs(5));
+    let mut aggregator: FillAggregator<ParsedEvent> = FillAggregator::new(Duration::from_millis(cfg.aggregation_window_ms));
+
+    // Act on the first copy of each log; later copies only feed latency stats
+    loop {
+        let flush_at = aggregator.next_deadline();
+        let arrival = tokio::select! {
+            arrival = event_rx.recv() => match arrival {
+                Some(a) => a,
+                None => break,
+            },
+            _ = expiry_check.tick() => {
+                for tx_hash in pending.take_expired(Instant::now(), PENDING_CONFIRM_TIMEOUT) {
+                    let engine = order_engine.clone();
+                    tokio::spawn(async move { handle_unconfirmed(tx_hash, &engine).await });
+                }
+                continue;
+            }
+            _ = tokio::time::sleep_until(flush_at.unwrap_or_else(Instant::now).into()), if flush_at.is_some() => {
+                dispatch_aggregated(aggregator.take_due(Instant::now()), &order_engine, &http_client);
+                continue;
+            }
+        };
+
+        if arrival.event.removed {
+            let engine = order_engine.clone();
+            tokio::spawn(async move { handle_removed(arrival.event, &engine).await });
+            continue;
+        }
+        if arrival.event.pending {
+            if pending.observe_pending(&arrival.event.tx_hash, arrival.event.log_index, arrival.received_at) {
+                let engine = order_engine.clone();
+                let client = http_client.clone();
+                let keys = vec![(arrival.event.tx_hash.clone(), arrival.event.log_index)];
+                tokio::spawn(async move { handle_event(arrival.event, keys, &engine, &client).await });
+            }
+            continue;
+        }
+        if order_engine.recent.lock().unwrap().mark_restored(&arrival.event.tx_hash) {
+            println!("♻️ Reorged tx {} re-included in block {}", arrival.event.tx_hash, arrival.event.block_number);
+        }
+
+        // Already acted on from the mempool: the log only confirms it
+        if let Confirmation::Confirmed { after, first } = pending.confirm(&arrival.event.tx_hash, arrival.received_at) {
+            if first {
+                println!(
+                    "✅ Pending tx {} confirmed in block {} ({:.0}ms after mempool)",
+                    arrival.event.tx_hash, arrival.event.block_number, after.as_secs_f64() * 1000.0
+                );
+            }
+            continue;
+        }
+
+        let key = (arrival.event.tx_hash.clone(), arrival.event.log_index);
+        if dedup.observe(key, &arrival.provider, arrival.received_at) == Arrival::First {
+            // Split fills of one whale trade are sized as a single trade
+            let agg_key = (arrival.event.tx_hash.clone(), arrival.event.order.clob_token_id.to_string());
+            let (shares, usd) = (arrival.event.order.shares, arrival.event.order.usd_value);
+            aggregator.add(agg_key, shares, usd, arrival.event, arrival.received_at);
+            dispatch_aggregated(aggregator.take_due(Instant::now()), &order_engine, &http_client);
+        }
+
+        if subscriptions > 1 && last_report.elapsed() >= LATENCY_REPORT_INTERVAL {
+            println!("📡 Provider latency ({} unique events):", dedup.unique());
+            for line in dedup.report() {
+                println!("   {}", line);
+            }
+            last_report = Instant::now();
+        }
+    }
+
+    Err(anyhow!("All log subscriptions stopped"))
+}
+
+/// Copy each merged trade, sized from the totals of its fills.
+fn dispatch_aggregated(due: Vec<(Vec<ParsedEvent>, Aggregate)>, order_engine: &OrderEngine, http_client: &reqwest::Client) {
+    for (fills, totals) in due {
+        // Every merged log maps to the one copy, so a reorg of any of them is caught
+        let keys: Vec<EventKey> = fills.iter().map(|e| (e.tx_hash.clone(), e.log_index)).collect();
+        let Some(mut evt) = fills.into_iter().next() else { continue };
+        if totals.fills > 1 {
+            println!(
+                "🧩 Merged {} fills of tx {} | {:.2} shares @ {:.4} VWAP",
+                totals.fills, evt.tx_hash, totals.shares, totals.vwap()
+            );
+            evt.order.shares = totals.shares;
+            evt.order.usd_value = totals.usd;
+            evt.order.price_per_share = totals.vwap();
+        }
+        let engine = order_engine.clone();
+        let client = http_client.clone();
+        tokio::spawn(async move { handle_event(evt, keys, &engine, &client).await });
+    }
+}
+
+// ============================================================================
+// Subscription Supervisor
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SubscriptionKind {
+    /// Confirmed `OrdersFilled` logs
+    Logs,
+    /// Pending whale transactions (Alchemy only)
+    Mempool,
+}
+
+/// A log delivered by one provider, timestamped on receipt
+struct ProviderEvent {
+    provider: Arc<str>,
+    received_at: Instant,
+    event: ParsedEvent,
+}
+
+/// Keep one subscription alive: reconnect with backoff, and fail over to
+/// the next provider in `pool` on repeated errors.
+async fn supervise_subscription(kind: SubscriptionKind, mut pool: ProviderPool, events: mpsc::UnboundedSender<ProviderEvent>) {
     let mut backoff_secs = 1u64;
     let max_backoff_secs = 60u64;
     let mut consecutive_failures = 0u32;
+    let http_client = reqwest::Client::builder().no_proxy().build().unwrap_or_default();
+    // Last block known to be delivered, live or by backfill
+    let mut covered_to: Option<u64> = None;
     
-    loop {
-        match run_ws_loop(&cfg.wss_url, &order_engine).await {
+    while !events.is_closed() {
+        if let Some(p) = pool.maybe_fail_back(Instant::now()) {
+            println!("↩️ Returning to preferred provider {}", p.name);
+        }
+        let provider = pool.current().clone();
+        let wss_url = provider.wss_url.as_deref().unwrap_or_default();
+        let rpc_url = provider.http_url.clone().unwrap_or_else(providers::rpc_http_url);
+        let started = Instant::now();
+
+        // Once the new subscription is live, fetch whatever the gap swallowed
+        let mut subscribed = false;
+        let on_subscribed = || {
+            subscribed = true;
+            if let Some(last) = covered_to {
+                let backfill_name: Arc<str> = format!("{} (backfill)", provider.name).into();
+                tokio::spawn(backfill_gap(backfill_name, http_client.clone(), rpc_url.clone(), last + 1, events.clone()));
+            }
+        };
+        let result = run_ws_loop(kind, &provider.name, wss_url, &events, on_subscribed).await;
+
+        // Pending transactions that were missed are picked up by the log subscription
+        if subscribed && kind == SubscriptionKind::Logs {
+            if let Ok(head) = research::latest_block(&http_client, &rpc_url).await {
+                let covered = backfill::covered_before_disconnect(head, WS_PING_TIMEOUT);
+                covered_to = Some(covered_to.map_or(covered, |c| c.max(covered)));
+            }
+        }
+
+        match result {
             Ok(_) => {
                 // Connection closed normally, reset backoff
+                pool.record_success();
                 backoff_secs = 1;
                 consecutive_failures = 0;
-                eprintln!("⚠️ WS connection closed. Reconnecting...");
+                eprintln!("⚠️ WS connection to {} closed. Reconnecting...", provider.name);
             }
             Err(e) => {
+                // A long-lived session that eventually dropped means the provider works
+                if started.elapsed() >= providers::HEALTHY_SESSION {
+                    pool.record_success();
+                    backoff_secs = 1;
+                    consecutive_failures = 0;
+                }
                 consecutive_failures += 1;
+
+                // Repeated errors on one provider: switch instead of backing off on it
+                if pool.record_failure(Instant::now()) {
+                    eprintln!(
+                        "⚠️ WS error on {} (attempt {}): {}. Failing over to {}...",
+                        provider.name, consecutive_failures, e, pool.current().name
+                    );
+                    backoff_secs = 1;
+                    consecutive_failures = 0;
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                }
                 
                 // Categorize errors for better handling
                 let error_msg = e.to_string();
@@ -163,8 +432,8 @@ async fn main() -> Result<()> {
                 let final_delay = (delay as f64 + jitter).max(1.0) as u64;
                 
                 eprintln!(
-                    "⚠️ WS error (attempt {}): {}. Reconnecting in {}s...",
-                    consecutive_failures, e, final_delay
+                    "⚠️ WS error on {} (attempt {}): {}. Reconnecting in {}s...",
+                    provider.name, consecutive_failures, e, final_delay
                 );
                 
                 tokio::time::sleep(Duration::from_secs(final_delay)).await;
@@ -216,27 +485,74 @@ fn start_order_worker(
     private_key: Arc<String>,
     funder_address: Arc<String>,
     config: Arc<Config>,
-    risk_config: RiskGuardConfig,
     resubmit_tx: mpsc::UnboundedSender<ResubmitRequest>,
 ) {
     std::thread::spawn(move || {
-        let mut guard = RiskGuard::new(risk_config);
-        order_worker(rx, client, private_key, funder_address, config, &mut guard, resubmit_tx);
+        order_worker(rx, client, private_key, funder_address, config, resubmit_tx);
     });
 }
 
+/// Breaker state of one token
+struct TokenGuard {
+    guard: RiskGuard,
+    /// Category whose CB_<CATEGORY>_* profile the guard runs with
+    category: String,
+    /// Sequence window plus trip duration; idle longer, the guard holds no state
+    ttl: Duration,
+    last_used: Instant,
+}
+
+impl TokenGuard {
+    fn new(config: &Config, category: &str, now: Instant) -> Self {
+        let cfg = config.risk_guard_config_for(category);
+        Self {
+            ttl: cfg.sequence_window + cfg.trip_duration,
+            guard: RiskGuard::new(cfg),
+            category: category.to_string(),
+            last_used: now,
+        }
+    }
+
+    /// Switch to the profile of the token's current category, keeping the
+    /// sequence and trip state
+    fn set_category(&mut self, config: &Config, category: &str) {
+        if self.category == category {
+            return;
+        }
+        let cfg = config.risk_guard_config_for(category);
+        // A trip started under the old profile may outlast the new one
+        self.ttl = self.ttl.max(cfg.sequence_window + cfg.trip_duration);
+        self.guard.set_config(cfg);
+        self.category = category.to_string();
+    }
+}
+
 fn order_worker(
     mut rx: mpsc::Receiver<WorkItem>,
     client: Arc<RustClobClient>,
     private_key: Arc<String>,
     funder_address: Arc<String>,
     config: Arc<Config>,
-    guard: &mut RiskGuard,
     resubmit_tx: mpsc::UnboundedSender<ResubmitRequest>,
 ) {
     let mut client_mut = (*client).clone();
+    // Breaker state per token, using the CB_<CATEGORY>_* profile of the token's
+    // current category; a reclassification keeps the state
+    let mut guards: HashMap<String, TokenGuard> = HashMap::new();
+    let mut last_sweep = Instant::now();
     while let Some(work) = rx.blocking_recv() {
-        let status = process_order(&work.event.order, &mut client_mut, private_key.as_str(), funder_address.as_str(), &config, guard, &resubmit_tx, work.is_live);
+        let now = Instant::now();
+        if now.duration_since(last_sweep) >= GUARD_SWEEP_INTERVAL {
+            guards.retain(|_, g| now.duration_since(g.last_used) < g.ttl);
+            last_sweep = now;
+        }
+
+        let token = &work.event.order.clob_token_id;
+        let category = &market_category::category_for_token(token).name;
+        let entry = guards.entry(token.to_string()).or_insert_with(|| TokenGuard::new(&config, category, now));
+        entry.set_category(&config, category);
+        entry.last_used = now;
+        let status = process_order(&work.event.order, &mut client_mut, private_key.as_str(), funder_address.as_str(), &config, &mut entry.guard, &resubmit_tx, work.is_live);
         let _ = work.respond_to.send(status);
     }
 }
@@ -261,6 +577,20 @@ fn process_order(
     let side_is_buy = info.order_type.starts_with("BUY");
     let whale_shares = info.shares;
     let whale_price = info.price_per_share;
+    let fill = WhaleFill {
+        token_id: &info.clob_token_id,
+        side_is_buy,
+        shares: whale_shares,
+        price: whale_price,
+        passive: info.whale_role == FillRole::Maker,
+        is_live,
+    };
+    let category = market_category::category_for_token(&info.clob_token_id);
+
+    let OrderTerms { limit_price, order_action } = match sizing::order_terms(config, category, &fill) {
+        Ok(terms) => terms,
+        Err(status) => return status,
+    };
 
     // Risk guard safety check
     let eval = guard.check_fast(&info.clob_token_id, whale_shares);
@@ -284,86 +614,31 @@ fn process_order(
         SafetyDecision::Allow => {}
     }
 
-    let (buffer, order_action, _tier_size_multiplier) = get_tier_params(whale_shares, side_is_buy, &info.clob_token_id);
-
-    // Polymarket valid price range: 0.01 to 0.99 (tick size 0.001 = 3 decimal places)
-    let limit_price_raw = if side_is_buy {
-        (whale_price + buffer).min(0.99)
-    } else {
-        (whale_price - buffer).max(0.01)
+    let my_shares = match sizing::copy_shares(config, category, &fill, limit_price) {
+        Ok(shares) => shares,
+        Err(status) => return status,
     };
-    
-    // Round price to 3 decimal places (tick size 0.001)
-    // Polymarket requires prices to be rounded to the nearest tick (0.001)
-    let limit_price = ((limit_price_raw * 1000.0).round() / 1000.0).max(0.01).min(0.99);
-
-    // For sell orders: calculate as percentage of shares (not USD)
-    // For buy orders: calculate as percentage of USD value, then convert to shares
-    let my_shares = if !side_is_buy {
-        // SELL: Calculate as percentage of trader's share count
-        let base_shares = match config.copy_strategy {
-            pm_whale_follower::settings::CopyStrategy::Percentage => {
-                // Percentage of trader's shares
-                whale_shares * (config.copy_size / 100.0)
-            }
-            pm_whale_follower::settings::CopyStrategy::Fixed => {
-                // For sell orders with FIXED strategy, treat COPY_SIZE as fixed number of shares
-                // (not USD, since we're selling shares we already own)
-                config.copy_size
-            }
-            pm_whale_follower::settings::CopyStrategy::Adaptive => {
-                // Adaptive: calculate effective % based on trader order size in USD
-                // Then apply that % to trader's share count
-                let trader_order_size_usd = whale_shares * whale_price;
-                let effective_percent = strategy::calculate_adaptive_percent_for_display(
-                    config, trader_order_size_usd
-                );
-                whale_shares * (effective_percent / 100.0)
-            }
+
+    // Slippage guard against the live book; a resting GTD order cannot slip
+    let slippage_limits = SlippageLimits::from_config(config);
+    let my_shares = if order_action == "FAK" && slippage_limits.any() {
+        let (levels, book_tick) = match fetch_book_levels_blocking(client, &info.clob_token_id, side_is_buy) {
+            Ok(side) => side,
+            Err(e) => return format!("RISK_BOOK_FAIL:{e}"),
         };
-        
-        // Apply single multiplier
-        let mut shares = base_shares * config.trade_multiplier;
-        
-        // Apply tiered multiplier if configured (based on trader order size in USD)
-        let trader_order_size_usd = whale_shares * whale_price;
-        if let Some(tiered_mult) = strategy::get_tiered_multiplier(&config.tiered_multipliers, trader_order_size_usd) {
-            shares *= tiered_mult;
+        let tick_size = market_meta::tick_size(&info.clob_token_id)
+            .or(book_tick)
+            .unwrap_or(slippage::DEFAULT_TICK_SIZE);
+        match slippage::check(&slippage_limits, side_is_buy, whale_price, limit_price, my_shares, &levels, tick_size) {
+            SlippageCheck::Ok => my_shares,
+            SlippageCheck::Resize(depth) if depth * limit_price >= config.min_order_size_usd => depth,
+            SlippageCheck::Resize(_) => return "SKIPPED_SLIPPAGE:THIN_BOOK".into(),
+            SlippageCheck::Skip(block) => return format!("SKIPPED_SLIPPAGE:{}", block.as_str()),
         }
-        
-        // Apply MAX_ORDER_SIZE_USD cap (convert to shares for comparison)
-        let max_shares_from_usd = config.max_order_size_usd / limit_price;
-        shares = shares.min(max_shares_from_usd);
-        
-        // Respect MIN_ORDER_SIZE_USD (convert to shares)
-        let min_shares = config.min_order_size_usd / limit_price;
-        if shares < min_shares {
-            return format!("SKIPPED_BELOW_MIN ({:.2} shares < {:.2} min)", shares, min_shares);
-        }
-        
-        shares
     } else {
-        // BUY: Calculate as percentage of USD value, then convert to shares
-        let trader_order_size_usd = whale_shares * whale_price;
-        
-        // Calculate base order size using strategy
-        let base_order_usd = strategy::calculate_base_order_size(config, trader_order_size_usd, whale_price);
-        
-        // Apply multipliers and limits
-        let final_order_usd = strategy::calculate_final_order_size(config, base_order_usd, trader_order_size_usd);
-        
-        if final_order_usd < config.min_order_size_usd {
-            return format!("SKIPPED_BELOW_MIN (${:.2} < ${:.2})", final_order_usd, config.min_order_size_usd);
-        }
-        
-        // Convert USD to shares
-        strategy::usd_to_shares(final_order_usd, limit_price)
+        my_shares
     };
     
-    if my_shares <= 0.0 {
-        return "SKIPPED_INVALID_SIZE".into();
-    }
-    
     // Create size type string for display
     let size_type_str = match config.copy_strategy {
         pm_whale_follower::settings::CopyStrategy::Percentage => {
@@ -614,6 +889,11 @@ fn fetch_book_depth_blocking(
     side: TradeSide,
     threshold: f64,
 ) -> Result<f64, &'static str> {
+    if let Some(book) = market_data::book(token_id) {
+        let levels = book.taking_side(side == TradeSide::Buy);
+        return Ok(calc_liquidity_depth(side, &levels[..levels.len().min(10)], threshold));
+    }
+
     let url = format!("{}/book?token_id={}", CLOB_API_BASE, token_id);
     let resp = client.http_client()
         .get(&url)
@@ -644,68 +924,222 @@ fn fetch_book_depth_blocking(
     Ok(calc_liquidity_depth(side, &levels[..count], threshold))
 }
 
+/// All `(price, size)` levels on the side we would take from: asks for a buy,
+/// bids for a sell. The REST book also reports the market's tick size.
+fn fetch_book_levels_blocking(
+    client: &RustClobClient,
+    token_id: &str,
+    side_is_buy: bool,
+) -> Result<(Vec<(f64, f64)>, Option<f64>), &'static str> {
+    if let Some(book) = market_data::book(token_id) {
+        return Ok((book.taking_side(side_is_buy).to_vec(), None));
+    }
+
+    let url = format!("{}/book?token_id={}", CLOB_API_BASE, token_id);
+    let resp = client.http_client()
+        .get(&url)
+        .timeout(Duration::from_millis(500))
+        .send()
+        .map_err(|_| "NETWORK")?;
+
+    if !resp.status().is_success() { return Err("HTTP_ERROR"); }
+
+    let book: Value = resp.json().map_err(|_| "PARSE")?;
+    let key = if side_is_buy { "asks" } else { "bids" };
+    let levels = book[key].as_array().map(|arr| {
+        arr.iter().filter_map(|lvl| Some((
+            lvl["price"].as_str()?.parse().ok()?,
+            lvl["size"].as_str()?.parse().ok()?,
+        ))).collect()
+    }).unwrap_or_default();
+    let tick_size = book["tick_size"].as_str().and_then(|t| t.parse().ok());
+    Ok((levels, tick_size))
+}
+
 // ============================================================================
 // WebSocket Loop
 // ============================================================================
 
-async fn run_ws_loop(wss_url: &str, order_engine: &OrderEngine) -> Result<()> {
+async fn run_ws_loop(
+    kind: SubscriptionKind,
+    provider_name: &str,
+    wss_url: &str,
+    events: &mpsc::UnboundedSender<ProviderEvent>,
+    on_subscribed: impl FnOnce(),
+) -> Result<()> {
     // Add connection timeout to prevent hanging on TLS handshake
     let (mut ws, _) = tokio::time::timeout(Duration::from_secs(10), connect_async(wss_url))
         .await
         .map_err(|_| anyhow!("Connection timeout"))??;
 
-    let sub = serde_json::json!({
-        "jsonrpc": "2.0", "id": 1, "method": "eth_subscribe",
-        "params": ["logs", {
-            "address": MONITORED_ADDRESSES,
-            "topics": [[ORDERS_FILLED_EVENT_SIGNATURE], Value::Null, TARGET_TOPIC_HEX.as_str()]
-        }]
-    }).to_string();
+    let sub = match kind {
+        SubscriptionKind::Logs => serde_json::json!({
+            "jsonrpc": "2.0", "id": 1, "method": "eth_subscribe",
+            "params": ["logs", {
+                "address": MONITORED_ADDRESSES,
+                "topics": [[ORDERS_FILLED_EVENT_SIGNATURE], Value::Null, TARGET_TOPIC_HEX.as_str()]
+            }]
+        }).to_string(),
+        SubscriptionKind::Mempool => mempool::subscribe_request(),
+    };
 
-    println!("🔌 Connected. Subscribing...");
+    println!("🔌 Connected to {}. Subscribing...", provider_name);
     ws.send(Message::Text(sub)).await?;
+    on_subscribed();
 
-    let http_client = reqwest::Client::builder().no_proxy().build()?;
+    let provider: Arc<str> = provider_name.into();
+    let mut sub_id: Option<String> = None;
 
     loop {
         let msg = tokio::time::timeout(WS_PING_TIMEOUT, ws.next()).await
             .map_err(|_| anyhow!("WS timeout"))?
             .ok_or_else(|| anyhow!("WS closed"))??;
 
-        match msg {
-            Message::Text(text) => {
-                if let Some(evt) = parse_event(text) {
-                    let engine = order_engine.clone();
-                    let client = http_client.clone();
-                    tokio::spawn(async move { handle_event(evt, &engine, &client).await });
-                }
-            }
-            Message::Binary(bin) => {
-                if let Ok(text) = String::from_utf8(bin) {
-                    if let Some(evt) = parse_event(text) {
-                        let engine = order_engine.clone();
-                        let client = http_client.clone();
-                        tokio::spawn(async move { handle_event(evt, &engine, &client).await });
-                    }
-                }
-            }
-            Message::Ping(d) => { ws.send(Message::Pong(d)).await?; }
+        let text = match msg {
+            Message::Text(text) => text,
+            Message::Binary(bin) => match String::from_utf8(bin) {
+                Ok(text) => text,
+                Err(_) => continue,
+            },
+            Message::Ping(d) => { ws.send(Message::Pong(d)).await?; continue; }
             Message::Close(f) => return Err(anyhow!("WS closed: {:?}", f)),
-            _ => {}
+            _ => continue,
+        };
+
+        let received_at = Instant::now();
+        let parsed: Vec<ParsedEvent> = match kind {
+            SubscriptionKind::Logs => parse_event(text).into_iter().collect(),
+            SubscriptionKind::Mempool => parse_pending(&text, &mut sub_id),
+        };
+        for event in parsed {
+            events
+                .send(ProviderEvent { provider: provider.clone(), received_at, event })
+                .map_err(|_| anyhow!("event consumer stopped"))?;
         }
     }
 }
 
-async fn handle_event(evt: ParsedEvent, order_engine: &OrderEngine, http_client: &reqwest::Client) {
-    // Check live status from cache, fallback to API lookup
-    let is_live = match market_cache::get_is_live(&evt.order.clob_token_id) {
-        Some(v) => Some(v),
-        None => fetch_is_live(&evt.order.clob_token_id, http_client).await,
+/// Replay whale fills from `from` to the current head through the pipeline,
+/// marked late. Anything the live subscriptions already delivered is dropped
+/// by the deduplicator.
+async fn backfill_gap(
+    provider: Arc<str>,
+    client: reqwest::Client,
+    rpc_url: String,
+    from: u64,
+    events: mpsc::UnboundedSender<ProviderEvent>,
+) {
+    let head = match research::latest_block(&client, &rpc_url).await {
+        Ok(h) => h,
+        Err(e) => {
+            eprintln!("⚠️ Backfill skipped, head block unavailable: {}", e);
+            return;
+        }
+    };
+    let Some((start, end)) = backfill::gap_range(from, head) else { return };
+    if start > from {
+        eprintln!("⚠️ Gap of {} blocks exceeds backfill limit; blocks {}..{} not checked", head - from + 1, from, start - 1);
+    }
+
+    let logs = match backfill::fetch_whale_logs(&client, &rpc_url, start, end).await {
+        Ok(logs) => logs,
+        Err(e) => {
+            eprintln!("⚠️ Backfill of blocks {}..{} failed: {}", start, end, e);
+            return;
+        }
+    };
+
+    let parsed: Vec<ParsedEvent> = logs.into_iter()
+        .filter_map(|v| serde_json::from_value::<LogResult>(v).ok())
+        .filter_map(parse_log)
+        .collect();
+    let mut blocks: Vec<u64> = parsed.iter().map(|e| e.block_number).collect();
+    blocks.dedup();
+    let times = audit::fetch_block_timestamps(&client, &rpc_url, &blocks).await;
+    let now_ts = Utc::now().timestamp();
+
+    println!("🧩 Backfilled blocks {}..{}: {} whale fills", start, end, parsed.len());
+    for mut event in parsed {
+        event.late_secs = Some(backfill::block_age_secs(event.block_number, head, times.get(&event.block_number).copied(), now_ts));
+        let arrival = ProviderEvent { provider: provider.clone(), received_at: Instant::now(), event };
+        if events.send(arrival).is_err() {
+            return;
+        }
+    }
+}
+
+/// Copy one whale trade. `keys` are the logs it was merged from.
+async fn handle_event(evt: ParsedEvent, keys: Vec<EventKey>, order_engine: &OrderEngine, http_client: &reqwest::Client) {
+    let side_is_buy = evt.order.order_type.starts_with("BUY");
+    market_data::watch(&evt.order.clob_token_id);
+    {
+        let mut recent = order_engine.recent.lock().unwrap();
+        for key in &keys {
+            recent.record(key.clone(), evt.block_number, &evt.order.clob_token_id, side_is_buy, Instant::now());
+        }
+    }
+
+    // Live status from the caches only; a miss is fetched in the background
+    let is_live = market_cache::get_is_live(&evt.order.clob_token_id)
+        .or_else(|| market_meta::is_live(&evt.order.clob_token_id));
+
+    // Portfolio limits apply on top of the per-token risk guard
+    let today = Utc::now().date_naive();
+    let needs_group = order_engine.portfolio.as_ref()
+        .is_some_and(|p| p.lock().unwrap().limits().max_event_exposure_usd.is_some());
+    // A token whose event is not cached yet counts as its own group
+    let group = if needs_group {
+        portfolio::market_group(&evt.order.clob_token_id)
+    } else {
+        evt.order.clob_token_id.to_string()
+    };
+    let late = evt.late_secs.filter(|age| *age > order_engine.max_late_secs);
+    let halted = side_is_buy && order_engine.kill_switch.lock().unwrap().is_halted();
+
+    // Checked and reserved in one step, so concurrent buys count each other.
+    // Holdings are marked from the book and the data API, never from the whale's price
+    let reservation = match &order_engine.portfolio {
+        Some(p) if side_is_buy && late.is_none() && !halted => {
+            Some(p.lock().unwrap().reserve(&evt.order.clob_token_id, &group, order_engine.reserve_usd, today))
+        }
+        _ => None,
+    };
+
+    let status = match (late, &reservation) {
+        (Some(age), _) => format!("SKIPPED_LATE ({}s old)", age),
+        _ if halted => "RISK_BLOCKED:KILL_SWITCH".to_string(),
+        (_, Some(Err(block))) => format!("RISK_BLOCKED:{}", block.as_str()),
+        _ => order_engine.submit(evt.clone(), is_live).await,
     };
 
-    let status = order_engine.submit(evt.clone(), is_live).await;
+    let outcome = match audit::parse_order_status(&status) {
+        audit::ActualOutcome::Filled { filled, fill_price, .. } if filled > 0.0 => CopyOutcome::Copied { shares: filled, price: fill_price },
+        _ => CopyOutcome::NotCopied,
+    };
+    if let Some(p) = &order_engine.portfolio {
+        let mut p = p.lock().unwrap();
+        if let (CopyOutcome::Copied { shares, price }, false) = (outcome, order_engine.user_stream) {
+            p.record_fill(&evt.order.clob_token_id, &group, side_is_buy, shares, price, today);
+        }
+        if let Some(Ok(reservation)) = reservation {
+            // The user channel books the fill itself; until then it stays reserved
+            let unbooked_usd = match outcome {
+                CopyOutcome::Copied { shares, price } if order_engine.user_stream => shares * price,
+                _ => 0.0,
+            };
+            p.release(reservation, unbooked_usd);
+        }
+    }
+    // The source log may have been removed while our order was in flight
+    let reorged = order_engine.recent.lock().unwrap().set_outcomes(&keys, outcome);
+    for ev in reorged {
+        report_reorg(ev, order_engine).await;
+    }
 
-    tokio::time::sleep(Duration::from_secs_f32(2.8)).await;
+    // REST needs a moment for the book to reflect the trade; the stream is already current
+    if market_data::book(&evt.order.clob_token_id).is_none() {
+        tokio::time::sleep(Duration::from_secs_f32(2.8)).await;
+    }
 
     // Fetch order book for post-trade logging
     let bests = fetch_best_book(&evt.order.clob_token_id, &evt.order.order_type, http_client).await;
@@ -720,23 +1154,35 @@ async fn handle_event(evt: ParsedEvent, order_engine: &OrderEngine, http_client:
         "live: false".to_string()
     };
 
-    // Tennis market indicator (green)
-    let tennis_display = if tennis_markets::get_tennis_token_buffer(&evt.order.clob_token_id) > 0.0 {
-        "\x1b[32m(TENNIS)\x1b[0m "
+    // Market category indicator (green)
+    let category = &market_category::category_for_token(&evt.order.clob_token_id).name;
+    let category_display = if category != market_category::OTHER {
+        format!("\x1b[32m({})\x1b[0m ", category)
     } else {
-        ""
+        String::new()
     };
 
-    // Soccer market indicator (cyan)
-    let soccer_display = if soccer_markets::get_soccer_token_buffer(&evt.order.clob_token_id) > 0.0 {
-        "\x1b[36m(SOCCER)\x1b[0m "
-    } else {
-        ""
+    let role_display = match evt.order.whale_role {
+        FillRole::Maker => "\x1b[35m(MAKER)\x1b[0m ",
+        FillRole::Taker => "",
+    };
+
+    let late_display = match evt.late_secs {
+        Some(age) => format!("\x1b[33m(LATE {}s)\x1b[0m ", age),
+        None => String::new(),
+    };
+
+    let block_display = if evt.pending { "mempool".to_string() } else { evt.block_number.to_string() };
+
+    // Order hash and fee of the whale's fill, when decoded from a log
+    let fill_display = match &evt.fill {
+        Some(f) => format!(" | order {} fee {:.4}", f.order_hash, u256_to_f64(&f.fee).unwrap_or_default() / 1e6),
+        None => String::new(),
     };
 
     println!(
-        "⚡ [B:{}] {}{}{} | ${:.0} | {} | best: {} @ {} | 2nd: {} @ {} | {}",
-        evt.block_number, tennis_display, soccer_display, evt.order.order_type, evt.order.usd_value, status, colored_bp, bs, sp, ss, live_display
+        "⚡ [B:{}] {}{}{}{} | ${:.0} | {} | best: {} @ {} | 2nd: {} @ {} | {}{}",
+        block_display, late_display, role_display, category_display, evt.order.order_type, evt.order.usd_value, status, colored_bp, bs, sp, ss, live_display, fill_display
     );
 
     let ts: DateTime<Utc> = Utc::now();
@@ -747,11 +1193,11 @@ async fn handle_event(evt: ParsedEvent, order_engine: &OrderEngine, http_client:
             sanitize_csv(&status, &mut sb);
             b.clear();
             let _ = write!(b,
-                "{},{},{},{:.2},{:.6},{:.4},{},{},{},{},{},{},{},{}",
+                "{},{},{},{:.2},{:.6},{:.4},{},{},{},{},{},{},{},{},{}",
                 ts.format("%Y-%m-%d %H:%M:%S%.3f"),
                 evt.block_number, evt.order.clob_token_id, evt.order.usd_value,
                 evt.order.shares, evt.order.price_per_share, evt.order.order_type,
-                sb, bp, bs, sp, ss, evt.tx_hash, is_live
+                sb, bp, bs, sp, ss, evt.tx_hash, is_live, evt.order.whale_role.as_str()
             );
             b.clone()
         })
@@ -759,6 +1205,207 @@ async fn handle_event(evt: ParsedEvent, order_engine: &OrderEngine, http_client:
     let _ = tokio::task::spawn_blocking(move || append_csv_row(row)).await;
 }
 
+/// Resync portfolio holdings and marks from the data API.
+async fn sync_portfolio(portfolio: Arc<Mutex<PortfolioRisk>>, funder: String) {
+    let client = reqwest::Client::builder().no_proxy().build().unwrap_or_default();
+    let mut interval = tokio::time::interval(portfolio::POSITIONS_REFRESH);
+    loop {
+        interval.tick().await;
+        match wallet_compare::fetch_open_positions(&client, &funder).await {
+            Ok(positions) => {
+                let mut p = portfolio.lock().unwrap();
+                p.sync(&positions, Utc::now().date_naive());
+                let held = p.tokens();
+                // Held markets stay warm in the metadata cache
+                held.iter().for_each(|t| market_meta::request(t));
+                market_data::set_held(held);
+            }
+            Err(e) => eprintln!("⚠️ Portfolio sync failed: {}", e),
+        }
+    }
+}
+
+/// Log our order events and book confirmed trades into the portfolio ledger.
+async fn run_user_events(mut rx: mpsc::UnboundedReceiver<UserEvent>, portfolio: Option<Arc<Mutex<PortfolioRisk>>>) {
+    let mut tracker = TradeTracker::default();
+    while let Some(event) = rx.recv().await {
+        match event {
+            UserEvent::Order(o) => println!(
+                "📋 Order {} {:?}: {} {:.2}/{:.2} @ {:.3}",
+                &o.order_id[..o.order_id.len().min(10)], o.kind,
+                if o.side_is_buy { "BUY" } else { "SELL" }, o.size_matched, o.original_size, o.price
+            ),
+            UserEvent::Trade(t) => {
+                let Some(action) = tracker.on_trade(&t) else { continue };
+                println!(
+                    "💱 Trade {} {:?} ({:?}): {} {:.2} @ {:.3}",
+                    &t.trade_id[..t.trade_id.len().min(10)], t.status, action,
+                    if t.side_is_buy { "BUY" } else { "SELL" }, t.shares, t.price
+                );
+                let Some(p) = &portfolio else { continue };
+                let group = portfolio::market_group(&t.asset_id);
+                let mut p = p.lock().unwrap();
+                let today = Utc::now().date_naive();
+                match action {
+                    LedgerAction::Apply => p.record_fill(&t.asset_id, &group, t.side_is_buy, t.shares, t.price, today),
+                    LedgerAction::Reverse => p.reverse_fill(&t.asset_id, &group, t.side_is_buy, t.shares, t.price, today),
+                }
+            }
+        }
+    }
+}
+
+/// Mark held positions to the best bid and halt new buys when the day's P&L
+/// or the USDC balance crosses the kill switch limits.
+async fn run_kill_switch(
+    kill_switch: Arc<Mutex<KillSwitch>>,
+    portfolio: Arc<Mutex<PortfolioRisk>>,
+    limits: KillSwitchLimits,
+    funder: String,
+) {
+    let client = reqwest::Client::builder().no_proxy().build().unwrap_or_default();
+    let rpc_url = providers::rpc_http_url();
+    let owner = funder.parse::<alloy::primitives::Address>().ok();
+    let mut interval = tokio::time::interval(kill_switch::KILL_CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        if kill_switch.lock().unwrap().is_halted() {
+            continue;
+        }
+
+        let tokens = portfolio.lock().unwrap().tokens();
+        for token in tokens {
+            // Long positions are worth what the best bid pays
+            let bid = fetch_best_book(&token, "SELL", &client).await
+                .and_then(|((price, _), _)| price.trim_matches('"').parse::<f64>().ok());
+            if let Some(bid) = bid {
+                portfolio.lock().unwrap().mark(&token, bid);
+            }
+        }
+        let daily_pnl = portfolio.lock().unwrap().daily_pnl();
+
+        let usdc = match (limits.min_usdc_balance, owner) {
+            (Some(_), Some(owner)) => approvals::usdc_balance(&rpc_url, owner).await
+                .ok()
+                .map(|b| b.to::<u128>() as f64 / 1e6),
+            _ => None,
+        };
+
+        if let Some(reason) = limits.breach(daily_pnl, usdc) {
+            match kill_switch.lock().unwrap().halt(&reason) {
+                Ok(_) => println!("\x1b[31m🛑 KILL SWITCH: {}. New buys halted until `polymarket-bot main clear-halt`.\x1b[0m", reason),
+                Err(e) => eprintln!("⚠️ Kill switch tripped ({}) but halt state could not be saved: {}", reason, e),
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Reorg Handling
+// ============================================================================
+
+async fn handle_removed(evt: ParsedEvent, order_engine: &OrderEngine) {
+    let key = (evt.tx_hash.clone(), evt.log_index);
+    let removals = {
+        let mut recent = order_engine.recent.lock().unwrap();
+        match recent.mark_removed(&key) {
+            // Acted on from the mempool, so recorded under the pending keys
+            Removal::Unknown => recent.mark_tx_removed(&evt.tx_hash, mempool::is_pending_log_index),
+            r => vec![r],
+        }
+    };
+    if removals.is_empty() {
+        println!("↩️ Removed log outside reorg window | tx {}", evt.tx_hash);
+    }
+    for removal in removals {
+        match removal {
+            Removal::Reorged(ev) => report_reorg(ev, order_engine).await,
+            Removal::Pending => println!("⚠️ REORG [B:{}] log removed while copy order in flight | tx {}", evt.block_number, evt.tx_hash),
+            Removal::Unknown | Removal::AlreadyHandled => {}
+        }
+    }
+}
+
+/// A transaction acted on from the mempool was never mined. It is handled
+/// like a reorged-out log, including the optional unwind.
+async fn handle_unconfirmed(tx_hash: String, order_engine: &OrderEngine) {
+    let removals = order_engine.recent.lock().unwrap().mark_tx_removed(&tx_hash, mempool::is_pending_log_index);
+    for removal in removals {
+        match removal {
+            Removal::Reorged(ev) => report_reorg(ev, order_engine).await,
+            Removal::Pending => println!("⚠️ Pending tx {} not confirmed while copy order in flight", tx_hash),
+            Removal::Unknown | Removal::AlreadyHandled => {}
+        }
+    }
+}
+
+/// Surface a reorged event in the console and trade log, and schedule an
+/// unwind of the copied position when enabled.
+async fn report_reorg(ev: ReorgedEvent, order_engine: &OrderEngine) {
+    let cause = if mempool::is_pending_log_index(ev.key.1) { "pending tx dropped or reorged" } else { "source log removed" };
+    let status = match ev.copied() {
+        Some((shares, price)) => format!("REORGED: copied {:.2} @ {:.3}, {}", shares, price, cause),
+        None => "REORGED: not copied".to_string(),
+    };
+    println!("\x1b[31m⛔ REORG [B:{}] {} | token {} | tx {}\x1b[0m", ev.block_number, status, ev.token_id, ev.key.0);
+    log_reorg_row(&ev, &status).await;
+
+    let (Some((shares, price)), Some((private_key, funder))) = (ev.copied(), order_engine.unwind_creds.clone()) else {
+        return;
+    };
+    let recent = order_engine.recent.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(RESTORE_GRACE).await;
+        if !recent.lock().unwrap().is_reorged(&ev.key) {
+            println!("♻️ Unwind cancelled, tx {} was re-included", ev.key.0);
+            return;
+        }
+        let status = unwind_copied_trade(&ev, shares, price, &private_key, &funder).await;
+        println!("\x1b[33m↪️ REORG UNWIND [B:{}] {} | token {}\x1b[0m", ev.block_number, status, ev.token_id);
+        log_reorg_row(&ev, &status).await;
+    });
+}
+
+/// Reverse a copied trade with an immediate-or-cancel order on the other side.
+async fn unwind_copied_trade(ev: &ReorgedEvent, shares: f64, price: f64, private_key: &str, funder: &str) -> String {
+    // Accept a little slippage to get out; prices stay in the valid 0.01-0.99 range
+    let limit = if ev.side_is_buy { price - REORG_UNWIND_BUFFER } else { price + REORG_UNWIND_BUFFER };
+    let limit = ((limit * 1000.0).round() / 1000.0).clamp(0.01, 0.99);
+    let size = (shares * 100.0).floor() / 100.0;
+    let (Ok(size_dec), Ok(price_dec)) = (Decimal::try_from(size), Decimal::try_from(limit)) else {
+        return format!("REORG_UNWIND_FAIL: invalid size/price {:.2} @ {:.3}", size, limit);
+    };
+
+    let result = if ev.side_is_buy {
+        orders::sell_order(private_key, funder, &ev.token_id, size_dec, price_dec, Some(OrderType::FOK)).await
+    } else {
+        orders::buy_limit_order(private_key, funder, &ev.token_id, size_dec, price_dec, Some(OrderType::FOK)).await
+    };
+    let side = if ev.side_is_buy { "SELL" } else { "BUY" };
+    match result {
+        Ok(resp) if resp.error_msg.as_deref().unwrap_or_default().is_empty() => {
+            format!("REORG_UNWIND: {} {:.2} @ {:.3} | order {}", side, size, limit, resp.order_id)
+        }
+        Ok(resp) => format!("REORG_UNWIND_FAIL: {} {:.2} @ {:.3} | {}", side, size, limit, resp.error_msg.unwrap_or_default()),
+        Err(e) => format!("REORG_UNWIND_FAIL: {} {:.2} @ {:.3} | {}", side, size, limit, e),
+    }
+}
+
+async fn log_reorg_row(ev: &ReorgedEvent, status: &str) {
+    let (shares, price) = ev.copied().unwrap_or((0.0, 0.0));
+    let mut sanitized = String::new();
+    sanitize_csv(status, &mut sanitized);
+    let row = format!(
+        "{},{},{},{:.2},{:.6},{:.4},{},{},N/A,N/A,N/A,N/A,{},false,",
+        Utc::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+        ev.block_number, ev.token_id, shares * price, shares, price,
+        if ev.side_is_buy { "REORG_BUY" } else { "REORG_SELL" },
+        sanitized, ev.key.0
+    );
+    let _ = tokio::task::spawn_blocking(move || append_csv_row(row)).await;
+}
+
 // ============================================================================
 // Resubmitter Worker (handles FAK failures with price escalation)
 // ============================================================================
@@ -1158,22 +1805,15 @@ fn submit_resubmit_order_sync(
     Ok((status.is_success(), body_text, filled_shares))
 }
 
-async fn fetch_is_live(token_id: &str, client: &reqwest::Client) -> Option<bool> {
-    // Fetch market info to get slug
-    let market_url = format!("{}/markets?clob_token_ids={}", GAMMA_API_BASE, token_id);
-    let resp = client.get(&market_url).timeout(Duration::from_secs(2)).send().await.ok()?;
-    let val: Value = resp.json().await.ok()?;
-    let slug = val.get(0)?.get("slug")?.as_str()?.to_string();
-
-    // Fetch live status from events API
-    let event_url = format!("{}/events/slug/{}", GAMMA_API_BASE, slug);
-    let resp = client.get(&event_url).timeout(Duration::from_secs(2)).send().await.ok()?;
-    let val: Value = resp.json().await.ok()?;
-
-    Some(val["live"].as_bool().unwrap_or(false))
-}
-
 async fn fetch_best_book(token_id: &str, order_type: &str, client: &reqwest::Client) -> Option<((String, String), (String, String))> {
+    if let Some(book) = market_data::book(token_id) {
+        let mut levels = book.taking_side(order_type.starts_with("BUY")).iter()
+            .map(|(p, s)| (p.to_string(), s.to_string()));
+        let best = levels.next()?;
+        let second = levels.next().unwrap_or_else(|| ("N/A".into(), "N/A".into()));
+        return Some((best, second));
+    }
+
     let url = format!("{}/book?token_id={}", CLOB_API_BASE, token_id);
     let resp = client.get(&url).timeout(BOOK_REQ_TIMEOUT).send().await.ok()?;
     if !resp.status().is_success() { return None; }
@@ -1230,88 +1870,103 @@ async fn fetch_best_book(token_id: &str, order_type: &str, client: &reqwest::Cli
 
 fn parse_event(message: String) -> Option<ParsedEvent> {
     let msg: WsMessage = serde_json::from_str(&message).ok()?;
-    let result = msg.params?.result?;
-    
+    parse_log(msg.params?.result?)
+}
+
+/// Decode one `OrdersFilled` log, from the subscription or from `eth_getLogs`.
+fn parse_log(result: LogResult) -> Option<ParsedEvent> {
     // just to double check! 
-    if result.topics.len() < 3 { return None; }
-    
     let has_target = result.topics.get(2)
         .map(|t| t.eq_ignore_ascii_case(TARGET_TOPIC_HEX.as_str()))
         .unwrap_or(false);
     if !has_target { return None; }
 
-    let hex_data = &result.data;
-    if hex_data.len() < 2 + 64 * 4 { return None; }
-
-    let (maker_id, maker_bytes) = parse_u256_hex_slice_with_bytes(hex_data, 2, 66)?;
-    let (taker_id, taker_bytes) = parse_u256_hex_slice_with_bytes(hex_data, 66, 130)?;
-
-    let (clob_id, token_bytes, maker_amt, taker_amt, base_type) =
-        if maker_id.is_zero() && !taker_id.is_zero() {
-            let m = parse_u256_hex_slice(hex_data, 130, 194)?;
-            let t = parse_u256_hex_slice(hex_data, 194, 258)?;
-            (taker_id, taker_bytes, m, t, "BUY")
-        } else if taker_id.is_zero() && !maker_id.is_zero() {
-            let m = parse_u256_hex_slice(hex_data, 130, 194)?;
-            let t = parse_u256_hex_slice(hex_data, 194, 258)?;
-            (maker_id, maker_bytes, m, t, "SELL")
+    let fill = OrdersFilledEvent::decode(&result)?;
+
+    // Asset id 0 is USDC: the whale paid USDC for tokens (BUY) or the reverse (SELL)
+    let (clob_id, base_type) =
+        if fill.maker_asset_id.is_zero() && !fill.taker_asset_id.is_zero() {
+            (fill.taker_asset_id, "BUY")
+        } else if fill.taker_asset_id.is_zero() && !fill.maker_asset_id.is_zero() {
+            (fill.maker_asset_id, "SELL")
         } else {
             return None;
         };
+    let (usd_amt, share_amt) = if base_type == "BUY" {
+        (&fill.maker_amount_filled, &fill.taker_amount_filled)
+    } else {
+        (&fill.taker_amount_filled, &fill.maker_amount_filled)
+    };
 
-    let shares = if base_type == "BUY" { u256_to_f64(&taker_amt)? } else { u256_to_f64(&maker_amt)? } / 1e6;
+    let shares = u256_to_f64(share_amt)? / 1e6;
     if shares <= 0.0 { return None; }
     
-    let usd = if base_type == "BUY" { u256_to_f64(&maker_amt)? } else { u256_to_f64(&taker_amt)? } / 1e6;
+    let usd = u256_to_f64(usd_amt)? / 1e6;
     let price = usd / shares;
-    
-    let mut order_type = base_type.to_string();
-    if result.topics[0].eq_ignore_ascii_case(ORDERS_FILLED_EVENT_SIGNATURE) {
-        order_type.push_str("_FILL");
-    }
+
+    // The exchange logs itself as the taker of the taker order's fill;
+    // otherwise the whale's order was resting and someone else took it
+    let taker = fill.taker.to_string();
+    let whale_role = if MONITORED_ADDRESSES.iter().any(|a| a.eq_ignore_ascii_case(&taker)) {
+        FillRole::Taker
+    } else {
+        FillRole::Maker
+    };
 
     Some(ParsedEvent {
         block_number: result.block_number.as_deref()
             .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok())
             .unwrap_or_default(),
         tx_hash: result.transaction_hash.unwrap_or_default(),
+        log_index: fill.log_index,
         order: OrderInfo {
-            order_type,
-            clob_token_id: u256_to_dec_cached(&token_bytes, &clob_id),
+            order_type: format!("{}_FILL", base_type),
+            clob_token_id: u256_to_dec_cached(&clob_id.to_be_bytes::<32>(), &clob_id),
             usd_value: usd,
             shares,
             price_per_share: price,
+            whale_role,
         },
+        late_secs: None,
+        removed: result.removed,
+        pending: false,
+        fill: Some(fill),
     })
 }
 
-// ============================================================================
-// Hex Parsing Helpers
-// ============================================================================
-
-#[inline]
-fn parse_u256_hex_slice_with_bytes(full: &str, start: usize, end: usize) -> Option<(U256, [u8; 32])> {
-    let slice = full.get(start..end)?;
-    let clean = slice.strip_prefix("0x").unwrap_or(slice);
-    if clean.len() > 64 { return None; }
-
-    let mut hex_buf = [b'0'; 64];
-    hex_buf[64 - clean.len()..].copy_from_slice(clean.as_bytes());
-
-    let mut out = [0u8; 32];
-    for i in 0..32 {
-        let hi = hex_nibble(hex_buf[i * 2])?;
-        let lo = hex_nibble(hex_buf[i * 2 + 1])?;
-        out[i] = (hi << 4) | lo;
+/// Decode a pending whale transaction from the mempool subscription, one
+/// event per token and side. The first message is the subscription reply; its
+/// id is kept in `sub_id`.
+fn parse_pending(text: &str, sub_id: &mut Option<String>) -> Vec<ParsedEvent> {
+    if sub_id.is_none() {
+        *sub_id = mempool::extract_subscription_id(text).map(str::to_string);
+        return Vec::new();
     }
-    Some((U256::from_be_slice(&out), out))
-}
+    if !mempool::contains_whale(text.as_bytes()) { return Vec::new(); }
 
-#[inline]
-fn parse_u256_hex_slice(full: &str, start: usize, end: usize) -> Option<U256> {
-    parse_u256_hex_slice_with_bytes(full, start, end).map(|(v, _)| v)
+    mempool::parse_pending_tx(text, sub_id.as_deref()).into_iter().enumerate().map(|(n, fill)| ParsedEvent {
+        block_number: 0,
+        tx_hash: fill.tx_hash.to_lowercase(),
+        log_index: mempool::pending_log_index(n),
+        order: OrderInfo {
+            order_type: if fill.side_is_buy { "BUY_FILL" } else { "SELL_FILL" }.to_string(),
+            clob_token_id: fill.token_id.into(),
+            usd_value: fill.usd_value,
+            shares: fill.shares,
+            price_per_share: fill.price,
+            whale_role: if fill.taker { FillRole::Taker } else { FillRole::Maker },
+        },
+        late_secs: None,
+        removed: false,
+        pending: true,
+        fill: None,
+    }).collect()
 }
 
+// ============================================================================
+// U256 Helpers
+// ============================================================================
+
 fn u256_to_dec_cached(bytes: &[u8; 32], val: &U256) -> Arc<str> {
     TOKEN_ID_CACHE.with(|cache| {
         let mut cache = cache.borrow_mut();
@@ -1327,42 +1982,35 @@ fn u256_to_f64(v: &U256) -> Option<f64> {
     else { v.to_string().parse().ok() }
 }
 
-// Hex nibble lookup table - 2-3x faster than branching
-const HEX_NIBBLE_LUT: [u8; 256] = {
-    let mut lut = [255u8; 256];
-    let mut i = b'0';
-    while i <= b'9' {
-        lut[i as usize] = i - b'0';
-        i += 1;
-    }
-    let mut i = b'a';
-    while i <= b'f' {
-        lut[i as usize] = i - b'a' + 10;
-        i += 1;
-    }
-    let mut i = b'A';
-    while i <= b'F' {
-        lut[i as usize] = i - b'A' + 10;
-        i += 1;
-    }
-    lut
-};
-
-#[inline(always)]
-fn hex_nibble(b: u8) -> Option<u8> {
-    let val = HEX_NIBBLE_LUT[b as usize];
-    if val == 255 { None } else { Some(val) }
-}
-
 // ============================================================================
 // CSV Helpers
 // ============================================================================
 
+const CSV_HEADER: &str = "timestamp,block,clob_asset_id,usd_value,shares,price_per_share,direction,order_status,best_price,best_size,second_price,second_size,tx_hash,is_live,whale_role";
+
 fn ensure_csv() -> Result<()> {
     if !Path::new(CSV_FILE).exists() {
         let mut f = File::create(CSV_FILE)?;
-        writeln!(f, "timestamp,block,clob_asset_id,usd_value,shares,price_per_share,direction,order_status,best_price,best_size,second_price,second_size,tx_hash,is_live")?;
+        writeln!(f, "{}", CSV_HEADER)?;
+        return Ok(());
+    }
+    // Logs written before the whale_role column get an empty one, so every row
+    // keeps the header's field count
+    let content = std::fs::read_to_string(CSV_FILE)?;
+    let Some((header, rows)) = content.split_once('\n') else { return Ok(()) };
+    if header.trim_end() == CSV_HEADER || !CSV_HEADER.starts_with(header.trim_end()) {
+        return Ok(());
+    }
+    let mut upgraded = String::with_capacity(content.len() + content.len() / 50);
+    upgraded.push_str(CSV_HEADER);
+    upgraded.push('\n');
+    for row in rows.lines().filter(|r| !r.is_empty()) {
+        upgraded.push_str(row);
+        upgraded.push_str(",\n");
     }
+    let tmp = format!("{}.tmp", CSV_FILE);
+    std::fs::write(&tmp, upgraded)?;
+    std::fs::rename(&tmp, CSV_FILE)?;
     Ok(())
 }
 
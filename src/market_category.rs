@@ -0,0 +1,323 @@
+//! Declarative market categories
+//!
+//! Categories are defined in `CATEGORY_RULES` (default `categories.json`) and
+//! matched by Gamma tags, slug regexes or series ids; the first matching
+//! category wins and unmatched markets fall into `OTHER`. Each category can
+//! add to the tier price buffer, scale the size and force an order type for
+//! buys, and choose how live markets are handled. Breaker profiles are keyed by
+//! the category name (`CB_<NAME>_*`), so adding NBA or esports needs no code:
+//!
+//! ```json
+//! [
+//!   { "name": "NBA", "match": { "tags": ["nba"], "slug_regex": ["^nba-"] },
+//!     "buffer": 0.01, "size_multiplier": 0.5, "live": "skip" }
+//! ]
+//! ```
+//!
+//! Without a rules file the built-in rules keep the previous ATP and Ligue 1
+//! buffers (`TENNIS`, `SOCCER`) and add short crypto up/down windows.
+
+use anyhow::{Context, Result, anyhow};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use std::env;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{market_cache, market_meta};
+
+pub const DEFAULT_RULES_FILE: &str = "categories.json";
+/// Category of markets no rule matches
+pub const OTHER: &str = "OTHER";
+
+const BUILTIN_RULES: &str = r#"[
+    { "name": "TENNIS", "match": { "slug_regex": ["^atp-"] }, "buffer": 0.01 },
+    { "name": "SOCCER", "match": { "slug_regex": ["^fl1-"] }, "buffer": 0.01 },
+    { "name": "CRYPTO", "match": { "slug_regex": ["^(btc|eth|sol|xrp|bitcoin|ethereum)-.*(updown|up-or-down)"] } }
+]"#;
+
+static RULES: Lazy<CategoryRules> = Lazy::new(|| {
+    CategoryRules::load_configured().unwrap_or_else(|e| {
+        eprintln!("⚠️ {:#} - using built-in category rules", e);
+        CategoryRules::builtin()
+    })
+});
+
+/// `CATEGORY_RULES`, or `DEFAULT_RULES_FILE`
+pub fn rules_path() -> String {
+    env::var("CATEGORY_RULES").unwrap_or_else(|_| DEFAULT_RULES_FILE.to_string())
+}
+
+/// Rules loaded once by `CategoryRules::load_configured`
+pub fn rules() -> &'static CategoryRules {
+    &RULES
+}
+
+// ============================================================================
+// Rule Definitions
+// ============================================================================
+
+/// How a category treats markets that are in play
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LiveMode {
+    #[default]
+    Any,
+    /// Do not copy while the market is live
+    Skip,
+    /// Only copy while the market is live
+    Only,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct MatchSpec {
+    tags: Vec<String>,
+    slug_regex: Vec<String>,
+    series_ids: Vec<String>,
+}
+
+fn one() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Deserialize)]
+struct CategorySpec {
+    name: String,
+    #[serde(rename = "match", default)]
+    matcher: MatchSpec,
+    #[serde(default)]
+    buffer: f64,
+    #[serde(default = "one")]
+    size_multiplier: f64,
+    #[serde(default)]
+    order_type: Option<String>,
+    #[serde(default)]
+    live: LiveMode,
+    /// Extra buffer while the market is live
+    #[serde(default)]
+    live_buffer: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Category {
+    pub name: String,
+    /// Added to the tier buffer for buys
+    pub buffer: f64,
+    /// Scales the copy size of buys, before the order size limits
+    pub size_multiplier: f64,
+    /// Replaces the tier order type for buys
+    pub order_type: Option<&'static str>,
+    pub live: LiveMode,
+    pub live_buffer: f64,
+    tags: Vec<String>,
+    slug_patterns: Vec<Regex>,
+    series_ids: Vec<String>,
+}
+
+impl Category {
+    fn other() -> Self {
+        Self {
+            name: OTHER.to_string(),
+            buffer: 0.0,
+            size_multiplier: 1.0,
+            order_type: None,
+            live: LiveMode::Any,
+            live_buffer: 0.0,
+            tags: Vec::new(),
+            slug_patterns: Vec::new(),
+            series_ids: Vec::new(),
+        }
+    }
+
+    fn compile(spec: CategorySpec) -> Result<Self> {
+        let name = spec.name.trim().to_uppercase();
+        if name.is_empty() || name == OTHER {
+            return Err(anyhow!("category name '{}' is reserved or empty", spec.name));
+        }
+        // The name is part of the CB_<NAME>_* breaker keys
+        if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(anyhow!("category name '{}' must only use letters, digits and _", spec.name));
+        }
+        let order_type = match spec.order_type.as_deref().map(str::to_uppercase).as_deref() {
+            None => None,
+            Some("FAK") => Some("FAK"),
+            Some("GTD") => Some("GTD"),
+            Some("GTC") => Some("GTC"),
+            Some(other) => return Err(anyhow!("{}: invalid order_type {} (expected FAK, GTD or GTC)", name, other)),
+        };
+        let slug_patterns = spec.matcher.slug_regex.iter()
+            .map(|r| Regex::new(r).with_context(|| format!("{}: invalid slug_regex {}", name, r)))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            name,
+            buffer: spec.buffer,
+            size_multiplier: spec.size_multiplier,
+            order_type,
+            live: spec.live,
+            live_buffer: spec.live_buffer,
+            tags: spec.matcher.tags.iter().map(|t| t.to_lowercase()).collect(),
+            slug_patterns,
+            series_ids: spec.matcher.series_ids,
+        })
+    }
+
+    fn matches(&self, facts: &MarketFacts) -> bool {
+        facts.tags.iter().any(|t| self.tags.contains(&t.to_lowercase()))
+            || facts.slug.is_some_and(|s| self.slug_patterns.iter().any(|r| r.is_match(s)))
+            || facts.series_id.is_some_and(|id| self.series_ids.iter().any(|s| s == id))
+    }
+
+    /// Whether a copy is allowed given the market's live status
+    pub fn allows_live(&self, is_live: Option<bool>) -> bool {
+        match self.live {
+            LiveMode::Any => true,
+            LiveMode::Skip => is_live != Some(true),
+            LiveMode::Only => is_live == Some(true),
+        }
+    }
+}
+
+/// What is known about a token's market
+#[derive(Debug, Default)]
+pub struct MarketFacts<'a> {
+    pub slug: Option<&'a str>,
+    pub tags: &'a [String],
+    pub series_id: Option<&'a str>,
+}
+
+pub struct CategoryRules {
+    categories: Vec<Category>,
+    other: Category,
+}
+
+impl CategoryRules {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let specs: Vec<CategorySpec> = serde_json::from_str(json)?;
+        let categories: Vec<Category> = specs.into_iter().map(Category::compile).collect::<Result<_>>()?;
+        let mut seen = HashSet::new();
+        if let Some(dup) = categories.iter().find(|c| !seen.insert(c.name.as_str())) {
+            return Err(anyhow!("category {} is defined twice", dup.name));
+        }
+        Ok(Self { categories, other: Category::other() })
+    }
+
+    pub fn builtin() -> Self {
+        Self::from_json(BUILTIN_RULES).expect("built-in category rules are valid")
+    }
+
+    /// Rules from `path`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::load_from(path.as_ref(), true)
+    }
+
+    /// Rules from `CATEGORY_RULES`, which must be readable when set, or from
+    /// `DEFAULT_RULES_FILE` with the built-in rules when that does not exist
+    pub fn load_configured() -> Result<Self> {
+        match env::var("CATEGORY_RULES") {
+            Ok(path) => Self::load_from(Path::new(&path), true),
+            Err(_) => Self::load_from(Path::new(DEFAULT_RULES_FILE), false),
+        }
+    }
+
+    fn load_from(path: &Path, required: bool) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(json) => Self::from_json(&json).with_context(|| format!("invalid category rules in {}", path.display())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound && !required => Ok(Self::builtin()),
+            Err(e) => Err(e).with_context(|| format!("cannot read category rules {}", path.display())),
+        }
+    }
+
+    /// Every category name, `OTHER` last
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.categories.iter().chain(std::iter::once(&self.other)).map(|c| c.name.as_str())
+    }
+
+    pub fn classify(&self, facts: &MarketFacts) -> &Category {
+        self.categories.iter().find(|c| c.matches(facts)).unwrap_or(&self.other)
+    }
+}
+
+/// Category of a token from the metadata caches. Tokens whose metadata is
+/// still being fetched are matched on the cached slug alone.
+pub fn category_for_token(token_id: &str) -> &'static Category {
+    let meta = market_meta::get(token_id);
+    let now = chrono::Utc::now().timestamp();
+    let cached_slug = market_cache::get_slug(token_id);
+    let facts = MarketFacts {
+        slug: meta.as_ref().and_then(|m| m.slug(now)).or(cached_slug.as_deref()),
+        tags: meta.as_ref().and_then(|m| m.tags(now)).unwrap_or(&[]),
+        series_id: meta.as_ref().and_then(|m| m.series_id(now)),
+    };
+    rules().classify(&facts)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slug(s: &str) -> MarketFacts<'_> {
+        MarketFacts { slug: Some(s), ..Default::default() }
+    }
+
+    #[test]
+    fn test_builtin_rules() {
+        let rules = CategoryRules::builtin();
+        assert_eq!(rules.classify(&slug("btc-updown-15m-1760000000")).name, "CRYPTO");
+        assert_eq!(rules.classify(&slug("ethereum-up-or-down-october-18-3pm-et")).name, "CRYPTO");
+        assert_eq!(rules.classify(&slug("atp-sinner-alcaraz-2026-10-18")).buffer, 0.01);
+        assert_eq!(rules.classify(&slug("fl1-psg-lyo-2026-10-18")).name, "SOCCER");
+        // A crypto price market is not a short up/down window
+        assert_eq!(rules.classify(&slug("btc-above-120k-on-october-31")).name, OTHER);
+
+        // Same markets as the old ATP and Ligue 1 token sets: no WTA, no other leagues
+        assert_eq!(rules.classify(&slug("wta-swiatek-gauff-2026-10-18")).name, OTHER);
+        let tags = vec!["Soccer".to_string()];
+        let soccer = MarketFacts { tags: &tags, ..Default::default() };
+        assert_eq!(rules.classify(&soccer).name, OTHER);
+        assert_eq!(rules.names().collect::<Vec<_>>(), vec!["TENNIS", "SOCCER", "CRYPTO", OTHER]);
+    }
+
+    #[test]
+    fn test_rules_from_json() {
+        let rules = CategoryRules::from_json(r#"[
+            { "name": "nba", "match": { "series_ids": ["10345"], "slug_regex": ["^nba-"] },
+              "buffer": 0.02, "size_multiplier": 0.5, "order_type": "gtd", "live": "skip" },
+            { "name": "ESPORTS", "match": { "tags": ["esports"] }, "live": "only", "live_buffer": 0.01 }
+        ]"#).unwrap();
+
+        let nba = rules.classify(&MarketFacts { series_id: Some("10345"), ..Default::default() });
+        assert_eq!((nba.name.as_str(), nba.order_type, nba.size_multiplier), ("NBA", Some("GTD"), 0.5));
+        assert!(nba.allows_live(None));
+        assert!(!nba.allows_live(Some(true)));
+
+        let tags = vec!["esports".to_string()];
+        let esports = rules.classify(&MarketFacts { tags: &tags, ..Default::default() });
+        assert!(!esports.allows_live(Some(false)));
+        assert!(esports.allows_live(Some(true)));
+
+        assert!(CategoryRules::from_json(r#"[{ "name": "X", "match": { "slug_regex": ["("] } }]"#).is_err());
+        assert!(CategoryRules::from_json(r#"[{ "name": "other" }]"#).is_err());
+        // Names must be unique and usable in CB_<NAME>_* keys
+        assert!(CategoryRules::from_json(r#"[{ "name": "NBA" }, { "name": "nba" }]"#).is_err());
+        assert!(CategoryRules::from_json(r#"[{ "name": "LA-LIGA" }]"#).is_err());
+        assert!(CategoryRules::from_json(r#"[{ "name": "LA_LIGA2" }]"#).is_ok());
+    }
+
+    #[test]
+    fn test_load_fallback() {
+        let missing = std::env::temp_dir().join("pm_whale_follower_no_such_rules.json");
+        // Only a missing default file falls back to the built-in rules
+        assert!(CategoryRules::load_from(&missing, false).is_ok());
+        assert!(CategoryRules::load_from(&missing, true).is_err());
+        // Any other read error is reported
+        assert!(CategoryRules::load_from(&std::env::temp_dir(), false).is_err());
+    }
+}
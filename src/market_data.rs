@@ -0,0 +1,514 @@
+//! Streaming order books from the CLOB market WebSocket
+//!
+//! One connection subscribes to the market channel for every watched token:
+//! tokens we hold (`set_held`) and tokens a whale traded recently (`watch`,
+//! expiring after `WATCH_TTL`). Each token gets a local L2 book built from the
+//! `book` snapshot and kept current with `price_change` deltas.
+//!
+//! The channel has no sequence numbers, so every update is checked instead:
+//! a delta for a book without a snapshot, a timestamp going backwards, a
+//! crossed book, or a local best bid/ask that disagrees with the one the
+//! server reports all mark the book out of sync. It is dropped and the token
+//! resubscribed for a fresh snapshot; deltas that arrive before the snapshot
+//! are ignored rather than triggering another resync.
+//!
+//! Readers call `book(token)`, which is lock-free and returns None while a
+//! token is not streamed or is resyncing, so callers fall back to REST.
+
+use arc_swap::ArcSwap;
+use futures::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+
+pub const MARKET_WS_URL: &str = "wss://ws-subscriptions-clob.polymarket.com/ws/market";
+
+/// How long a token stays subscribed after the last whale trade in it
+pub const WATCH_TTL: Duration = Duration::from_secs(30 * 60);
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+const WATCH_SYNC_INTERVAL: Duration = Duration::from_secs(1);
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// Prices are keyed in 1/10000ths so levels compare exactly
+const PRICE_SCALE: f64 = 10_000.0;
+
+// Published snapshots, swapped whole on every update
+static BOOKS: Lazy<ArcSwap<HashMap<String, Arc<BookSnapshot>>>> =
+    Lazy::new(|| ArcSwap::from_pointee(HashMap::new()));
+
+#[derive(Default)]
+struct WatchSet {
+    held: HashSet<String>,
+    recent: HashMap<String, Instant>,
+}
+
+static WATCHED: Lazy<Mutex<WatchSet>> = Lazy::new(|| Mutex::new(WatchSet::default()));
+
+// ============================================================================
+// Snapshot API
+// ============================================================================
+
+/// Immutable view of one token's book
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookSnapshot {
+    /// `(price, size)`, best (highest) first
+    pub bids: Vec<(f64, f64)>,
+    /// `(price, size)`, best (lowest) first
+    pub asks: Vec<(f64, f64)>,
+    /// Server timestamp of the last applied update, unix ms
+    pub timestamp_ms: u64,
+}
+
+impl BookSnapshot {
+    pub fn best_bid(&self) -> Option<(f64, f64)> {
+        self.bids.first().copied()
+    }
+
+    pub fn best_ask(&self) -> Option<(f64, f64)> {
+        self.asks.first().copied()
+    }
+
+    /// The side an order would take from: asks for a buy, bids for a sell
+    pub fn taking_side(&self, side_is_buy: bool) -> &[(f64, f64)] {
+        if side_is_buy { &self.asks } else { &self.bids }
+    }
+}
+
+/// Current streamed book of a token, if it is subscribed and in sync
+pub fn book(token_id: &str) -> Option<Arc<BookSnapshot>> {
+    BOOKS.load().get(token_id).cloned()
+}
+
+/// Stream a token a whale just traded
+pub fn watch(token_id: &str) {
+    WATCHED.lock().unwrap().recent.insert(token_id.to_string(), Instant::now());
+}
+
+/// Replace the set of held tokens, which stay streamed while held
+pub fn set_held(tokens: impl IntoIterator<Item = String>) {
+    WATCHED.lock().unwrap().held = tokens.into_iter().collect();
+}
+
+fn watched_tokens(now: Instant) -> HashSet<String> {
+    let mut w = WATCHED.lock().unwrap();
+    w.recent.retain(|_, seen| now.duration_since(*seen) < WATCH_TTL);
+    w.held.iter().chain(w.recent.keys()).cloned().collect()
+}
+
+// ============================================================================
+// Local L2 Books
+// ============================================================================
+
+#[derive(Debug, Default)]
+struct L2Book {
+    bids: BTreeMap<u32, f64>,
+    asks: BTreeMap<u32, f64>,
+    timestamp_ms: u64,
+}
+
+impl L2Book {
+    fn set_level(&mut self, is_bid: bool, price: f64, size: f64) {
+        let key = (price * PRICE_SCALE).round() as u32;
+        let side = if is_bid { &mut self.bids } else { &mut self.asks };
+        if size > 0.0 {
+            side.insert(key, size);
+        } else {
+            side.remove(&key);
+        }
+    }
+
+    fn best_bid(&self) -> Option<f64> {
+        self.bids.keys().next_back().map(|&k| k as f64 / PRICE_SCALE)
+    }
+
+    fn best_ask(&self) -> Option<f64> {
+        self.asks.keys().next().map(|&k| k as f64 / PRICE_SCALE)
+    }
+
+    fn crossed(&self) -> bool {
+        matches!((self.best_bid(), self.best_ask()), (Some(b), Some(a)) if b >= a)
+    }
+
+    fn snapshot(&self) -> BookSnapshot {
+        let level = |(&k, &s): (&u32, &f64)| (k as f64 / PRICE_SCALE, s);
+        BookSnapshot {
+            bids: self.bids.iter().rev().map(level).collect(),
+            asks: self.asks.iter().map(level).collect(),
+            timestamp_ms: self.timestamp_ms,
+        }
+    }
+}
+
+/// Why a book was dropped for a resync
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncError {
+    /// Delta for a token without a snapshot
+    NoSnapshot,
+    /// Update older than the last one applied
+    OutOfOrder,
+    Crossed,
+    /// Local best bid/ask differs from the server's
+    BestMismatch,
+}
+
+/// Outcome of one message for one token
+#[derive(Debug, Clone, PartialEq)]
+enum BookUpdate {
+    Updated(String),
+    Resync(String, SyncError),
+}
+
+#[derive(Default)]
+struct BookSet {
+    books: HashMap<String, L2Book>,
+    /// Subscribed or resubscribed tokens whose `book` snapshot has not arrived
+    awaiting_snapshot: HashSet<String>,
+}
+
+impl BookSet {
+    /// Tokens just (re)subscribed: their deltas are ignored until the snapshot
+    fn expect_snapshot<'a>(&mut self, assets: impl IntoIterator<Item = &'a String>) {
+        self.awaiting_snapshot.extend(assets.into_iter().cloned());
+    }
+
+    fn forget(&mut self, asset: &str) {
+        self.books.remove(asset);
+        self.awaiting_snapshot.remove(asset);
+    }
+
+    /// Apply one WebSocket frame, which may hold a single event or an array
+    fn handle_message(&mut self, text: &str) -> Vec<BookUpdate> {
+        let Ok(val) = serde_json::from_str::<Value>(text) else {
+            return Vec::new();
+        };
+        let events = match val {
+            Value::Array(events) => events,
+            event => vec![event],
+        };
+        let mut updates = Vec::new();
+        for event in &events {
+            match event["event_type"].as_str() {
+                Some("book") => updates.extend(self.apply_book(event)),
+                Some("price_change") => updates.extend(self.apply_price_change(event)),
+                _ => {}
+            }
+        }
+        // Each resync resubscribes, which sends a fresh snapshot
+        for update in &updates {
+            if let BookUpdate::Resync(asset, _) = update {
+                self.awaiting_snapshot.insert(asset.clone());
+            }
+        }
+        updates
+    }
+
+    fn apply_book(&mut self, event: &Value) -> Option<BookUpdate> {
+        let asset = event["asset_id"].as_str()?.to_string();
+        let mut book = L2Book { timestamp_ms: parse_num(&event["timestamp"]).unwrap_or(0.0) as u64, ..Default::default() };
+        for (key, is_bid) in [("bids", true), ("buys", true), ("asks", false), ("sells", false)] {
+            for lvl in event[key].as_array().into_iter().flatten() {
+                if let (Some(p), Some(s)) = (parse_num(&lvl["price"]), parse_num(&lvl["size"])) {
+                    book.set_level(is_bid, p, s);
+                }
+            }
+        }
+        if book.crossed() {
+            self.books.remove(&asset);
+            return Some(BookUpdate::Resync(asset, SyncError::Crossed));
+        }
+        self.awaiting_snapshot.remove(&asset);
+        self.books.insert(asset.clone(), book);
+        Some(BookUpdate::Updated(asset))
+    }
+
+    fn apply_price_change(&mut self, event: &Value) -> Vec<BookUpdate> {
+        let timestamp_ms = parse_num(&event["timestamp"]).unwrap_or(0.0) as u64;
+        // Current format: one entry per change with its own asset id.
+        // Older format: a single asset id with a `changes` array.
+        let changes: Vec<&Value> = match event["price_changes"].as_array() {
+            Some(arr) => arr.iter().collect(),
+            None => event["changes"].as_array().map(|arr| arr.iter().collect()).unwrap_or_default(),
+        };
+
+        let mut touched: Vec<String> = Vec::new();
+        let mut failed: HashMap<String, SyncError> = HashMap::new();
+        for change in changes {
+            let Some(asset) = change["asset_id"].as_str().or_else(|| event["asset_id"].as_str()) else {
+                continue;
+            };
+            if failed.contains_key(asset) || self.awaiting_snapshot.contains(asset) {
+                continue;
+            }
+            if let Err(e) = self.apply_change(asset, change, timestamp_ms) {
+                self.books.remove(asset);
+                failed.insert(asset.to_string(), e);
+            } else if !touched.iter().any(|t| t == asset) {
+                touched.push(asset.to_string());
+            }
+        }
+
+        let mut updates: Vec<BookUpdate> = Vec::new();
+        for asset in touched {
+            if failed.contains_key(&asset) {
+                continue;
+            }
+            match self.books.get(&asset) {
+                Some(book) if book.crossed() => {
+                    self.books.remove(&asset);
+                    updates.push(BookUpdate::Resync(asset, SyncError::Crossed));
+                }
+                Some(_) => updates.push(BookUpdate::Updated(asset)),
+                None => {}
+            }
+        }
+        updates.extend(failed.into_iter().map(|(asset, e)| BookUpdate::Resync(asset, e)));
+        updates
+    }
+
+    fn apply_change(&mut self, asset: &str, change: &Value, timestamp_ms: u64) -> Result<(), SyncError> {
+        let book = self.books.get_mut(asset).ok_or(SyncError::NoSnapshot)?;
+        if timestamp_ms < book.timestamp_ms {
+            return Err(SyncError::OutOfOrder);
+        }
+        let (Some(price), Some(size)) = (parse_num(&change["price"]), parse_num(&change["size"])) else {
+            return Ok(());
+        };
+        let is_bid = change["side"].as_str().is_some_and(|s| s.eq_ignore_ascii_case("BUY"));
+        book.set_level(is_bid, price, size);
+        book.timestamp_ms = timestamp_ms;
+
+        // The server's view after this change, when it sends one
+        let agrees = |server: &Value, local: Option<f64>| match (parse_num(server), local) {
+            (Some(s), Some(l)) => (s - l).abs() < 0.5 / PRICE_SCALE,
+            (Some(s), None) => s == 0.0,
+            (None, _) => true,
+        };
+        if !agrees(&change["best_bid"], book.best_bid()) || !agrees(&change["best_ask"], book.best_ask()) {
+            return Err(SyncError::BestMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Numbers arrive as strings; accept plain numbers too
+fn parse_num(v: &Value) -> Option<f64> {
+    v.as_str().and_then(|s| s.parse().ok()).or_else(|| v.as_f64())
+}
+
+// ============================================================================
+// Stream Task
+// ============================================================================
+
+fn publish(books: &BookSet, updates: &[BookUpdate]) {
+    let mut map = HashMap::clone(&BOOKS.load());
+    for update in updates {
+        match update {
+            BookUpdate::Updated(asset) => {
+                if let Some(book) = books.books.get(asset) {
+                    map.insert(asset.clone(), Arc::new(book.snapshot()));
+                }
+            }
+            BookUpdate::Resync(asset, _) => {
+                map.remove(asset);
+            }
+        }
+    }
+    BOOKS.store(Arc::new(map));
+}
+
+/// Keep only the books of still-watched tokens visible to readers
+fn retain_published(watched: &HashSet<String>) {
+    let map: HashMap<_, _> = BOOKS.load().iter()
+        .filter(|(k, _)| watched.contains(*k))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    BOOKS.store(Arc::new(map));
+}
+
+fn subscription(assets: &[&String], operation: Option<&str>) -> String {
+    match operation {
+        None => json!({ "assets_ids": assets, "type": "market" }).to_string(),
+        Some(op) => json!({ "assets_ids": assets, "operation": op }).to_string(),
+    }
+}
+
+/// Run the market stream forever, reconnecting on errors
+pub fn spawn_market_stream() -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async {
+        loop {
+            let watched = watched_tokens(Instant::now());
+            if watched.is_empty() {
+                tokio::time::sleep(WATCH_SYNC_INTERVAL).await;
+                continue;
+            }
+            if let Err(e) = run_market_stream(watched).await {
+                eprintln!("⚠️ Market stream: {} - reconnecting", e);
+            }
+            // Nothing is in sync while disconnected
+            BOOKS.store(Arc::new(HashMap::new()));
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    })
+}
+
+async fn run_market_stream(mut subscribed: HashSet<String>) -> anyhow::Result<()> {
+    let (ws, _) = connect_async(MARKET_WS_URL).await?;
+    let (mut write, mut read) = ws.split();
+    let initial: Vec<&String> = subscribed.iter().collect();
+    write.send(Message::Text(subscription(&initial, None))).await?;
+
+    let mut books = BookSet::default();
+    books.expect_snapshot(&subscribed);
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    let mut sync = tokio::time::interval(WATCH_SYNC_INTERVAL);
+
+    loop {
+        tokio::select! {
+            msg = read.next() => {
+                let text = match msg {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => anyhow::bail!("connection closed"),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                };
+                let updates = books.handle_message(&text);
+                if updates.is_empty() {
+                    continue;
+                }
+                publish(&books, &updates);
+                let mut resync: Vec<&String> = Vec::new();
+                for update in &updates {
+                    if let BookUpdate::Resync(asset, reason) = update {
+                        eprintln!("⚠️ Book {} out of sync ({:?}), resubscribing", asset, reason);
+                        resync.push(asset);
+                    }
+                }
+                if !resync.is_empty() {
+                    // A fresh subscription starts with a full `book` snapshot
+                    write.send(Message::Text(subscription(&resync, Some("unsubscribe")))).await?;
+                    write.send(Message::Text(subscription(&resync, Some("subscribe")))).await?;
+                }
+            }
+            _ = ping.tick() => {
+                write.send(Message::Text("PING".into())).await?;
+            }
+            _ = sync.tick() => {
+                let watched = watched_tokens(Instant::now());
+                let added: Vec<&String> = watched.difference(&subscribed).collect();
+                let removed: Vec<&String> = subscribed.difference(&watched).collect();
+                if !added.is_empty() {
+                    write.send(Message::Text(subscription(&added, Some("subscribe")))).await?;
+                    books.expect_snapshot(added.iter().copied());
+                }
+                if !removed.is_empty() {
+                    write.send(Message::Text(subscription(&removed, Some("unsubscribe")))).await?;
+                    for asset in &removed {
+                        books.forget(asset);
+                    }
+                    retain_published(&watched);
+                }
+                subscribed = watched;
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot_msg(asset: &str, ts: u64) -> String {
+        json!([{
+            "event_type": "book", "asset_id": asset, "timestamp": ts.to_string(),
+            "bids": [{ "price": "0.48", "size": "100" }, { "price": "0.49", "size": "50" }],
+            "asks": [{ "price": "0.53", "size": "70" }, { "price": "0.51", "size": "20" }],
+        }]).to_string()
+    }
+
+    fn change_msg(asset: &str, ts: u64, side: &str, price: &str, size: &str, best_bid: &str, best_ask: &str) -> String {
+        json!({
+            "event_type": "price_change", "timestamp": ts.to_string(),
+            "price_changes": [{ "asset_id": asset, "price": price, "size": size, "side": side,
+                                "best_bid": best_bid, "best_ask": best_ask }],
+        }).to_string()
+    }
+
+    #[test]
+    fn test_snapshot_and_deltas() {
+        let mut set = BookSet::default();
+        assert_eq!(set.handle_message(&snapshot_msg("1", 100)), vec![BookUpdate::Updated("1".into())]);
+        let snap = set.books["1"].snapshot();
+        assert_eq!(snap.best_bid(), Some((0.49, 50.0)));
+        assert_eq!(snap.best_ask(), Some((0.51, 20.0)));
+        assert_eq!(snap.taking_side(true), &[(0.51, 20.0), (0.53, 70.0)]);
+
+        // Best ask lifted, new bid level
+        set.handle_message(&change_msg("1", 101, "SELL", "0.51", "0", "0.49", "0.53"));
+        set.handle_message(&change_msg("1", 102, "BUY", "0.50", "10", "0.5", "0.53"));
+        let snap = set.books["1"].snapshot();
+        assert_eq!(snap.best_ask(), Some((0.53, 70.0)));
+        assert_eq!(snap.best_bid(), Some((0.50, 10.0)));
+        assert_eq!(snap.timestamp_ms, 102);
+    }
+
+    #[test]
+    fn test_sequence_checks_force_resync() {
+        let mut set = BookSet::default();
+        // Delta before any snapshot
+        assert_eq!(
+            set.handle_message(&change_msg("1", 100, "BUY", "0.50", "10", "0.5", "0.51")),
+            vec![BookUpdate::Resync("1".into(), SyncError::NoSnapshot)]
+        );
+
+        set.handle_message(&snapshot_msg("1", 100));
+        assert_eq!(
+            set.handle_message(&change_msg("1", 99, "BUY", "0.50", "10", "0.5", "0.51")),
+            vec![BookUpdate::Resync("1".into(), SyncError::OutOfOrder)]
+        );
+        assert!(!set.books.contains_key("1"));
+
+        // The server's best bid says we missed an update
+        set.handle_message(&snapshot_msg("1", 100));
+        assert_eq!(
+            set.handle_message(&change_msg("1", 101, "BUY", "0.47", "10", "0.5", "0.51")),
+            vec![BookUpdate::Resync("1".into(), SyncError::BestMismatch)]
+        );
+
+        set.handle_message(&snapshot_msg("1", 100));
+        let crossed = json!({
+            "event_type": "price_change", "asset_id": "1", "timestamp": "101",
+            "changes": [{ "price": "0.52", "size": "5", "side": "BUY" }],
+        }).to_string();
+        assert_eq!(set.handle_message(&crossed), vec![BookUpdate::Resync("1".into(), SyncError::Crossed)]);
+    }
+
+    #[test]
+    fn test_deltas_ignored_until_resync_snapshot() {
+        let mut set = BookSet::default();
+        set.handle_message(&snapshot_msg("1", 100));
+        assert_eq!(
+            set.handle_message(&change_msg("1", 99, "BUY", "0.50", "10", "0.5", "0.51")),
+            vec![BookUpdate::Resync("1".into(), SyncError::OutOfOrder)]
+        );
+        // Deltas already in flight do not resubscribe again
+        assert!(set.handle_message(&change_msg("1", 101, "BUY", "0.50", "10", "0.5", "0.51")).is_empty());
+        assert!(set.handle_message(&change_msg("1", 102, "SELL", "0.51", "0", "0.49", "0.53")).is_empty());
+
+        set.handle_message(&snapshot_msg("1", 103));
+        assert_eq!(
+            set.handle_message(&change_msg("1", 104, "BUY", "0.50", "10", "0.5", "0.51")),
+            vec![BookUpdate::Updated("1".into())]
+        );
+
+        // A newly subscribed token waits for its first snapshot the same way
+        set.expect_snapshot([&"2".to_string()]);
+        assert!(set.handle_message(&change_msg("2", 100, "BUY", "0.50", "10", "0.5", "0.51")).is_empty());
+    }
+}
@@ -0,0 +1,378 @@
+//! Per-token market metadata
+//!
+//! One `MarketMeta` record per token holds what the bot needs to know about
+//! its market: condition id, outcome, tick size, neg-risk, fee, end time, live
+//! status, tags, series and sport. Each field carries the time it was fetched and goes
+//! stale on its own TTL: live status in minutes, trading parameters in an hour,
+//! the rest in a day.
+//!
+//! Lookups never block. A missing or stale field returns None and queues the
+//! token; a background task fetches queued tokens from Gamma in batches by
+//! `clob_token_ids` and persists everything to `META_FILE`.
+
+use arc_swap::ArcSwap;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const GAMMA_API_BASE: &str = "https://gamma-api.polymarket.com";
+
+pub const META_FILE: &str = ".market_meta.json";
+
+/// Tokens per Gamma request; both outcomes of a market come back together
+const BATCH_SIZE: usize = 40;
+const FETCH_INTERVAL: Duration = Duration::from_millis(250);
+const PERSIST_INTERVAL: Duration = Duration::from_secs(60);
+
+const LIVE_TTL_SECS: i64 = 2 * 60;
+const TRADING_TTL_SECS: i64 = 60 * 60;
+const STATIC_TTL_SECS: i64 = 24 * 60 * 60;
+
+const SPORTS: [&str; 10] = [
+    "tennis", "soccer", "basketball", "football", "baseball", "hockey", "cricket", "mma", "golf", "esports",
+];
+
+static META: Lazy<ArcSwap<HashMap<String, Arc<MarketMeta>>>> =
+    Lazy::new(|| ArcSwap::from_pointee(HashMap::new()));
+static PENDING: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+// ============================================================================
+// Record
+// ============================================================================
+
+/// A value and when it was fetched (unix seconds)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timed<T> {
+    pub value: T,
+    pub at: i64,
+}
+
+impl<T> Timed<T> {
+    fn new(value: T, at: i64) -> Self {
+        Self { value, at }
+    }
+}
+
+fn fresh<T>(field: &Option<Timed<T>>, ttl_secs: i64, now: i64) -> Option<&T> {
+    field.as_ref().filter(|t| now - t.at < ttl_secs).map(|t| &t.value)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MarketMeta {
+    pub condition_id: Option<Timed<String>>,
+    pub outcome: Option<Timed<String>>,
+    pub slug: Option<Timed<String>>,
+    pub event_slug: Option<Timed<String>>,
+    pub tick_size: Option<Timed<f64>>,
+    pub neg_risk: Option<Timed<bool>>,
+    pub fee_bps: Option<Timed<f64>>,
+    /// RFC 3339 as Gamma reports it
+    pub end_time: Option<Timed<String>>,
+    pub live: Option<Timed<bool>>,
+    pub tags: Option<Timed<Vec<String>>>,
+    /// Gamma series of the event, e.g. one league season
+    pub series_id: Option<Timed<String>>,
+    pub sport: Option<Timed<String>>,
+}
+
+impl MarketMeta {
+    pub fn condition_id(&self, now: i64) -> Option<&str> {
+        fresh(&self.condition_id, STATIC_TTL_SECS, now).map(String::as_str)
+    }
+
+    pub fn outcome(&self, now: i64) -> Option<&str> {
+        fresh(&self.outcome, STATIC_TTL_SECS, now).map(String::as_str)
+    }
+
+    pub fn slug(&self, now: i64) -> Option<&str> {
+        fresh(&self.slug, STATIC_TTL_SECS, now).map(String::as_str)
+    }
+
+    pub fn event_slug(&self, now: i64) -> Option<&str> {
+        fresh(&self.event_slug, STATIC_TTL_SECS, now).map(String::as_str)
+    }
+
+    pub fn tick_size(&self, now: i64) -> Option<f64> {
+        fresh(&self.tick_size, TRADING_TTL_SECS, now).copied()
+    }
+
+    pub fn neg_risk(&self, now: i64) -> Option<bool> {
+        fresh(&self.neg_risk, STATIC_TTL_SECS, now).copied()
+    }
+
+    pub fn fee_bps(&self, now: i64) -> Option<f64> {
+        fresh(&self.fee_bps, TRADING_TTL_SECS, now).copied()
+    }
+
+    pub fn end_time(&self, now: i64) -> Option<&str> {
+        fresh(&self.end_time, STATIC_TTL_SECS, now).map(String::as_str)
+    }
+
+    pub fn live(&self, now: i64) -> Option<bool> {
+        fresh(&self.live, LIVE_TTL_SECS, now).copied()
+    }
+
+    pub fn tags(&self, now: i64) -> Option<&[String]> {
+        fresh(&self.tags, STATIC_TTL_SECS, now).map(Vec::as_slice)
+    }
+
+    pub fn series_id(&self, now: i64) -> Option<&str> {
+        fresh(&self.series_id, STATIC_TTL_SECS, now).map(String::as_str)
+    }
+
+    pub fn sport(&self, now: i64) -> Option<&str> {
+        fresh(&self.sport, STATIC_TTL_SECS, now).map(String::as_str)
+    }
+
+    /// Whether any field needs refetching
+    fn stale(&self, now: i64) -> bool {
+        self.condition_id(now).is_none() || self.live(now).is_none()
+            || (self.tick_size.is_some() && self.tick_size(now).is_none())
+    }
+}
+
+// ============================================================================
+// Lookups
+// ============================================================================
+
+/// Metadata of a token as cached, fresh or not. Queues a refetch when any
+/// field is missing or stale.
+pub fn get(token_id: &str) -> Option<Arc<MarketMeta>> {
+    let meta = META.load().get(token_id).cloned();
+    if meta.as_ref().is_none_or(|m| m.stale(Utc::now().timestamp())) {
+        request(token_id);
+    }
+    meta
+}
+
+/// Fresh live status, or None while it is being fetched
+pub fn is_live(token_id: &str) -> Option<bool> {
+    get(token_id)?.live(Utc::now().timestamp())
+}
+
+pub fn event_slug(token_id: &str) -> Option<String> {
+    get(token_id)?.event_slug(Utc::now().timestamp()).map(str::to_string)
+}
+
+pub fn slug(token_id: &str) -> Option<String> {
+    get(token_id)?.slug(Utc::now().timestamp()).map(str::to_string)
+}
+
+/// Fresh minimum price increment of the token's market
+pub fn tick_size(token_id: &str) -> Option<f64> {
+    get(token_id)?.tick_size(Utc::now().timestamp())
+}
+
+/// Queue a token for the next batch fetch
+pub fn request(token_id: &str) {
+    PENDING.lock().unwrap().insert(token_id.to_string());
+}
+
+// ============================================================================
+// Gamma Parsing
+// ============================================================================
+
+/// Gamma encodes some arrays as JSON strings
+fn string_array(v: &Value) -> Vec<String> {
+    let parsed;
+    let arr = match v {
+        Value::String(s) => {
+            parsed = serde_json::from_str::<Value>(s).unwrap_or(Value::Null);
+            parsed.as_array()
+        }
+        other => other.as_array(),
+    };
+    arr.map(|a| a.iter().filter_map(|x| x.as_str().map(str::to_string)).collect()).unwrap_or_default()
+}
+
+fn tag_labels(v: &Value) -> Vec<String> {
+    v.as_array().map(|a| a.iter()
+        .filter_map(|t| t["slug"].as_str().or_else(|| t["label"].as_str()).or_else(|| t.as_str()))
+        .map(str::to_lowercase)
+        .collect()
+    ).unwrap_or_default()
+}
+
+/// One record per token of a Gamma market object
+fn parse_market(market: &Value, now: i64) -> Vec<(String, MarketMeta)> {
+    let tokens = string_array(&market["clobTokenIds"]);
+    let outcomes = string_array(&market["outcomes"]);
+    let event = &market["events"][0];
+    let timed_str = |v: &Value| v.as_str().map(|s| Timed::new(s.to_string(), now));
+
+    let mut tags = tag_labels(&market["tags"]);
+    for t in tag_labels(&event["tags"]) {
+        if !tags.contains(&t) {
+            tags.push(t);
+        }
+    }
+    let sport = SPORTS.iter().find(|s| tags.iter().any(|t| t == *s)).map(|s| Timed::new(s.to_string(), now));
+    let series = &event["series"][0]["id"];
+    let series_id = series.as_str().map(str::to_string).or_else(|| series.as_u64().map(|id| id.to_string()));
+    let fee = market["takerBaseFee"].as_f64().or_else(|| market["makerBaseFee"].as_f64());
+
+    let base = MarketMeta {
+        condition_id: timed_str(&market["conditionId"]),
+        outcome: None,
+        slug: timed_str(&market["slug"]),
+        event_slug: timed_str(&event["slug"]),
+        tick_size: market["orderPriceMinTickSize"].as_f64().map(|t| Timed::new(t, now)),
+        neg_risk: Some(Timed::new(market["negRisk"].as_bool().unwrap_or(false), now)),
+        fee_bps: fee.map(|f| Timed::new(f, now)),
+        end_time: timed_str(&market["endDate"]),
+        live: Some(Timed::new(event["live"].as_bool().unwrap_or(false), now)),
+        tags: Some(Timed::new(tags, now)),
+        series_id: series_id.map(|id| Timed::new(id, now)),
+        sport,
+    };
+    tokens.into_iter().enumerate().map(|(i, token)| {
+        let outcome = outcomes.get(i).map(|o| Timed::new(o.clone(), now));
+        (token, MarketMeta { outcome, ..base.clone() })
+    }).collect()
+}
+
+// ============================================================================
+// Warmup and Persistence
+// ============================================================================
+
+/// Fetch metadata for `tokens` in batches and merge it into the cache
+pub async fn warmup(client: &reqwest::Client, tokens: &[String]) -> usize {
+    let mut fetched = 0;
+    for chunk in tokens.chunks(BATCH_SIZE) {
+        let query: Vec<(&str, &str)> = chunk.iter().map(|t| ("clob_token_ids", t.as_str())).collect();
+        let resp = client.get(format!("{}/markets", GAMMA_API_BASE))
+            .query(&query)
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await;
+        let markets: Value = match resp {
+            Ok(r) => match r.json().await {
+                Ok(v) => v,
+                Err(_) => continue,
+            },
+            Err(_) => continue,
+        };
+
+        let now = Utc::now().timestamp();
+        let records: Vec<(String, MarketMeta)> = markets.as_array().into_iter().flatten()
+            .flat_map(|m| parse_market(m, now))
+            .collect();
+        fetched += records.len();
+        let mut map = HashMap::clone(&META.load());
+        for (token, meta) in records {
+            map.insert(token, Arc::new(meta));
+        }
+        META.store(Arc::new(map));
+    }
+    fetched
+}
+
+/// Load the persisted cache. A missing or unreadable file starts empty.
+pub fn load(path: impl AsRef<Path>) -> usize {
+    let map: HashMap<String, MarketMeta> = fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    let count = map.len();
+    META.store(Arc::new(map.into_iter().map(|(k, v)| (k, Arc::new(v))).collect()));
+    count
+}
+
+pub fn save(path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let snapshot = META.load();
+    let map: HashMap<&String, &MarketMeta> = snapshot.iter().map(|(k, v)| (k, v.as_ref())).collect();
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string(&map)?)?;
+    fs::rename(tmp, path)?;
+    Ok(())
+}
+
+/// Fetch queued tokens in batches and persist the cache periodically
+pub fn spawn_meta_task(path: &'static str) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let client = reqwest::Client::builder().no_proxy().build().unwrap_or_default();
+        let mut fetch = tokio::time::interval(FETCH_INTERVAL);
+        let mut persist = tokio::time::interval(PERSIST_INTERVAL);
+        let mut dirty = false;
+        loop {
+            tokio::select! {
+                _ = fetch.tick() => {
+                    let tokens: Vec<String> = PENDING.lock().unwrap().drain().collect();
+                    if !tokens.is_empty() {
+                        dirty |= warmup(&client, &tokens).await > 0;
+                    }
+                }
+                _ = persist.tick() => {
+                    if dirty {
+                        match save(path) {
+                            Ok(()) => dirty = false,
+                            Err(e) => eprintln!("⚠️ Failed to save {}: {}", path, e),
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market() -> Value {
+        serde_json::json!({
+            "conditionId": "0xcond",
+            "slug": "atp-sinner-alcaraz",
+            "clobTokenIds": "[\"111\", \"222\"]",
+            "outcomes": "[\"Sinner\", \"Alcaraz\"]",
+            "orderPriceMinTickSize": 0.01,
+            "negRisk": false,
+            "takerBaseFee": 0,
+            "endDate": "2026-10-18T18:00:00Z",
+            "events": [{ "slug": "atp-shanghai-final", "live": true, "tags": [{ "slug": "tennis" }, { "slug": "sports" }],
+                          "series": [{ "id": 10365 }] }],
+        })
+    }
+
+    #[test]
+    fn test_parse_market() {
+        let records = parse_market(&market(), 1_000);
+        assert_eq!(records.len(), 2);
+        let (token, meta) = &records[1];
+        assert_eq!(token, "222");
+        assert_eq!(meta.outcome(1_000), Some("Alcaraz"));
+        assert_eq!(meta.condition_id(1_000), Some("0xcond"));
+        assert_eq!(meta.event_slug(1_000), Some("atp-shanghai-final"));
+        assert_eq!(meta.tick_size(1_000), Some(0.01));
+        assert_eq!(meta.live(1_000), Some(true));
+        assert_eq!(meta.sport(1_000), Some("tennis"));
+        assert_eq!(meta.series_id(1_000), Some("10365"));
+        assert_eq!(meta.fee_bps(1_000), Some(0.0));
+    }
+
+    #[test]
+    fn test_fields_expire_on_their_own_ttl() {
+        let (_, meta) = parse_market(&market(), 0).remove(0);
+        assert!(!meta.stale(60));
+
+        // Live status goes stale first, the market identity much later
+        let later = LIVE_TTL_SECS;
+        assert_eq!(meta.live(later), None);
+        assert_eq!(meta.tick_size(later), Some(0.01));
+        assert!(meta.stale(later));
+        assert_eq!(meta.tick_size(TRADING_TTL_SECS), None);
+        assert_eq!(meta.slug(TRADING_TTL_SECS), Some("atp-sinner-alcaraz"));
+        assert_eq!(meta.slug(STATIC_TTL_SECS), None);
+    }
+}
@@ -0,0 +1,489 @@
+//! Mempool event source
+//!
+//! Decodes the whale's pending exchange transactions from Alchemy's
+//! `alchemy_pendingTransactions` subscription so the main pipeline can act
+//! before the fill is mined. Calldata of the CTF and neg-risk exchanges
+//! (`fillOrder`, `fillOrders`, `matchOrders`, identical ABI on both) is
+//! ABI-decoded, so the whale's orders are found whether it is a maker or the
+//! taker. A pending transaction is later reconciled against
+//! its confirmed `OrdersFilled` log(s): `PendingTracker` remembers which
+//! transactions were acted on from the mempool, so the confirmation is not
+//! copied a second time and transactions that never land can be flagged.
+
+use anyhow::{Result, anyhow};
+use alloy::primitives::{Address, U256};
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use memchr::memmem;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::preflight;
+use crate::providers::ProviderConfig;
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// Exchange contracts whose pending transactions are watched
+pub const WATCH_ADDRESSES: [&str; 4] = [
+    "0xB768891e3130F6dF18214Ac804d4DB76c2C37730",
+    "0xaB45c5A4B0c941a2F231C04C3f49182e1A254052",
+    "0x125914B1d921D83367A0478f4ea6447AFf2Ee9DA",
+    "0xE3f18aCc55091e2c48d883fc8C8413319d4Ab7b0",
+];
+
+/// `log_index` of the first fill taken from a pending transaction, which has no
+/// log yet; further fills of the same transaction count down from it
+pub const PENDING_LOG_INDEX: u64 = u64::MAX;
+/// Fills of one pending transaction that get a key of their own
+const MAX_PENDING_FILLS: u64 = 1024;
+/// A pending transaction not mined within this window is treated as dropped
+pub const PENDING_CONFIRM_TIMEOUT: Duration = Duration::from_secs(90);
+/// Confirmed entries are kept this long to absorb duplicate confirmation logs
+const CONFIRMED_RETENTION: Duration = Duration::from_secs(600);
+
+// TARGET_WHALE_ADDRESS as lowercase hex without 0x; empty when unset or
+// invalid, which `check_whale_filter` rejects at startup
+static WHALE_FILTER_LOWER: Lazy<Vec<u8>> = Lazy::new(|| {
+    std::env::var("TARGET_WHALE_ADDRESS").ok()
+        .and_then(|addr| preflight::validate_address("TARGET_WHALE_ADDRESS", &addr).ok())
+        .map(|hex| hex.to_lowercase().into_bytes())
+        .unwrap_or_default()
+});
+
+static WHALE_ADDRESS: Lazy<Option<Address>> = Lazy::new(|| {
+    std::str::from_utf8(&WHALE_FILTER_LOWER).ok().and_then(|hex| Address::from_str(hex).ok())
+});
+
+// Pre-built SIMD finder over the static pattern
+static WHALE_FILTER_FINDER: Lazy<memmem::Finder<'static>> = Lazy::new(|| {
+    memmem::Finder::new(WHALE_FILTER_LOWER.as_slice())
+});
+
+/// Fail at startup, rather than on the first pending transaction, when the
+/// whale address cannot be used as the mempool filter.
+pub fn check_whale_filter() -> Result<()> {
+    if WHALE_ADDRESS.is_none() {
+        return Err(anyhow!("TARGET_WHALE_ADDRESS must be a 40-char hex address to filter the mempool"));
+    }
+    Ok(())
+}
+
+/// `log_index` of the `n`th fill taken from one pending transaction
+pub fn pending_log_index(n: usize) -> u64 {
+    PENDING_LOG_INDEX - (n as u64).min(MAX_PENDING_FILLS - 1)
+}
+
+/// Whether `log_index` belongs to a fill taken from the mempool
+pub fn is_pending_log_index(log_index: u64) -> bool {
+    log_index > PENDING_LOG_INDEX - MAX_PENDING_FILLS
+}
+
+sol! {
+    /// CTF exchange order, shared by the neg-risk exchange
+    struct Order {
+        uint256 salt;
+        address maker;
+        address signer;
+        address taker;
+        uint256 tokenId;
+        uint256 makerAmount;
+        uint256 takerAmount;
+        uint256 expiration;
+        uint256 nonce;
+        uint256 feeRateBps;
+        uint8 side;
+        uint8 signatureType;
+        bytes signature;
+    }
+
+    interface ICtfExchange {
+        function fillOrder(Order order, uint256 fillAmount) external;
+        function fillOrders(Order[] orders, uint256[] fillAmounts) external;
+        function matchOrders(Order takerOrder, Order[] makerOrders, uint256 takerFillAmount, uint256[] makerFillAmounts) external;
+    }
+}
+
+// ============================================================================
+// Providers & Subscription
+// ============================================================================
+
+/// `alchemy_pendingTransactions` is Alchemy-only, so only Alchemy endpoints
+/// can carry the mempool subscription.
+pub fn mempool_providers(providers: &[ProviderConfig]) -> Vec<ProviderConfig> {
+    providers
+        .iter()
+        .filter(|p| p.wss_url.as_deref().is_some_and(|u| u.contains("alchemy.com")))
+        .cloned()
+        .collect()
+}
+
+pub fn subscribe_request() -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_subscribe",
+        "params": ["alchemy_pendingTransactions", {
+            "toAddress": WATCH_ADDRESSES,
+            "hashesOnly": false
+        }]
+    }).to_string()
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+/// A whale order decoded from pending calldata
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingFill {
+    pub tx_hash: String,
+    pub side_is_buy: bool,
+    /// Decimal CLOB token id
+    pub token_id: String,
+    pub usd_value: f64,
+    pub shares: f64,
+    pub price: f64,
+    /// The whale's order was the `takerOrder` of a `matchOrders` call
+    pub taker: bool,
+}
+
+/// Cheap pre-filter run on every message before any JSON parsing
+#[inline(always)]
+pub fn contains_whale(haystack: &[u8]) -> bool {
+    if WHALE_FILTER_LOWER.is_empty() {
+        return false;
+    }
+    if WHALE_FILTER_FINDER.find(haystack).is_some() {
+        return true;
+    }
+    find_ignore_case(haystack, &WHALE_FILTER_LOWER).is_some()
+}
+
+#[inline(never)]
+fn find_ignore_case(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.len() > haystack.len() { return None; }
+    haystack.windows(needle.len()).position(|w| {
+        w.iter().zip(needle.iter()).all(|(h, n)| h.to_ascii_lowercase() == *n)
+    })
+}
+
+/// Subscription id from the `eth_subscribe` reply
+#[inline]
+pub fn extract_subscription_id(text: &str) -> Option<&str> {
+    let idx = text.find("\"result\":\"")?;
+    let start = idx + 10;
+    let rest = text.get(start..)?;
+    let end = rest.find('"')?;
+    Some(&rest[..end])
+}
+
+/// Decode a pending-transaction notification for subscription `sub_id`: one
+/// fill per token and side the whale trades in the transaction.
+pub fn parse_pending_tx(text: &str, sub_id: Option<&str>) -> Vec<PendingFill> {
+    let Some(whale) = *WHALE_ADDRESS else { return Vec::new() };
+    let Ok(val) = serde_json::from_str::<Value>(text) else { return Vec::new() };
+    let params = &val["params"];
+    if sub_id.is_none() || params["subscription"].as_str() != sub_id {
+        return Vec::new();
+    }
+
+    let result = &params["result"];
+    let (Some(tx_hash), Some(input)) = (result["hash"].as_str(), result["input"].as_str()) else {
+        return Vec::new();
+    };
+    let Ok(input) = alloy::hex::decode(input) else { return Vec::new() };
+    pending_fills(tx_hash, decode_whale_orders(&input, whale))
+}
+
+/// Combine the whale's orders into one fill per (token, side), in calldata order
+fn pending_fills(tx_hash: &str, orders: Vec<WhaleOrder>) -> Vec<PendingFill> {
+    let mut combined: Vec<WhaleOrder> = Vec::new();
+    for o in orders {
+        match combined.iter_mut().find(|c| c.token_id == o.token_id && c.side_is_buy == o.side_is_buy) {
+            Some(c) => {
+                c.usd += o.usd;
+                c.shares += o.shares;
+            }
+            None => combined.push(o),
+        }
+    }
+
+    combined.into_iter().filter_map(|o| {
+        let usd = u256_to_f64(o.usd)? / 1e6;
+        let shares = u256_to_f64(o.shares)? / 1e6;
+        if shares <= 0.0 { return None; }
+        Some(PendingFill {
+            tx_hash: tx_hash.to_string(),
+            side_is_buy: o.side_is_buy,
+            token_id: o.token_id.to_string(),
+            usd_value: usd,
+            shares,
+            price: usd / shares,
+            taker: o.taker,
+        })
+    }).collect()
+}
+
+/// One of the whale's orders in exchange calldata, with the filled amounts
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhaleOrder {
+    pub side_is_buy: bool,
+    pub token_id: U256,
+    pub taker: bool,
+    /// USDC filled, 6 decimals
+    pub usd: U256,
+    /// Outcome tokens filled, 6 decimals
+    pub shares: U256,
+}
+
+/// Find every order of `whale` (as maker or signer) in `fillOrder`,
+/// `fillOrders` or `matchOrders` calldata. Other selectors yield nothing.
+pub fn decode_whale_orders(input: &[u8], whale: Address) -> Vec<WhaleOrder> {
+    let Some(selector) = input.get(..4) else { return Vec::new() };
+    let mut orders = Vec::new();
+
+    if selector == ICtfExchange::fillOrderCall::SELECTOR {
+        if let Ok(call) = ICtfExchange::fillOrderCall::abi_decode(input) {
+            orders.extend(whale_fill(&call.order, call.fillAmount, whale, false));
+        }
+    } else if selector == ICtfExchange::fillOrdersCall::SELECTOR {
+        if let Ok(call) = ICtfExchange::fillOrdersCall::abi_decode(input) {
+            for (order, amount) in call.orders.iter().zip(call.fillAmounts.iter()) {
+                orders.extend(whale_fill(order, *amount, whale, false));
+            }
+        }
+    } else if selector == ICtfExchange::matchOrdersCall::SELECTOR {
+        if let Ok(call) = ICtfExchange::matchOrdersCall::abi_decode(input) {
+            orders.extend(whale_fill(&call.takerOrder, call.takerFillAmount, whale, true));
+            for (order, amount) in call.makerOrders.iter().zip(call.makerFillAmounts.iter()) {
+                orders.extend(whale_fill(order, *amount, whale, false));
+            }
+        }
+    }
+    orders
+}
+
+/// Fill amounts are in units of the order's maker asset (USDC for a buy,
+/// outcome tokens for a sell); the other leg follows the order's price.
+fn whale_fill(order: &Order, fill_amount: U256, whale: Address, taker: bool) -> Option<WhaleOrder> {
+    if order.maker != whale && order.signer != whale {
+        return None;
+    }
+    if order.makerAmount.is_zero() || fill_amount.is_zero() {
+        return None;
+    }
+    let other = fill_amount.checked_mul(order.takerAmount)? / order.makerAmount;
+    let side_is_buy = order.side == 0;
+    let (usd, shares) = if side_is_buy { (fill_amount, other) } else { (other, fill_amount) };
+    Some(WhaleOrder { side_is_buy, token_id: order.tokenId, taker, usd, shares })
+}
+
+#[inline]
+fn u256_to_f64(v: U256) -> Option<f64> {
+    u128::try_from(v).ok().map(|x| x as f64)
+}
+
+// ============================================================================
+// Reconciliation
+// ============================================================================
+
+/// Result of matching a confirmed log against transactions acted on from the mempool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confirmation {
+    /// Not a transaction we saw pending: handle the log normally
+    NotPending,
+    /// Mined `after` the pending notification; `first` is false for further
+    /// logs of the same transaction
+    Confirmed { after: Duration, first: bool },
+}
+
+struct PendingEntry {
+    seen_at: Instant,
+    confirmed: bool,
+    /// `log_index` of every fill acted on; empty when the log came first
+    fills: HashSet<u64>,
+}
+
+impl PendingEntry {
+    fn new(now: Instant) -> Self {
+        Self { seen_at: now, confirmed: false, fills: HashSet::new() }
+    }
+}
+
+#[derive(Default)]
+pub struct PendingTracker {
+    txs: HashMap<String, PendingEntry>,
+}
+
+impl PendingTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one fill of a pending transaction. Returns false if that fill
+    /// was already seen, or the transaction's log already arrived (a lagging
+    /// mempool provider).
+    pub fn observe_pending(&mut self, tx_hash: &str, log_index: u64, now: Instant) -> bool {
+        let entry = self.txs.entry(tx_hash.to_lowercase()).or_insert_with(|| PendingEntry::new(now));
+        !entry.confirmed && entry.fills.insert(log_index)
+    }
+
+    /// Match a confirmed log's transaction. Transactions not acted on from the
+    /// mempool are remembered too, so a late pending notification is ignored.
+    pub fn confirm(&mut self, tx_hash: &str, now: Instant) -> Confirmation {
+        let e = self.txs.entry(tx_hash.to_lowercase()).or_insert_with(|| PendingEntry::new(now));
+        if e.fills.is_empty() {
+            e.confirmed = true;
+            return Confirmation::NotPending;
+        }
+        let first = !e.confirmed;
+        e.confirmed = true;
+        Confirmation::Confirmed { after: now.saturating_duration_since(e.seen_at), first }
+    }
+
+    /// Drop and return transactions still unconfirmed after `timeout`, and
+    /// forget old confirmed ones.
+    pub fn take_expired(&mut self, now: Instant, timeout: Duration) -> Vec<String> {
+        let mut expired = Vec::new();
+        self.txs.retain(|tx, e| {
+            let age = now.saturating_duration_since(e.seen_at);
+            if !e.confirmed && age >= timeout {
+                expired.push(tx.clone());
+                false
+            } else {
+                !(e.confirmed && age >= CONFIRMED_RETENTION)
+            }
+        });
+        expired
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn whale() -> Address {
+        Address::from_str("0x204f72f35326db932158cba6adff0b9a1da95e14").unwrap()
+    }
+
+    fn order(maker: Address, token: u64, maker_amount: u64, taker_amount: u64, side: u8) -> Order {
+        Order {
+            salt: U256::ZERO,
+            maker,
+            signer: maker,
+            taker: Address::ZERO,
+            tokenId: U256::from(token),
+            makerAmount: U256::from(maker_amount),
+            takerAmount: U256::from(taker_amount),
+            expiration: U256::ZERO,
+            nonce: U256::ZERO,
+            feeRateBps: U256::ZERO,
+            side,
+            signatureType: 0,
+            signature: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_decode_whale_as_taker() {
+        let other = Address::repeat_byte(0x11);
+        let call = ICtfExchange::matchOrdersCall {
+            // Whale buys 10 shares at 0.50 against two sellers
+            takerOrder: order(whale(), 42, 5_000_000, 10_000_000, 0),
+            makerOrders: vec![order(other, 42, 6_000_000, 3_000_000, 1), order(other, 42, 4_000_000, 2_000_000, 1)],
+            takerFillAmount: U256::from(5_000_000u64),
+            makerFillAmounts: vec![U256::from(6_000_000u64), U256::from(4_000_000u64)],
+        };
+        let orders = decode_whale_orders(&call.abi_encode(), whale());
+        assert_eq!(orders, vec![WhaleOrder {
+            side_is_buy: true,
+            token_id: U256::from(42u64),
+            taker: true,
+            usd: U256::from(5_000_000u64),
+            shares: U256::from(10_000_000u64),
+        }]);
+    }
+
+    #[test]
+    fn test_decode_whale_as_maker() {
+        let other = Address::repeat_byte(0x11);
+        let call = ICtfExchange::matchOrdersCall {
+            takerOrder: order(other, 7, 3_000_000, 6_000_000, 0),
+            // Whale sells 10 shares at 0.40, only 4 of them are filled here
+            makerOrders: vec![order(other, 7, 2_000_000, 1_000_000, 1), order(whale(), 7, 10_000_000, 4_000_000, 1)],
+            takerFillAmount: U256::from(2_600_000u64),
+            makerFillAmounts: vec![U256::from(2_000_000u64), U256::from(4_000_000u64)],
+        };
+        let orders = decode_whale_orders(&call.abi_encode(), whale());
+        assert_eq!(orders.len(), 1);
+        assert!(!orders[0].side_is_buy && !orders[0].taker);
+        assert_eq!(orders[0].shares, U256::from(4_000_000u64));
+        assert_eq!(orders[0].usd, U256::from(1_600_000u64));
+
+        let fill = ICtfExchange::fillOrdersCall {
+            orders: vec![order(whale(), 7, 10_000_000, 4_000_000, 1), order(other, 7, 1, 1, 0)],
+            fillAmounts: vec![U256::from(5_000_000u64), U256::from(1u64)],
+        };
+        let orders = decode_whale_orders(&fill.abi_encode(), whale());
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].usd, U256::from(2_000_000u64));
+
+        assert!(decode_whale_orders(&[0xde, 0xad, 0xbe, 0xef], whale()).is_empty());
+    }
+
+    #[test]
+    fn test_pending_fill_per_token_and_side() {
+        let whale_order = |token: u64, side_is_buy: bool, usd: u64, shares: u64| WhaleOrder {
+            side_is_buy, token_id: U256::from(token), taker: false, usd: U256::from(usd), shares: U256::from(shares),
+        };
+        let fills = pending_fills("0xaa", vec![
+            whale_order(1, true, 1_000_000, 2_000_000),
+            whale_order(2, false, 3_000_000, 4_000_000),
+            whale_order(1, true, 3_000_000, 6_000_000),
+            whale_order(1, false, 1_000_000, 4_000_000),
+        ]);
+        let summary: Vec<_> = fills.iter().map(|f| (f.token_id.as_str(), f.side_is_buy, f.shares, f.price)).collect();
+        assert_eq!(summary, vec![("1", true, 8.0, 0.5), ("2", false, 4.0, 0.75), ("1", false, 4.0, 0.25)]);
+
+        assert!(is_pending_log_index(pending_log_index(0)) && is_pending_log_index(pending_log_index(3)));
+        assert!(!is_pending_log_index(7));
+    }
+
+    #[test]
+    fn test_pending_reconciliation() {
+        let t0 = Instant::now();
+        let mut t = PendingTracker::new();
+        assert!(t.observe_pending("0xAA", pending_log_index(0), t0));
+        assert!(!t.observe_pending("0xaa", pending_log_index(0), t0));
+        assert!(t.observe_pending("0xaa", pending_log_index(1), t0));
+
+        let later = t0 + Duration::from_secs(3);
+        assert_eq!(t.confirm("0xaa", later), Confirmation::Confirmed { after: Duration::from_secs(3), first: true });
+        assert_eq!(t.confirm("0xaa", later), Confirmation::Confirmed { after: Duration::from_secs(3), first: false });
+        assert_eq!(t.confirm("0xbb", later), Confirmation::NotPending);
+
+        // Mined before the pending notification arrived: only the log counts
+        assert!(!t.observe_pending("0xbb", pending_log_index(0), later));
+        assert_eq!(t.confirm("0xbb", later), Confirmation::NotPending);
+
+        t.observe_pending("0xcc", pending_log_index(0), t0);
+        let expired = t.take_expired(t0 + PENDING_CONFIRM_TIMEOUT, PENDING_CONFIRM_TIMEOUT);
+        assert_eq!(expired, vec!["0xcc".to_string()]);
+        assert_eq!(t.confirm("0xcc", later), Confirmation::NotPending);
+    }
+
+    #[test]
+    fn test_extract_subscription_id() {
+        let reply = r#"{"jsonrpc":"2.0","id":1,"result":"0x9ce59a13"}"#;
+        assert_eq!(extract_subscription_id(reply), Some("0x9ce59a13"));
+    }
+}
@@ -1,6 +1,9 @@
 // src/types.rs
 // Core types for the trading system
 
+use alloy::primitives::{Address, B256, U256};
+use alloy::sol;
+use alloy::sol_types::SolEvent;
 use serde::Deserialize;
 use std::fmt;
 use std::sync::Arc;
@@ -14,6 +17,26 @@ pub struct OrderInfo {
     pub usd_value: f64,
     pub shares: f64,
     pub price_per_share: f64,
+    pub whale_role: FillRole,
+}
+
+/// The whale's side of the match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillRole {
+    /// Resting order filled by someone else (passive)
+    Maker,
+    /// Crossed the spread (aggressive)
+    Taker,
+}
+
+impl FillRole {
+    /// `whale_role` column of the trade log
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FillRole::Maker => "MAKER",
+            FillRole::Taker => "TAKER",
+        }
+    }
 }
 
 /// Fully parsed blockchain event ready for processing
@@ -21,7 +44,70 @@ pub struct OrderInfo {
 pub struct ParsedEvent {
     pub block_number: u64,
     pub tx_hash: String,
+    pub log_index: u64,
     pub order: OrderInfo,
+    /// Age in seconds when recovered by backfill after a reconnect; None for live events
+    pub late_secs: Option<u64>,
+    /// The log was reorged out (`"removed": true`)
+    pub removed: bool,
+    /// Taken from the mempool before the transaction was mined
+    pub pending: bool,
+    /// The decoded log; None for events taken from the mempool
+    pub fill: Option<OrdersFilledEvent>,
+}
+
+sol! {
+    /// CTF exchange event emitted once per filled maker order
+    event OrderFilled(
+        bytes32 indexed orderHash,
+        address indexed maker,
+        address indexed taker,
+        uint256 makerAssetId,
+        uint256 takerAssetId,
+        uint256 makerAmountFilled,
+        uint256 takerAmountFilled,
+        uint256 fee
+    );
+}
+
+/// Typed `OrderFilled` log. Asset id 0 is USDC; amounts have 6 decimals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrdersFilledEvent {
+    pub order_hash: B256,
+    pub maker: Address,
+    pub taker: Address,
+    pub maker_asset_id: U256,
+    pub taker_asset_id: U256,
+    pub maker_amount_filled: U256,
+    pub taker_amount_filled: U256,
+    /// Fee paid by the maker, in the asset it receives
+    pub fee: U256,
+    pub log_index: u64,
+}
+
+impl OrdersFilledEvent {
+    /// Decode a raw log. Returns None for other events or malformed data.
+    pub fn decode(log: &LogResult) -> Option<Self> {
+        let topics = log.topics.iter()
+            .map(|t| t.parse::<B256>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        let data = alloy::hex::decode(&log.data).ok()?;
+        let ev = OrderFilled::decode_raw_log(topics, &data).ok()?;
+
+        Some(Self {
+            order_hash: ev.orderHash,
+            maker: ev.maker,
+            taker: ev.taker,
+            maker_asset_id: ev.makerAssetId,
+            taker_asset_id: ev.takerAssetId,
+            maker_amount_filled: ev.makerAmountFilled,
+            taker_amount_filled: ev.takerAmountFilled,
+            fee: ev.fee,
+            log_index: log.log_index.as_deref()
+                .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+                .unwrap_or_default(),
+        })
+    }
 }
 
 /// Work item for the order processing queue
@@ -33,7 +119,6 @@ pub struct WorkItem {
 }
 
 /// Size calculation result 
-/// Note: Still used by mempool_monitor binary
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy)]
 pub enum SizeType {
@@ -91,4 +176,53 @@ pub struct LogResult {
     pub block_number: Option<String>,
     #[serde(rename = "transactionHash")]
     pub transaction_hash: Option<String>,
-}
\ No newline at end of file
+    #[serde(rename = "logIndex")]
+    pub log_index: Option<String>,
+    #[serde(default)]
+    pub removed: bool,
+}
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_order_filled() {
+        assert_eq!(
+            format!("{:?}", OrderFilled::SIGNATURE_HASH),
+            "0xd0a08e8c493f9c94f29311604c9de1b4e8c8d4c06bd0c789af57f2d65bfec0f6"
+        );
+
+        let word = |v: u64| format!("{:064x}", v);
+        let log = LogResult {
+            topics: vec![
+                format!("{:?}", OrderFilled::SIGNATURE_HASH),
+                format!("0x{}", "11".repeat(32)),
+                format!("0x{:0>64}", "ab".repeat(20)),
+                format!("0x{:0>64}", "cd".repeat(20)),
+            ],
+            data: format!("0x{}{}{}{}{}", word(0), word(42), word(5_000_000), word(10_000_000), word(25_000)),
+            block_number: Some("0x64".into()),
+            transaction_hash: Some("0xabc".into()),
+            log_index: Some("0x1f".into()),
+            removed: false,
+        };
+
+        let ev = OrdersFilledEvent::decode(&log).unwrap();
+        assert_eq!(ev.order_hash, B256::repeat_byte(0x11));
+        assert_eq!(ev.maker, Address::repeat_byte(0xab));
+        assert_eq!(ev.taker, Address::repeat_byte(0xcd));
+        assert!(ev.maker_asset_id.is_zero());
+        assert_eq!(ev.taker_asset_id, U256::from(42u64));
+        assert_eq!(ev.maker_amount_filled, U256::from(5_000_000u64));
+        assert_eq!(ev.fee, U256::from(25_000u64));
+        assert_eq!(ev.log_index, 31);
+
+        let mut other = log;
+        other.topics[0] = format!("0x{}", "22".repeat(32));
+        assert!(OrdersFilledEvent::decode(&other).is_none());
+    }
+}
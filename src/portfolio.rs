@@ -0,0 +1,521 @@
+//! Portfolio-level risk limits
+//!
+//! The risk guard looks at one token at a time: large-trade sequences and book
+//! depth. `PortfolioRisk` adds limits across everything we hold: total open
+//! exposure, exposure per event (both sides of a neg-risk event count
+//! together), the number of concurrent positions, and the day's loss with open
+//! positions marked to market. Only buys are checked; sells reduce risk and are
+//! always allowed.
+//!
+//! Holdings are updated from our own fills as they happen and resynced from the
+//! data API positions endpoint periodically, which also supplies fresh marks.
+//! A buy that passes the check reserves exposure until its fill is booked, so
+//! buys in flight at the same time count against the limits together.
+
+use chrono::NaiveDate;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::market_meta;
+use crate::settings::Config;
+use crate::wallet_compare::OpenPosition;
+
+/// How often holdings and marks are resynced from the data API
+pub const POSITIONS_REFRESH: Duration = Duration::from_secs(60);
+/// Holdings worth less than this are treated as closed
+const DUST_USD: f64 = 0.5;
+
+// ============================================================================
+// Limits
+// ============================================================================
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PortfolioLimits {
+    pub max_open_exposure_usd: Option<f64>,
+    pub max_event_exposure_usd: Option<f64>,
+    pub max_open_positions: Option<usize>,
+    pub max_daily_loss_usd: Option<f64>,
+}
+
+impl PortfolioLimits {
+    pub fn from_config(cfg: &Config) -> Self {
+        Self {
+            max_open_exposure_usd: cfg.max_open_exposure_usd,
+            max_event_exposure_usd: cfg.max_event_exposure_usd,
+            max_open_positions: cfg.max_open_positions,
+            max_daily_loss_usd: cfg.max_daily_loss_usd,
+        }
+    }
+
+    pub fn any(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+/// Why a buy was blocked. Logged as `RISK_BLOCKED:<reason>` like the risk guard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortfolioBlock {
+    OpenExposure,
+    EventExposure,
+    MaxPositions,
+    DailyLoss,
+}
+
+impl PortfolioBlock {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PortfolioBlock::OpenExposure => "PORTFOLIO_EXPOSURE",
+            PortfolioBlock::EventExposure => "EVENT_EXPOSURE",
+            PortfolioBlock::MaxPositions => "MAX_POSITIONS",
+            PortfolioBlock::DailyLoss => "DAILY_LOSS",
+        }
+    }
+}
+
+// ============================================================================
+// Holdings
+// ============================================================================
+
+#[derive(Debug, Clone)]
+struct Holding {
+    /// Event slug, or the token id until the slug is known (see `regroup`)
+    group: String,
+    shares: f64,
+    avg_price: f64,
+    mark: f64,
+}
+
+impl Holding {
+    fn value(&self) -> f64 {
+        self.shares * self.mark
+    }
+
+    fn unrealized(&self) -> f64 {
+        self.shares * (self.mark - self.avg_price)
+    }
+}
+
+/// Exposure held for a buy from its check until the fill is booked
+#[derive(Debug, Clone)]
+struct Reserved {
+    token: String,
+    group: String,
+    usd: f64,
+    /// Filled; waiting for the user channel to book it
+    filled: bool,
+}
+
+/// An in-flight buy's reservation, see [`PortfolioRisk::reserve`]
+#[must_use]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Reservation(u64);
+
+pub struct PortfolioRisk {
+    limits: PortfolioLimits,
+    holdings: HashMap<String, Holding>,
+    /// Holdings a sell closed today, so a failed sell can be reversed
+    closed: HashMap<String, Holding>,
+    reserved: HashMap<u64, Reserved>,
+    next_reservation: u64,
+    day: Option<NaiveDate>,
+    realized_today: f64,
+    /// Unrealized P&L when the day started, so only today's moves count
+    day_start_unrealized: f64,
+}
+
+impl PortfolioRisk {
+    pub fn new(limits: PortfolioLimits) -> Self {
+        Self {
+            limits,
+            holdings: HashMap::new(),
+            closed: HashMap::new(),
+            reserved: HashMap::new(),
+            next_reservation: 0,
+            day: None,
+            realized_today: 0.0,
+            day_start_unrealized: 0.0,
+        }
+    }
+
+    pub fn limits(&self) -> &PortfolioLimits {
+        &self.limits
+    }
+
+    /// Replace holdings with the data API view. Positions keep the group they
+    /// were recorded with unless the API reports an event slug. Positions that
+    /// appear or disappear here move the day-start baseline with them, so a
+    /// restart with losing positions does not count the old loss as today's.
+    /// Filled reservations still waiting for the user channel are dropped, as
+    /// the data API now holds those fills.
+    pub fn sync(&mut self, positions: &[OpenPosition], today: NaiveDate) {
+        self.roll_day(today);
+        self.reserved.retain(|_, r| !r.filled);
+        let mut holdings = HashMap::with_capacity(positions.len());
+        for p in positions.iter().filter(|p| p.size > 0.0) {
+            let group = if !p.event_slug.is_empty() {
+                p.event_slug.clone()
+            } else {
+                self.holdings.get(&p.asset).map(|h| h.group.clone()).unwrap_or_else(|| p.asset.clone())
+            };
+            let h = Holding { group, shares: p.size, avg_price: p.avg_price, mark: p.cur_price };
+            if !self.holdings.contains_key(&p.asset) {
+                self.day_start_unrealized += h.unrealized();
+            }
+            holdings.insert(p.asset.clone(), h);
+        }
+        for (token, h) in &self.holdings {
+            if !holdings.contains_key(token) {
+                self.day_start_unrealized -= h.unrealized();
+            }
+        }
+        self.holdings = holdings;
+    }
+
+    /// Apply one of our fills.
+    pub fn record_fill(&mut self, token_id: &str, group: &str, side_is_buy: bool, shares: f64, price: f64, today: NaiveDate) {
+        self.roll_day(today);
+        let h = self.holdings.entry(token_id.to_string()).or_insert_with(|| Holding {
+            group: group.to_string(),
+            shares: 0.0,
+            avg_price: price,
+            mark: price,
+        });
+        if side_is_buy {
+            let total = h.shares + shares;
+            if total > 0.0 {
+                h.avg_price = (h.avg_price * h.shares + price * shares) / total;
+            }
+            h.shares = total;
+            self.reserved.retain(|_, r| !(r.filled && r.token == token_id));
+            self.closed.remove(token_id);
+        } else {
+            let sold = shares.min(h.shares);
+            self.realized_today += sold * (price - h.avg_price);
+            h.shares -= sold;
+        }
+        h.mark = price;
+        if h.shares * h.mark < DUST_USD {
+            if let Some(h) = self.holdings.remove(token_id) {
+                if !side_is_buy {
+                    self.closed.insert(token_id.to_string(), h);
+                }
+            }
+        }
+    }
+
+    /// Undo one of our fills whose settlement failed: shares and average price
+    /// go back to what they were before it. Unlike booking the opposite side,
+    /// this realizes no P&L, and a failed sell takes back the P&L it realized.
+    pub fn reverse_fill(&mut self, token_id: &str, group: &str, side_is_buy: bool, shares: f64, price: f64, today: NaiveDate) {
+        self.roll_day(today);
+        if side_is_buy {
+            let Some(h) = self.holdings.get_mut(token_id) else { return };
+            let remaining = h.shares - shares.min(h.shares);
+            if remaining > 0.0 {
+                h.avg_price = (h.avg_price * h.shares - price * shares) / remaining;
+            }
+            h.shares = remaining;
+            if h.shares * h.mark < DUST_USD {
+                self.holdings.remove(token_id);
+            }
+        } else {
+            // A sell never moved the average price; a holding it closed is restored
+            let closed = self.closed.remove(token_id);
+            let h = self.holdings.entry(token_id.to_string()).or_insert_with(|| closed.unwrap_or(Holding {
+                group: group.to_string(),
+                shares: 0.0,
+                avg_price: price,
+                mark: price,
+            }));
+            self.realized_today -= shares * (price - h.avg_price);
+            h.shares += shares;
+        }
+    }
+
+    /// Update the mark of a held token from the book.
+    pub fn mark(&mut self, token_id: &str, price: f64) {
+        if let Some(h) = self.holdings.get_mut(token_id) {
+            h.mark = price;
+        }
+    }
+
+    pub fn open_exposure(&self) -> f64 {
+        self.holdings.values().map(Holding::value).sum()
+    }
+
+    pub fn event_exposure(&self, group: &str) -> f64 {
+        self.holdings.values().filter(|h| h.group == group).map(Holding::value).sum()
+    }
+
+    /// Token ids currently held
+    pub fn tokens(&self) -> Vec<String> {
+        self.holdings.keys().cloned().collect()
+    }
+
+    pub fn open_positions(&self) -> usize {
+        self.holdings.len()
+    }
+
+    /// Realized plus change in unrealized P&L since the start of the day
+    pub fn daily_pnl(&self) -> f64 {
+        let unrealized: f64 = self.holdings.values().map(Holding::unrealized).sum();
+        self.realized_today + unrealized - self.day_start_unrealized
+    }
+
+    fn reserved_usd(&self, group: Option<&str>) -> f64 {
+        self.reserved.values().filter(|r| group.is_none_or(|g| r.group == g)).map(|r| r.usd).sum()
+    }
+
+    /// Check a copy trade against the limits, counting reserved buys as held.
+    /// Checked before sizing, so one order can overshoot a limit by at most
+    /// its own size.
+    pub fn check(&mut self, token_id: &str, group: &str, side_is_buy: bool, today: NaiveDate) -> Option<PortfolioBlock> {
+        self.roll_day(today);
+        if !side_is_buy {
+            return None;
+        }
+        let l = self.limits.clone();
+        if l.max_daily_loss_usd.is_some_and(|max| -self.daily_pnl() >= max) {
+            return Some(PortfolioBlock::DailyLoss);
+        }
+        if l.max_open_exposure_usd.is_some_and(|max| self.open_exposure() + self.reserved_usd(None) >= max) {
+            return Some(PortfolioBlock::OpenExposure);
+        }
+        if let Some(max) = l.max_event_exposure_usd {
+            self.regroup(market_meta::event_slug);
+            if self.event_exposure(group) + self.reserved_usd(Some(group)) >= max {
+                return Some(PortfolioBlock::EventExposure);
+            }
+        }
+        let positions: HashSet<&str> = self.holdings.keys().map(String::as_str)
+            .chain(self.reserved.values().map(|r| r.token.as_str()))
+            .collect();
+        let new_position = !positions.contains(token_id);
+        if new_position && l.max_open_positions.is_some_and(|max| positions.len() >= max) {
+            return Some(PortfolioBlock::MaxPositions);
+        }
+        None
+    }
+
+    /// Check a buy and, when it is allowed, reserve `usd` of exposure for it in
+    /// the same step. The reservation is held until [`release`](Self::release).
+    pub fn reserve(&mut self, token_id: &str, group: &str, usd: f64, today: NaiveDate) -> Result<Reservation, PortfolioBlock> {
+        if let Some(block) = self.check(token_id, group, true, today) {
+            return Err(block);
+        }
+        let id = self.next_reservation;
+        self.next_reservation += 1;
+        self.reserved.insert(id, Reserved { token: token_id.to_string(), group: group.to_string(), usd, filled: false });
+        Ok(Reservation(id))
+    }
+
+    /// Release a reservation once its order is answered. `unbooked_usd` is the
+    /// part that filled but is booked later from the user channel; it stays
+    /// reserved until that fill is recorded or the next resync.
+    pub fn release(&mut self, reservation: Reservation, unbooked_usd: f64) {
+        match self.reserved.get_mut(&reservation.0) {
+            Some(r) if unbooked_usd > 0.0 => {
+                r.usd = unbooked_usd;
+                r.filled = true;
+            }
+            _ => {
+                self.reserved.remove(&reservation.0);
+            }
+        }
+    }
+
+    /// Move holdings and reservations still grouped under their own token id
+    /// to their event once `resolve` knows it.
+    fn regroup(&mut self, resolve: impl Fn(&str) -> Option<String>) {
+        for (token, h) in self.holdings.iter_mut().filter(|(t, h)| **t == h.group) {
+            if let Some(group) = resolve(token) {
+                h.group = group;
+            }
+        }
+        for r in self.reserved.values_mut().filter(|r| r.token == r.group) {
+            if let Some(group) = resolve(&r.token) {
+                r.group = group;
+            }
+        }
+    }
+
+    fn roll_day(&mut self, today: NaiveDate) {
+        if self.day == Some(today) {
+            return;
+        }
+        self.day = Some(today);
+        self.realized_today = 0.0;
+        self.closed.clear();
+        self.day_start_unrealized = self.holdings.values().map(Holding::unrealized).sum();
+    }
+}
+
+// ============================================================================
+// Event Groups
+// ============================================================================
+
+/// Event slug of a token from the metadata cache, so every outcome of one
+/// event shares a group. A miss is queued for the background fetch and falls
+/// back to the token id.
+pub fn market_group(token_id: &str) -> String {
+    market_meta::event_slug(token_id).unwrap_or_else(|| {
+        market_meta::request(token_id);
+        token_id.to_string()
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, d).unwrap()
+    }
+
+    fn position(asset: &str, size: f64, avg_price: f64, cur_price: f64) -> OpenPosition {
+        OpenPosition {
+            asset: asset.into(),
+            size,
+            avg_price,
+            cur_price,
+            current_value: size * cur_price,
+            title: String::new(),
+            outcome: String::new(),
+            event_slug: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_exposure_and_position_limits() {
+        let mut p = PortfolioRisk::new(PortfolioLimits {
+            max_open_exposure_usd: Some(100.0),
+            max_event_exposure_usd: Some(40.0),
+            max_open_positions: Some(2),
+            max_daily_loss_usd: None,
+        });
+        p.record_fill("yes", "election", true, 50.0, 0.5, day(1));
+        assert_eq!(p.check("other", "sports", true, day(1)), None);
+
+        // Both sides of one event count against the same limit
+        p.record_fill("no", "election", true, 60.0, 0.25, day(1));
+        assert_eq!(p.event_exposure("election"), 40.0);
+        assert_eq!(p.check("yes", "election", true, day(1)), Some(PortfolioBlock::EventExposure));
+        assert_eq!(p.check("third", "sports", true, day(1)), Some(PortfolioBlock::MaxPositions));
+        // Adding to a held token is not a new position; sells are never blocked
+        assert_eq!(p.check("no", "other", true, day(1)), None);
+        assert_eq!(p.check("third", "sports", false, day(1)), None);
+
+        p.record_fill("big", "sports", true, 200.0, 0.5, day(1));
+        assert_eq!(p.check("big", "sports", true, day(1)), Some(PortfolioBlock::OpenExposure));
+    }
+
+    #[test]
+    fn test_regroup_once_event_known() {
+        let mut p = PortfolioRisk::new(PortfolioLimits::default());
+        // Metadata was cold when these were bought
+        p.record_fill("yes", "yes", true, 100.0, 0.5, day(1));
+        p.record_fill("no", "election", true, 100.0, 0.25, day(1));
+        assert_eq!(p.event_exposure("election"), 25.0);
+
+        p.regroup(|token| (token == "yes").then(|| "election".to_string()));
+        assert_eq!(p.event_exposure("election"), 75.0);
+    }
+
+    #[test]
+    fn test_reservations_count_until_booked() {
+        let mut p = PortfolioRisk::new(PortfolioLimits {
+            max_open_exposure_usd: Some(100.0),
+            max_open_positions: Some(2),
+            ..Default::default()
+        });
+        // Two buys in flight at once: the second sees the first
+        let a = p.reserve("a", "a", 60.0, day(1)).unwrap();
+        let b = p.reserve("b", "b", 40.0, day(1)).unwrap();
+        assert_eq!(p.reserve("c", "c", 10.0, day(1)), Err(PortfolioBlock::OpenExposure));
+
+        // Unfilled: released at once
+        p.release(b, 0.0);
+        assert_eq!(p.check("b", "b", true, day(1)), None);
+
+        // Filled, booked later by the user channel
+        p.release(a, 30.0);
+        let c = p.reserve("c", "c", 70.0, day(1)).unwrap();
+        assert_eq!(p.check("d", "d", true, day(1)), Some(PortfolioBlock::OpenExposure));
+        p.release(c, 0.0);
+        assert_eq!(p.check("d", "d", true, day(1)), None);
+        p.record_fill("a", "a", true, 60.0, 0.5, day(1));
+        assert_eq!(p.reserved_usd(None), 0.0);
+        assert_eq!(p.open_exposure(), 30.0);
+    }
+
+    #[test]
+    fn test_daily_loss_marked_to_market() {
+        let mut p = PortfolioRisk::new(PortfolioLimits { max_daily_loss_usd: Some(20.0), ..Default::default() });
+        p.record_fill("a", "a", true, 100.0, 0.75, day(1));
+        p.mark("a", 0.625);
+        assert_eq!(p.daily_pnl(), -12.5);
+        assert_eq!(p.check("b", "b", true, day(1)), None);
+
+        // Realized loss on a partial sell plus the remaining mark
+        p.record_fill("a", "a", false, 50.0, 0.5, day(1));
+        assert_eq!(p.daily_pnl(), -25.0);
+        assert_eq!(p.check("b", "b", true, day(1)), Some(PortfolioBlock::DailyLoss));
+
+        // A new day starts from the current marks
+        assert_eq!(p.check("b", "b", true, day(2)), None);
+        assert_eq!(p.daily_pnl(), 0.0);
+    }
+
+    #[test]
+    fn test_reverse_failed_buy() {
+        let mut p = PortfolioRisk::new(PortfolioLimits::default());
+        p.record_fill("a", "a", true, 100.0, 0.5, day(1));
+        p.record_fill("a", "a", true, 100.0, 0.75, day(1));
+        p.reverse_fill("a", "a", true, 100.0, 0.75, day(1));
+        assert_eq!(p.open_exposure(), 75.0);
+
+        // Selling at the original price now realizes nothing
+        p.mark("a", 0.5);
+        p.record_fill("a", "a", false, 100.0, 0.5, day(1));
+        assert_eq!((p.open_positions(), p.daily_pnl()), (0, 0.0));
+    }
+
+    #[test]
+    fn test_reverse_failed_sell() {
+        let mut p = PortfolioRisk::new(PortfolioLimits::default());
+        p.record_fill("a", "a", true, 100.0, 0.5, day(1));
+        p.record_fill("a", "a", false, 40.0, 0.75, day(1));
+        assert_eq!(p.daily_pnl(), 25.0);
+        p.reverse_fill("a", "a", false, 40.0, 0.75, day(1));
+        assert_eq!((p.open_exposure(), p.realized_today), (75.0, 0.0));
+
+        // A sell that closed the position: it comes back at the old average
+        p.record_fill("a", "a", false, 100.0, 0.75, day(1));
+        assert_eq!((p.open_positions(), p.realized_today), (0, 25.0));
+        p.reverse_fill("a", "a", false, 100.0, 0.75, day(1));
+        assert_eq!((p.open_positions(), p.realized_today), (1, 0.0));
+        p.mark("a", 0.5);
+        assert_eq!(p.daily_pnl(), 0.0);
+    }
+
+    #[test]
+    fn test_restart_with_losing_position() {
+        let mut p = PortfolioRisk::new(PortfolioLimits { max_daily_loss_usd: Some(20.0), ..Default::default() });
+        // Yesterday's loss on an existing position is not today's
+        p.sync(&[position("a", 100.0, 0.75, 0.5)], day(1));
+        assert_eq!(p.daily_pnl(), 0.0);
+        assert_eq!(p.check("b", "b", true, day(1)), None);
+
+        // Moves after the first sync count
+        p.sync(&[position("a", 100.0, 0.75, 0.25), position("c", 10.0, 0.5, 0.25)], day(1));
+        assert_eq!(p.daily_pnl(), -25.0);
+        assert_eq!(p.check("b", "b", true, day(1)), Some(PortfolioBlock::DailyLoss));
+        // A position closed elsewhere keeps the loss up to its last mark
+        p.sync(&[position("c", 10.0, 0.5, 0.25)], day(1));
+        assert_eq!(p.daily_pnl(), -25.0);
+    }
+}
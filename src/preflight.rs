@@ -0,0 +1,736 @@
+//! Startup preflight shared by all binaries
+//!
+//! Runs the checks every entry point used to re-implement on its own:
+//! configuration, RPC, CLOB, the provider WebSocket, balances, approvals, API
+//! credentials and clock skew. The result is a structured `PreflightReport`
+//! that prints for humans or serialises to JSON for scripts.
+//!
+//! The env helpers (key/address validation) are the single implementation
+//! behind `Config::from_env` and the binaries; RPC provider selection lives in
+//! `providers`.
+
+use anyhow::{Context, Result, anyhow};
+use alloy::primitives::{Address, U256};
+use alloy::providers::{Provider, ProviderBuilder};
+use futures::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::env;
+use std::future::Future;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+
+use crate::approvals::{self, ApprovalStatus};
+use crate::market_category::{self, CategoryRules};
+use crate::providers::{self, ProviderConfig};
+use crate::settings::{self, BreakerOverride, CLOB_API_BASE};
+use crate::ApiCreds;
+
+pub const POLYGON_CHAIN_ID: u64 = 137;
+pub const DEFAULT_CREDS_PATH: &str = ".clob_creds.json";
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+const CLOCK_SKEW_WARN_SECS: f64 = 2.0;
+const CLOCK_SKEW_FAIL_SECS: f64 = 30.0;
+/// Enough MATIC for a handful of approval / Safe transactions
+const MIN_GAS_BALANCE_MATIC: f64 = 0.05;
+
+// ============================================================================
+// Environment
+// ============================================================================
+
+pub(crate) fn is_placeholder(value: &str) -> bool {
+    let v = value.trim();
+    v.is_empty() || (v.starts_with("your_") && v.ends_with("_here")) || v.ends_with("_address_here")
+}
+
+/// Validate PRIVATE_KEY format. Returns the key without `0x`.
+pub fn validate_private_key(raw: &str) -> Result<String> {
+    let key = raw.trim().strip_prefix("0x").unwrap_or(raw.trim());
+    if is_placeholder(key) {
+        anyhow::bail!("PRIVATE_KEY is not set or still has placeholder value");
+    }
+    if key.len() != 64 {
+        anyhow::bail!(
+            "PRIVATE_KEY must be exactly 64 hex characters (found {}).\n\
+            Remove any '0x' prefix. Current value starts with: {}",
+            key.len(),
+            if key.len() > 10 { format!("{}...", &key[..10]) } else { key.to_string() }
+        );
+    }
+    if !key.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("PRIVATE_KEY contains invalid characters. Must be hexadecimal (0-9, a-f, A-F).");
+    }
+    Ok(key.to_string())
+}
+
+/// Validate a 20-byte address held in env var `var`. Returns it without `0x`.
+pub fn validate_address(var: &str, raw: &str) -> Result<String> {
+    let addr = raw.trim().strip_prefix("0x").unwrap_or(raw.trim());
+    if is_placeholder(addr) {
+        anyhow::bail!("{} is not set or still has placeholder value", var);
+    }
+    if addr.len() != 40 {
+        anyhow::bail!(
+            "{} must be exactly 40 hex characters (found {}).\n\
+            Current value: {}",
+            var,
+            addr.len(),
+            if addr.len() > 20 { format!("{}...", &addr[..20]) } else { addr.to_string() }
+        );
+    }
+    if !addr.chars().all(|c| c.is_ascii_hexdigit()) {
+        anyhow::bail!("{} contains invalid characters. Must be hexadecimal (0-9, a-f, A-F).", var);
+    }
+    Ok(addr.to_string())
+}
+
+/// Read and validate env var `var` as an address.
+pub fn env_address(var: &str) -> Result<Address> {
+    let raw = env::var(var).map_err(|_| anyhow!("{} is required. Add it to your .env file.", var))?;
+    let clean = validate_address(var, &raw)?;
+    Ok(Address::from_str(&clean)?)
+}
+
+/// Boolean env flag: "true"/"1" (any case) is on
+pub fn env_flag(key: &str, default: bool) -> bool {
+    env::var(key)
+        .map(|v| { let v = v.trim().to_lowercase(); v == "true" || v == "1" })
+        .unwrap_or(default)
+}
+
+// ============================================================================
+// Report
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Section {
+    Config,
+    Rpc,
+    Clob,
+    WebSocket,
+    Balances,
+    Approvals,
+    ApiCreds,
+    ClockSkew,
+}
+
+impl Section {
+    pub const ALL: [Section; 8] = [
+        Section::Config, Section::Rpc, Section::Clob, Section::WebSocket,
+        Section::Balances, Section::Approvals, Section::ApiCreds, Section::ClockSkew,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Section::Config => "CONFIGURATION",
+            Section::Rpc => "RPC",
+            Section::Clob => "CLOB API",
+            Section::WebSocket => "WEBSOCKET",
+            Section::Balances => "BALANCES",
+            Section::Approvals => "APPROVALS",
+            Section::ApiCreds => "API CREDENTIALS",
+            Section::ClockSkew => "CLOCK",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+impl CheckStatus {
+    fn icon(&self) -> &'static str {
+        match self {
+            CheckStatus::Pass => "✅",
+            CheckStatus::Warn => "⚠️ ",
+            CheckStatus::Fail => "❌",
+            CheckStatus::Skip => "⏭️ ",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub section: Section,
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreflightReport {
+    pub generated_at: String,
+    pub ok: bool,
+    pub checks: Vec<CheckResult>,
+}
+
+impl PreflightReport {
+    fn new(checks: Vec<CheckResult>) -> Self {
+        Self {
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            ok: !checks.iter().any(|c| c.status == CheckStatus::Fail),
+            checks,
+        }
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &CheckResult> {
+        self.checks.iter().filter(|c| c.status == CheckStatus::Fail)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &CheckResult> {
+        self.checks.iter().filter(|c| c.status == CheckStatus::Warn)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    pub fn print(&self) {
+        let mut current: Option<Section> = None;
+        for c in &self.checks {
+            if current != Some(c.section) {
+                println!("{}", c.section.title());
+                current = Some(c.section);
+            }
+            match c.latency_ms {
+                Some(ms) => println!("  {} {}: {} ({}ms)", c.status.icon(), c.name, c.detail, ms),
+                None => println!("  {} {}: {}", c.status.icon(), c.name, c.detail),
+            }
+        }
+        println!();
+        let (fails, warns) = (self.failures().count(), self.warnings().count());
+        if fails == 0 && warns == 0 {
+            println!("✅ Preflight passed");
+        } else if fails == 0 {
+            println!("⚠️  Preflight passed with {} warning(s)", warns);
+        } else {
+            println!("❌ Preflight failed: {} error(s), {} warning(s)", fails, warns);
+        }
+    }
+
+    /// Print in the requested format.
+    pub fn emit(&self, json: bool) {
+        if json {
+            println!("{}", self.to_json());
+        } else {
+            self.print();
+        }
+    }
+
+    /// Error listing every failed check, if any.
+    pub fn gate(&self) -> Result<()> {
+        if self.ok {
+            return Ok(());
+        }
+        let lines: Vec<String> = self.failures().map(|c| format!("  - [{}] {}: {}", c.section.title(), c.name, c.detail)).collect();
+        Err(anyhow!("Preflight failed:\n{}", lines.join("\n")))
+    }
+}
+
+// ============================================================================
+// Options
+// ============================================================================
+
+#[derive(Debug, Clone)]
+pub struct PreflightOptions {
+    pub sections: Vec<Section>,
+    /// Also check what only the trading bots read: TARGET_WHALE_ADDRESS,
+    /// trading mode and the numeric sizing / circuit breaker settings
+    pub trading: bool,
+    /// Minimum USDC allowance per spender, on top of covering the balance
+    pub min_allowance_usd: f64,
+    pub creds_path: String,
+}
+
+impl Default for PreflightOptions {
+    fn default() -> Self {
+        Self {
+            sections: Section::ALL.to_vec(),
+            trading: false,
+            min_allowance_usd: 0.0,
+            creds_path: DEFAULT_CREDS_PATH.to_string(),
+        }
+    }
+}
+
+impl PreflightOptions {
+    pub fn only(sections: &[Section]) -> Self {
+        Self { sections: sections.to_vec(), ..Default::default() }
+    }
+
+    /// Everything the trading bot depends on.
+    pub fn trading(min_allowance_usd: f64) -> Self {
+        Self { trading: true, min_allowance_usd, ..Default::default() }
+    }
+
+    fn wants(&self, section: Section) -> bool {
+        self.sections.contains(&section)
+    }
+}
+
+/// True when `--json` is among the process arguments.
+pub fn json_requested() -> bool {
+    env::args().any(|a| a == "--json")
+}
+
+/// Preflight for tool binaries: run `sections`, print only problems (or the
+/// full report as JSON with `--json`) and fail on any error.
+pub async fn require(sections: &[Section]) -> Result<PreflightReport> {
+    let report = run(&PreflightOptions::only(sections)).await;
+    if json_requested() {
+        println!("{}", report.to_json());
+    } else {
+        for c in report.checks.iter().filter(|c| matches!(c.status, CheckStatus::Warn | CheckStatus::Fail)) {
+            println!("{} {}: {}", c.status.icon(), c.name, c.detail);
+        }
+    }
+    report.gate()?;
+    Ok(report)
+}
+
+/// Preflight for the trading bots. Balances and approvals are only checked
+/// when real orders will be sent, and failures then refuse to start. With
+/// `--preflight` the process exits after reporting (status 1 on failure).
+pub async fn trading_gate() -> Result<PreflightReport> {
+    let live = env_flag("ENABLE_TRADING", true) && !env_flag("MOCK_TRADING", false);
+    let max_order_usd = env::var("MAX_ORDER_SIZE_USD").ok().and_then(|v| v.parse().ok()).unwrap_or(100.0);
+
+    let mut opts = PreflightOptions::trading(max_order_usd);
+    if !live {
+        // Monitoring or mock trading never touches funds
+        opts.sections.retain(|s| !matches!(s, Section::Balances | Section::Approvals));
+    }
+
+    let report = run(&opts).await;
+    report.emit(json_requested());
+    if env::args().any(|a| a == "--preflight") {
+        std::process::exit(if report.ok { 0 } else { 1 });
+    }
+    if live {
+        report.gate().map_err(|e| anyhow!("Refusing to trade. {}", e))?;
+    }
+    Ok(report)
+}
+
+// ============================================================================
+// Checks
+// ============================================================================
+
+struct Checks {
+    out: Vec<CheckResult>,
+}
+
+impl Checks {
+    fn push(&mut self, section: Section, name: &str, status: CheckStatus, detail: impl Into<String>) {
+        self.out.push(CheckResult { section, name: name.to_string(), status, detail: detail.into(), latency_ms: None });
+    }
+
+    fn push_timed(&mut self, section: Section, name: &str, status: CheckStatus, detail: impl Into<String>, started: Instant) {
+        self.push(section, name, status, detail);
+        if let Some(last) = self.out.last_mut() {
+            last.latency_ms = Some(started.elapsed().as_millis() as u64);
+        }
+    }
+}
+
+/// Addresses and providers parsed from the environment
+struct EnvState {
+    signer: Option<Address>,
+    funder: Option<Address>,
+    providers: Vec<ProviderConfig>,
+}
+
+async fn timed<T>(fut: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::time::timeout(CHECK_TIMEOUT, fut)
+        .await
+        .map_err(|_| anyhow!("timed out after {}s", CHECK_TIMEOUT.as_secs()))?
+}
+
+/// Run the selected checks. Later sections that depend on a failed earlier
+/// one (e.g. balances without a working RPC) are reported as skipped.
+pub async fn run(opts: &PreflightOptions) -> PreflightReport {
+    let mut c = Checks { out: Vec::new() };
+    let env_state = check_config(&mut c, opts);
+    let rpc_url = env_state.providers.iter().find_map(|p| p.http_url.clone()).unwrap_or_else(|| providers::DEFAULT_RPC_URL.to_string());
+    let client = reqwest::Client::builder().timeout(CHECK_TIMEOUT).build().unwrap_or_default();
+
+    let rpc_ok = if opts.wants(Section::Rpc) || opts.wants(Section::Balances) || opts.wants(Section::Approvals) {
+        check_rpc(&mut c, &client, &rpc_url, opts.wants(Section::Rpc)).await
+    } else {
+        false
+    };
+
+    if opts.wants(Section::Clob) || opts.wants(Section::ClockSkew) {
+        check_clob_and_clock(&mut c, &client, opts).await;
+    }
+
+    if opts.wants(Section::WebSocket) {
+        check_websockets(&mut c, &env_state.providers).await;
+    }
+
+    if opts.wants(Section::Balances) {
+        match (rpc_ok, env_state.funder) {
+            (true, Some(funder)) => check_balances(&mut c, &rpc_url, funder, env_state.signer, opts).await,
+            (false, _) => c.push(Section::Balances, "Balances", CheckStatus::Skip, "RPC unavailable"),
+            (_, None) => c.push(Section::Balances, "Balances", CheckStatus::Skip, "FUNDER_ADDRESS invalid"),
+        }
+    }
+
+    if opts.wants(Section::Approvals) {
+        match (rpc_ok, env_state.funder) {
+            (true, Some(funder)) => check_approvals(&mut c, &rpc_url, funder, opts.min_allowance_usd).await,
+            (false, _) => c.push(Section::Approvals, "Approvals", CheckStatus::Skip, "RPC unavailable"),
+            (_, None) => c.push(Section::Approvals, "Approvals", CheckStatus::Skip, "FUNDER_ADDRESS invalid"),
+        }
+    }
+
+    if opts.wants(Section::ApiCreds) {
+        check_api_creds(&mut c, &opts.creds_path);
+    }
+
+    PreflightReport::new(c.out)
+}
+
+fn check_config(c: &mut Checks, opts: &PreflightOptions) -> EnvState {
+    let report = opts.wants(Section::Config);
+    let mut push = |name: &str, status: CheckStatus, detail: String| {
+        if report { c.push(Section::Config, name, status, detail); }
+    };
+
+    if !Path::new(".env").exists() {
+        push(".env", CheckStatus::Warn, "file not found; copy .env.example to .env".into());
+    }
+
+    let signer = match env::var("PRIVATE_KEY").map_err(|_| anyhow!("PRIVATE_KEY is required. Add it to your .env file."))
+        .and_then(|k| validate_private_key(&k))
+        .and_then(|k| k.parse::<alloy::signers::local::PrivateKeySigner>().map_err(|e| anyhow!("PRIVATE_KEY is not a valid secp256k1 key: {}", e)))
+    {
+        Ok(s) => {
+            push("PRIVATE_KEY", CheckStatus::Pass, format!("signer {}", s.address()));
+            Some(s.address())
+        }
+        Err(e) => {
+            push("PRIVATE_KEY", CheckStatus::Fail, e.to_string());
+            None
+        }
+    };
+
+    let funder = match env_address("FUNDER_ADDRESS") {
+        Ok(a) => {
+            let kind = if Some(a) == signer { "same as signer (EOA)" } else { "differs from signer (Safe/proxy)" };
+            push("FUNDER_ADDRESS", CheckStatus::Pass, format!("{} {}", a, kind));
+            Some(a)
+        }
+        Err(e) => {
+            push("FUNDER_ADDRESS", CheckStatus::Fail, e.to_string());
+            None
+        }
+    };
+
+    let providers = providers::providers_from_env().unwrap_or_default();
+    match providers::ws_providers_from_env() {
+        Ok(ws) => {
+            let names: Vec<&str> = providers.iter().map(|p| p.name.as_str()).collect();
+            let detail = format!("{} in priority order: {}", providers.len(), names.join(", "));
+            if ws.len() > 1 {
+                push("RPC providers", CheckStatus::Pass, detail);
+            } else {
+                push("RPC providers", CheckStatus::Warn, format!("{}; no WebSocket failover (see RPC_PROVIDERS)", detail));
+            }
+        }
+        Err(e) => push("RPC providers", CheckStatus::Fail, e.to_string().lines().next().unwrap_or_default().to_string()),
+    }
+
+    if opts.trading {
+        match env_address("TARGET_WHALE_ADDRESS") {
+            Ok(a) => push("TARGET_WHALE_ADDRESS", CheckStatus::Pass, a.to_string()),
+            Err(e) => push("TARGET_WHALE_ADDRESS", CheckStatus::Fail, e.to_string()),
+        }
+
+        let (enable, mock) = (env_flag("ENABLE_TRADING", true), env_flag("MOCK_TRADING", false));
+        match (enable, mock) {
+            (true, false) => push("Trading mode", CheckStatus::Warn, "LIVE - real orders will be placed".into()),
+            (true, true) => push("Trading mode", CheckStatus::Pass, "mock (orders simulated)".into()),
+            (false, _) => push("Trading mode", CheckStatus::Pass, "monitor only (ENABLE_TRADING=false)".into()),
+        }
+
+        for key in ["CB_LARGE_TRADE_SHARES", "CB_CONSECUTIVE_TRIGGER", "CB_SEQUENCE_WINDOW_SECS", "CB_MIN_DEPTH_USD", "CB_TRIP_DURATION_SECS",
+                    "MAX_ORDER_SIZE_USD", "MIN_ORDER_SIZE_USD", "COPY_SIZE", "TRADE_MULTIPLIER", "WS_SUBSCRIPTIONS", "BACKFILL_MAX_AGE_SECS",
+                    "PASSIVE_SIZE_MULTIPLIER", "AGGREGATION_WINDOW_MS"] {
+            if let Ok(val) = env::var(key) {
+                if val.trim().parse::<f64>().is_err() {
+                    push(key, CheckStatus::Warn, format!("invalid value '{}', default will be used", val));
+                }
+            }
+        }
+        // Optional limits are not defaulted: Config::from_env rejects them
+        for (key, e) in settings::optional_limit_errors() {
+            push(key, CheckStatus::Fail, e.to_string());
+        }
+
+        let rules_path = market_category::rules_path();
+        let rules = match CategoryRules::load_configured() {
+            Ok(rules) => {
+                push("CATEGORY_RULES", CheckStatus::Pass, format!("{}: {}", rules_path, rules.names().collect::<Vec<_>>().join(", ")));
+                rules
+            }
+            Err(e) => {
+                push("CATEGORY_RULES", CheckStatus::Fail, format!("{:#}", e));
+                CategoryRules::builtin()
+            }
+        };
+        for category in rules.names() {
+            if let Err(e) = BreakerOverride::from_env(category) {
+                push(&format!("CB_{}_*", category), CheckStatus::Fail, e.to_string());
+            }
+        }
+    }
+
+    EnvState { signer, funder, providers }
+}
+
+async fn json_rpc(client: &reqwest::Client, url: &str, method: &str) -> Result<serde_json::Value> {
+    let resp: serde_json::Value = client
+        .post(url)
+        .json(&serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": [] }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    if let Some(err) = resp.get("error") {
+        return Err(anyhow!("{} error: {}", method, err));
+    }
+    Ok(resp["result"].clone())
+}
+
+fn hex_u64(v: &serde_json::Value) -> Result<u64> {
+    let s = v.as_str().ok_or_else(|| anyhow!("expected hex string, got {}", v))?;
+    Ok(u64::from_str_radix(s.trim_start_matches("0x"), 16)?)
+}
+
+async fn check_rpc(c: &mut Checks, client: &reqwest::Client, rpc_url: &str, report: bool) -> bool {
+    let started = Instant::now();
+    let result = timed(async {
+        let chain_id = hex_u64(&json_rpc(client, rpc_url, "eth_chainId").await?)?;
+        let block = hex_u64(&json_rpc(client, rpc_url, "eth_blockNumber").await?)?;
+        Ok((chain_id, block))
+    }).await;
+
+    let (status, detail, ok) = match result {
+        Ok((POLYGON_CHAIN_ID, block)) => (CheckStatus::Pass, format!("chain {} at block {}", POLYGON_CHAIN_ID, block), true),
+        Ok((chain_id, _)) => (CheckStatus::Fail, format!("wrong chain id {} (expected {})", chain_id, POLYGON_CHAIN_ID), false),
+        Err(e) => (CheckStatus::Fail, e.to_string(), false),
+    };
+    if report {
+        c.push_timed(Section::Rpc, "HTTP JSON-RPC", status, detail, started);
+    }
+    ok
+}
+
+async fn check_clob_and_clock(c: &mut Checks, client: &reqwest::Client, opts: &PreflightOptions) {
+    let started = Instant::now();
+    let result = timed(async {
+        let resp = client.get(format!("{}/time", CLOB_API_BASE)).send().await?;
+        if !resp.status().is_success() {
+            return Err(anyhow!("HTTP {}", resp.status()));
+        }
+        let body = resp.text().await?;
+        body.trim().parse::<f64>().with_context(|| format!("unexpected /time response '{}'", body.trim()))
+    }).await;
+    let latency = started.elapsed().as_secs_f64();
+
+    match result {
+        Ok(server_secs) => {
+            if opts.wants(Section::Clob) {
+                c.push_timed(Section::Clob, "CLOB API", CheckStatus::Pass, CLOB_API_BASE, started);
+            }
+            if opts.wants(Section::ClockSkew) {
+                // Server stamped the response roughly mid-flight
+                let local = chrono::Utc::now().timestamp_millis() as f64 / 1000.0 - latency / 2.0;
+                let skew = local - server_secs;
+                let status = if skew.abs() >= CLOCK_SKEW_FAIL_SECS {
+                    CheckStatus::Fail
+                } else if skew.abs() >= CLOCK_SKEW_WARN_SECS {
+                    CheckStatus::Warn
+                } else {
+                    CheckStatus::Pass
+                };
+                c.push(Section::ClockSkew, "Clock skew vs CLOB", status, format!("{:+.1}s", skew));
+            }
+        }
+        Err(e) => {
+            if opts.wants(Section::Clob) {
+                c.push_timed(Section::Clob, "CLOB API", CheckStatus::Fail, e.to_string(), started);
+            }
+            if opts.wants(Section::ClockSkew) {
+                c.push(Section::ClockSkew, "Clock skew vs CLOB", CheckStatus::Skip, "CLOB server time unavailable");
+            }
+        }
+    }
+}
+
+/// Probe every WebSocket provider. A dead provider is only a warning while
+/// another one can take over.
+async fn check_websockets(c: &mut Checks, providers: &[ProviderConfig]) {
+    let before = c.out.len();
+    for p in providers {
+        if let Some(url) = &p.wss_url {
+            check_websocket(c, &p.name, url).await;
+        }
+    }
+    if c.out.len() == before {
+        c.push(Section::WebSocket, "Provider WebSocket", CheckStatus::Skip, "no WebSocket provider configured");
+        return;
+    }
+    if c.out[before..].iter().any(|r| r.status == CheckStatus::Pass) {
+        for r in &mut c.out[before..] {
+            if r.status == CheckStatus::Fail {
+                r.status = CheckStatus::Warn;
+            }
+        }
+    }
+}
+
+async fn check_websocket(c: &mut Checks, name: &str, wss_url: &str) {
+    let label = format!("WebSocket ({})", name);
+    let started = Instant::now();
+    let result = timed(async {
+        let (mut ws, _) = connect_async(wss_url).await?;
+        let req = serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "eth_blockNumber", "params": [] }).to_string();
+        ws.send(Message::Text(req)).await?;
+        while let Some(msg) = ws.next().await {
+            if let Message::Text(text) = msg? {
+                let v: serde_json::Value = serde_json::from_str(&text)?;
+                let _ = ws.close(None).await;
+                return hex_u64(&v["result"]);
+            }
+        }
+        Err(anyhow!("closed before replying"))
+    }).await;
+
+    match result {
+        Ok(block) => c.push_timed(Section::WebSocket, &label, CheckStatus::Pass, format!("connected, block {}", block), started),
+        Err(e) => c.push_timed(Section::WebSocket, &label, CheckStatus::Fail, e.to_string(), started),
+    }
+}
+
+async fn check_balances(c: &mut Checks, rpc_url: &str, funder: Address, signer: Option<Address>, opts: &PreflightOptions) {
+    let result = timed(async {
+        let usdc = approvals::usdc_balance(rpc_url, funder).await?;
+        let gas = match signer {
+            Some(s) => {
+                let provider = ProviderBuilder::new().connect_http(rpc_url.parse()?);
+                Some(provider.get_balance(s).await?)
+            }
+            None => None,
+        };
+        Ok((usdc, gas))
+    }).await;
+
+    match result {
+        Ok((usdc, gas)) => {
+            let usdc_f: f64 = approvals::format_usdc(usdc).parse().unwrap_or(0.0);
+            let status = if usdc == U256::ZERO || usdc_f < opts.min_allowance_usd { CheckStatus::Warn } else { CheckStatus::Pass };
+            c.push(Section::Balances, "USDC (funder)", status, format!("{} USDC", approvals::format_usdc(usdc)));
+            if let Some(wei) = gas {
+                let matic = wei.to_string().parse::<f64>().unwrap_or(0.0) / 1e18;
+                let status = if matic < MIN_GAS_BALANCE_MATIC { CheckStatus::Warn } else { CheckStatus::Pass };
+                c.push(Section::Balances, "MATIC (signer gas)", status, format!("{:.4} MATIC", matic));
+            }
+        }
+        Err(e) => c.push(Section::Balances, "Balances", CheckStatus::Fail, e.to_string()),
+    }
+}
+
+async fn check_approvals(c: &mut Checks, rpc_url: &str, funder: Address, min_allowance_usd: f64) {
+    let started = Instant::now();
+    match timed(ApprovalStatus::fetch(rpc_url, funder)).await {
+        Ok(status) => {
+            let missing = status.missing(min_allowance_usd);
+            if missing.is_empty() {
+                c.push_timed(Section::Approvals, "USDC + Conditional Tokens", CheckStatus::Pass,
+                    format!("all {} spenders approved", status.spenders.len()), started);
+            }
+            for m in missing {
+                c.push(Section::Approvals, "Missing approval", CheckStatus::Fail,
+                    format!("{} (run 'cargo run --release --bin approve_tokens')", m));
+            }
+        }
+        Err(e) => c.push_timed(Section::Approvals, "Approvals", CheckStatus::Fail, e.to_string(), started),
+    }
+}
+
+fn check_api_creds(c: &mut Checks, path: &str) {
+    if !Path::new(path).exists() {
+        c.push(Section::ApiCreds, path, CheckStatus::Warn, "not found; will be derived from PRIVATE_KEY on first start");
+        return;
+    }
+    let parsed = std::fs::read_to_string(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|data| serde_json::from_str::<ApiCreds>(&data).map_err(anyhow::Error::from));
+    match parsed {
+        Ok(creds) if creds.api_key.is_empty() || creds.api_secret.is_empty() || creds.api_passphrase.is_empty() => {
+            c.push(Section::ApiCreds, path, CheckStatus::Fail, "empty apiKey/secret/passphrase; delete the file to re-derive");
+        }
+        Ok(creds) => {
+            let shown = creds.api_key.get(..8).unwrap_or(&creds.api_key);
+            c.push(Section::ApiCreds, path, CheckStatus::Pass, format!("apiKey {}...", shown));
+        }
+        Err(e) => c.push(Section::ApiCreds, path, CheckStatus::Fail, format!("unreadable ({}); delete the file to re-derive", e)),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_private_key() {
+        let key = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+        assert_eq!(validate_private_key(&format!("0x{}", key)).unwrap(), key);
+        assert!(validate_private_key("your_private_key_here").is_err());
+        assert!(validate_private_key("abc").is_err());
+        assert!(validate_private_key(&key.replace('a', "g")).is_err());
+    }
+
+    #[test]
+    fn test_validate_address() {
+        let addr = "204f72f35326db932158cba6adff0b9a1da95e14";
+        assert_eq!(validate_address("X", &format!(" 0x{} ", addr)).unwrap(), addr);
+        assert!(validate_address("X", "target_whale_address_here").is_err());
+        let err = validate_address("FUNDER_ADDRESS", "0x1234").unwrap_err().to_string();
+        assert!(err.starts_with("FUNDER_ADDRESS must be exactly 40 hex characters"));
+    }
+
+    #[test]
+    fn test_report_gate_lists_failures() {
+        let mut c = Checks { out: Vec::new() };
+        c.push(Section::Config, "PRIVATE_KEY", CheckStatus::Pass, "ok");
+        c.push(Section::Approvals, "Missing approval", CheckStatus::Fail, "USDC allowance for CTF Exchange");
+        c.push(Section::Balances, "MATIC (signer gas)", CheckStatus::Warn, "0.0 MATIC");
+        let report = PreflightReport::new(c.out);
+        assert!(!report.ok);
+        assert_eq!(report.warnings().count(), 1);
+        let err = report.gate().unwrap_err().to_string();
+        assert!(err.contains("[APPROVALS] Missing approval: USDC allowance for CTF Exchange"));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["ok"], false);
+        assert_eq!(json["checks"][1]["section"], "approvals");
+        assert_eq!(json["checks"][1]["status"], "fail");
+    }
+}
@@ -0,0 +1,509 @@
+//! RPC provider list with health scoring and failover
+//!
+//! Providers come from `RPC_PROVIDERS` plus the legacy `ALCHEMY_API_KEY` /
+//! `CHAINSTACK_API_KEY` keys, which are appended as lower-priority entries so
+//! existing `.env` files keep working.
+//!
+//! `RPC_PROVIDERS` is a comma-separated list of providers, each a set of
+//! `;`-separated `key=value` fields:
+//!
+//! ```text
+//! RPC_PROVIDERS=name=primary;wss=wss://a.example/KEY;http=https://a.example/KEY;priority=1,name=backup;wss=wss://b.example/KEY
+//! ```
+//!
+//! Lower `priority` is preferred (default: position in the list). A provider
+//! needs at least one of `wss` / `http`.
+
+use anyhow::{Result, anyhow};
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::preflight::is_placeholder;
+
+pub const DEFAULT_RPC_URL: &str = "https://polygon-rpc.com";
+
+const ALCHEMY_PRIORITY: u32 = 100;
+const CHAINSTACK_PRIORITY: u32 = 101;
+
+/// Consecutive session failures on one provider before failing over
+pub const FAILOVER_AFTER_FAILURES: u32 = 3;
+/// How long a failed-over provider is avoided
+const FAILOVER_COOLDOWN: Duration = Duration::from_secs(120);
+/// A WebSocket session that stayed up this long counts as a success even if it
+/// eventually dropped
+pub const HEALTHY_SESSION: Duration = Duration::from_secs(60);
+const CONSECUTIVE_FAILURE_PENALTY: f64 = 25.0;
+const ERROR_RATE_PENALTY: f64 = 50.0;
+const COOLDOWN_PENALTY: f64 = 1_000.0;
+
+// ============================================================================
+// Legacy Keys
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RpcProvider {
+    Alchemy(String),
+    Chainstack(String),
+    Public,
+}
+
+impl RpcProvider {
+    /// Every keyed provider with a usable key, Alchemy first.
+    pub fn keyed_from_env() -> Vec<Self> {
+        let key = |var: &str| env::var(var).ok().map(|k| k.trim().to_string()).filter(|k| !is_placeholder(k));
+        let mut out = Vec::new();
+        if let Some(k) = key("ALCHEMY_API_KEY") {
+            out.push(RpcProvider::Alchemy(k));
+        }
+        if let Some(k) = key("CHAINSTACK_API_KEY") {
+            out.push(RpcProvider::Chainstack(k));
+        }
+        out
+    }
+
+    /// Like `from_env`, but a keyed provider is required and a placeholder key
+    /// is an error rather than silently skipped.
+    pub fn from_env_strict() -> Result<Self> {
+        if let Ok(key) = env::var("ALCHEMY_API_KEY") {
+            if is_placeholder(&key) {
+                anyhow::bail!(
+                    "ALCHEMY_API_KEY is set but has placeholder value.\n\
+                    Get your API key from https://www.alchemy.com/ (free tier available)\n\
+                    Then add it to your .env file"
+                );
+            }
+            return Ok(RpcProvider::Alchemy(key.trim().to_string()));
+        }
+        if let Ok(key) = env::var("CHAINSTACK_API_KEY") {
+            if is_placeholder(&key) {
+                anyhow::bail!(
+                    "CHAINSTACK_API_KEY is set but has placeholder value.\n\
+                    Get your API key from https://chainstack.com/ (free tier available)\n\
+                    Or use ALCHEMY_API_KEY instead (recommended for beginners)"
+                );
+            }
+            return Ok(RpcProvider::Chainstack(key.trim().to_string()));
+        }
+        anyhow::bail!(
+            "WebSocket API key required!\n\
+            \n\
+            Set RPC_PROVIDERS, ALCHEMY_API_KEY or CHAINSTACK_API_KEY in your .env file.\n\
+            \n\
+            Recommended (beginners): ALCHEMY_API_KEY\n\
+            1. Sign up at https://www.alchemy.com/\n\
+            2. Create app (Polygon Mainnet)\n\
+            3. Copy API key to .env file\n\
+            \n\
+            Alternative: CHAINSTACK_API_KEY\n\
+            1. Sign up at https://chainstack.com/\n\
+            2. Create Polygon node\n\
+            3. Copy API key to .env file\n\
+            \n\
+            Run 'cargo run --release --bin check_config' to validate your setup"
+        )
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RpcProvider::Alchemy(_) => "Alchemy",
+            RpcProvider::Chainstack(_) => "Chainstack",
+            RpcProvider::Public => "Public RPC",
+        }
+    }
+
+    pub fn http_url(&self) -> String {
+        match self {
+            RpcProvider::Alchemy(k) => format!("https://polygon-mainnet.g.alchemy.com/v2/{}", k),
+            RpcProvider::Chainstack(k) => format!("https://polygon-mainnet.core.chainstack.com/{}", k),
+            RpcProvider::Public => DEFAULT_RPC_URL.to_string(),
+        }
+    }
+
+    /// Log subscriptions need a keyed provider; the public RPC has no WebSocket.
+    pub fn wss_url(&self) -> Option<String> {
+        match self {
+            RpcProvider::Alchemy(k) => Some(format!("wss://polygon-mainnet.g.alchemy.com/v2/{}", k)),
+            RpcProvider::Chainstack(k) => Some(format!("wss://polygon-mainnet.core.chainstack.com/{}", k)),
+            RpcProvider::Public => None,
+        }
+    }
+
+    fn to_config(&self) -> ProviderConfig {
+        ProviderConfig {
+            name: self.label().to_lowercase(),
+            wss_url: self.wss_url(),
+            http_url: Some(self.http_url()),
+            priority: match self {
+                RpcProvider::Alchemy(_) => ALCHEMY_PRIORITY,
+                RpcProvider::Chainstack(_) => CHAINSTACK_PRIORITY,
+                RpcProvider::Public => u32::MAX,
+            },
+        }
+    }
+}
+
+// ============================================================================
+// Provider List
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderConfig {
+    /// Used in logs instead of the URL, which usually embeds an API key
+    pub name: String,
+    pub wss_url: Option<String>,
+    pub http_url: Option<String>,
+    /// Lower is preferred
+    pub priority: u32,
+}
+
+/// Parse an `RPC_PROVIDERS` value.
+pub fn parse_provider_list(spec: &str) -> Result<Vec<ProviderConfig>> {
+    let mut out = Vec::new();
+    for (i, entry) in spec.split(',').map(str::trim).filter(|e| !e.is_empty()).enumerate() {
+        let mut p = ProviderConfig {
+            name: format!("provider{}", i + 1),
+            wss_url: None,
+            http_url: None,
+            priority: i as u32 + 1,
+        };
+        for field in entry.split(';').map(str::trim).filter(|f| !f.is_empty()) {
+            let (key, value) = field.split_once('=')
+                .ok_or_else(|| anyhow!("RPC_PROVIDERS entry {}: expected key=value, got '{}'", i + 1, field))?;
+            let value = value.trim();
+            match key.trim().to_lowercase().as_str() {
+                "name" => p.name = value.to_string(),
+                "wss" | "ws" => {
+                    if !value.starts_with("wss://") && !value.starts_with("ws://") {
+                        anyhow::bail!("RPC_PROVIDERS entry {}: wss must start with wss:// or ws://", i + 1);
+                    }
+                    p.wss_url = Some(value.to_string());
+                }
+                "http" | "https" => {
+                    if !value.starts_with("https://") && !value.starts_with("http://") {
+                        anyhow::bail!("RPC_PROVIDERS entry {}: http must start with https:// or http://", i + 1);
+                    }
+                    p.http_url = Some(value.to_string());
+                }
+                "priority" => {
+                    p.priority = value.parse()
+                        .map_err(|_| anyhow!("RPC_PROVIDERS entry {}: priority must be a non-negative integer", i + 1))?;
+                }
+                other => anyhow::bail!("RPC_PROVIDERS entry {}: unknown field '{}' (use name, wss, http, priority)", i + 1, other),
+            }
+        }
+        if p.wss_url.is_none() && p.http_url.is_none() {
+            anyhow::bail!("RPC_PROVIDERS entry {} ({}): needs a wss or http URL", i + 1, p.name);
+        }
+        out.push(p);
+    }
+    Ok(out)
+}
+
+/// All configured providers, preferred first.
+pub fn providers_from_env() -> Result<Vec<ProviderConfig>> {
+    let mut providers = match env::var("RPC_PROVIDERS") {
+        Ok(spec) => parse_provider_list(&spec)?,
+        Err(_) => Vec::new(),
+    };
+    providers.extend(RpcProvider::keyed_from_env().iter().map(RpcProvider::to_config));
+    providers.sort_by_key(|p| p.priority);
+    Ok(providers)
+}
+
+/// Providers usable for log subscriptions. Errors with setup help when none is
+/// configured.
+pub fn ws_providers_from_env() -> Result<Vec<ProviderConfig>> {
+    let providers: Vec<ProviderConfig> = providers_from_env()?
+        .into_iter()
+        .filter(|p| p.wss_url.is_some())
+        .collect();
+    if providers.is_empty() {
+        // Surfaces the placeholder / missing-key guidance
+        RpcProvider::from_env_strict()?;
+        anyhow::bail!("No WebSocket provider configured. Add a wss= entry to RPC_PROVIDERS.");
+    }
+    Ok(providers)
+}
+
+/// HTTP JSON-RPC endpoint of the preferred provider, or the public RPC
+pub fn rpc_http_url() -> String {
+    providers_from_env()
+        .ok()
+        .and_then(|ps| ps.into_iter().find_map(|p| p.http_url))
+        .unwrap_or_else(|| DEFAULT_RPC_URL.to_string())
+}
+
+/// Display name of the provider behind `rpc_http_url`
+pub fn rpc_http_label() -> String {
+    providers_from_env()
+        .ok()
+        .and_then(|ps| ps.into_iter().find(|p| p.http_url.is_some()).map(|p| p.name))
+        .unwrap_or_else(|| "Public RPC (may have rate limits)".to_string())
+}
+
+// ============================================================================
+// Health & Failover
+// ============================================================================
+
+#[derive(Debug, Clone, Default)]
+pub struct ProviderHealth {
+    pub successes: u64,
+    pub failures: u64,
+    pub consecutive_failures: u32,
+    pub cooldown_until: Option<Instant>,
+}
+
+impl ProviderHealth {
+    fn in_cooldown(&self, now: Instant) -> bool {
+        self.cooldown_until.is_some_and(|t| t > now)
+    }
+}
+
+/// Tracks provider health for a long-running connection and decides when to
+/// switch providers.
+#[derive(Debug, Clone)]
+pub struct ProviderPool {
+    providers: Vec<ProviderConfig>,
+    health: Vec<ProviderHealth>,
+    current: usize,
+    /// Provider this pool starts on and fails back to
+    home: usize,
+    slot: usize,
+    /// Current provider of every pool created together, by slot
+    claims: Arc<Mutex<Vec<usize>>>,
+}
+
+impl ProviderPool {
+    /// `providers` must be non-empty and sorted by preference.
+    pub fn new(providers: Vec<ProviderConfig>) -> Self {
+        Self::for_slots(providers, 1).remove(0)
+    }
+
+    /// One pool per redundant subscription, slot `n` starting on provider `n`.
+    /// Failover avoids the providers the other slots are currently on.
+    pub fn for_slots(providers: Vec<ProviderConfig>, slots: usize) -> Vec<Self> {
+        assert!(!providers.is_empty(), "ProviderPool needs at least one provider");
+        let homes: Vec<usize> = (0..slots.max(1)).map(|slot| slot % providers.len()).collect();
+        let claims = Arc::new(Mutex::new(homes.clone()));
+        let health = vec![ProviderHealth::default(); providers.len()];
+        homes.into_iter().enumerate().map(|(slot, home)| Self {
+            providers: providers.clone(),
+            health: health.clone(),
+            current: home,
+            home,
+            slot,
+            claims: claims.clone(),
+        }).collect()
+    }
+
+    pub fn current(&self) -> &ProviderConfig {
+        &self.providers[self.current]
+    }
+
+    pub fn providers(&self) -> &[ProviderConfig] {
+        &self.providers
+    }
+
+    pub fn health(&self, index: usize) -> &ProviderHealth {
+        &self.health[index]
+    }
+
+    /// Health score, lower is better: priority plus penalties for the current
+    /// failure streak, the lifetime error rate and an active cooldown.
+    pub fn score(&self, index: usize, now: Instant) -> f64 {
+        let h = &self.health[index];
+        let attempts = (h.successes + h.failures).max(1) as f64;
+        let mut score = self.providers[index].priority as f64
+            + CONSECUTIVE_FAILURE_PENALTY * h.consecutive_failures as f64
+            + ERROR_RATE_PENALTY * h.failures as f64 / attempts;
+        if h.in_cooldown(now) {
+            score += COOLDOWN_PENALTY;
+        }
+        score
+    }
+
+    fn best_excluding(&self, exclude: &[usize], now: Instant) -> Option<usize> {
+        (0..self.providers.len())
+            .filter(|i| !exclude.contains(i))
+            .min_by(|a, b| self.score(*a, now).partial_cmp(&self.score(*b, now)).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    /// Providers the other slots are on
+    fn used_by_others(&self) -> Vec<usize> {
+        let claims = self.claims.lock().unwrap();
+        claims.iter().enumerate().filter(|(slot, _)| *slot != self.slot).map(|(_, i)| *i).collect()
+    }
+
+    fn switch_to(&mut self, index: usize) {
+        self.current = index;
+        self.claims.lock().unwrap()[self.slot] = index;
+    }
+
+    pub fn record_success(&mut self) {
+        let h = &mut self.health[self.current];
+        h.successes += 1;
+        h.consecutive_failures = 0;
+    }
+
+    /// Record a failed session on the current provider. After
+    /// `FAILOVER_AFTER_FAILURES` in a row it is put in cooldown and the
+    /// healthiest provider no other slot is on becomes current (any other
+    /// provider when all are taken); returns true on failover.
+    pub fn record_failure(&mut self, now: Instant) -> bool {
+        let h = &mut self.health[self.current];
+        h.failures += 1;
+        h.consecutive_failures += 1;
+        if h.consecutive_failures < FAILOVER_AFTER_FAILURES || self.providers.len() < 2 {
+            return false;
+        }
+        h.cooldown_until = Some(now + FAILOVER_COOLDOWN);
+        h.consecutive_failures = 0;
+
+        let mut exclude = self.used_by_others();
+        exclude.push(self.current);
+        let next = self.best_excluding(&exclude, now)
+            .or_else(|| self.best_excluding(&[self.current], now));
+        match next {
+            Some(next) => {
+                self.switch_to(next);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move back to this pool's home provider once its cooldown has passed and
+    /// no other slot is on it. Returns the provider switched to.
+    pub fn maybe_fail_back(&mut self, now: Instant) -> Option<&ProviderConfig> {
+        if self.current == self.home
+            || self.health[self.home].in_cooldown(now)
+            || self.used_by_others().contains(&self.home)
+        {
+            return None;
+        }
+        self.switch_to(self.home);
+        Some(&self.providers[self.home])
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(name: &str, priority: u32) -> ProviderConfig {
+        ProviderConfig {
+            name: name.into(),
+            wss_url: Some(format!("wss://{}.example", name)),
+            http_url: None,
+            priority,
+        }
+    }
+
+    #[test]
+    fn test_parse_provider_list() {
+        let ps = parse_provider_list(
+            "name=qn;wss=wss://qn.example/k;http=https://qn.example/k;priority=5, wss=wss://b.example",
+        ).unwrap();
+        assert_eq!(ps.len(), 2);
+        assert_eq!(ps[0].name, "qn");
+        assert_eq!(ps[0].priority, 5);
+        assert_eq!(ps[0].http_url.as_deref(), Some("https://qn.example/k"));
+        assert_eq!(ps[1].name, "provider2");
+        assert_eq!(ps[1].priority, 2);
+        assert!(ps[1].http_url.is_none());
+
+        assert!(parse_provider_list("name=x").is_err());
+        assert!(parse_provider_list("wss=https://wrong.example").is_err());
+        assert!(parse_provider_list("wss=wss://a;colour=red").is_err());
+    }
+
+    #[test]
+    fn test_legacy_provider_urls_match() {
+        let p = RpcProvider::Chainstack("k".into());
+        assert_eq!(p.http_url(), "https://polygon-mainnet.core.chainstack.com/k");
+        assert_eq!(p.wss_url().unwrap(), "wss://polygon-mainnet.core.chainstack.com/k");
+        assert_eq!(RpcProvider::Public.wss_url(), None);
+        assert_eq!(p.to_config().name, "chainstack");
+    }
+
+    #[test]
+    fn test_fails_over_after_repeated_errors() {
+        let now = Instant::now();
+        let mut pool = ProviderPool::new(vec![provider("a", 1), provider("b", 2), provider("c", 3)]);
+
+        assert!(!pool.record_failure(now));
+        assert!(!pool.record_failure(now));
+        assert_eq!(pool.current().name, "a");
+        assert!(pool.record_failure(now));
+        assert_eq!(pool.current().name, "b");
+
+        // "a" is cooling down, so no fail-back yet
+        assert!(pool.maybe_fail_back(now).is_none());
+        let later = now + FAILOVER_COOLDOWN + Duration::from_secs(1);
+        assert_eq!(pool.maybe_fail_back(later).map(|p| p.name.clone()), Some("a".to_string()));
+    }
+
+    #[test]
+    fn test_failover_prefers_healthier_provider() {
+        let now = Instant::now();
+        let mut pool = ProviderPool::new(vec![provider("a", 1), provider("b", 2), provider("c", 3)]);
+        // "b" has a poor track record
+        pool.current = 1;
+        pool.record_failure(now);
+        pool.current = 0;
+
+        for _ in 0..FAILOVER_AFTER_FAILURES {
+            pool.record_failure(now);
+        }
+        assert_eq!(pool.current().name, "c");
+    }
+
+    #[test]
+    fn test_success_resets_streak() {
+        let now = Instant::now();
+        let mut pool = ProviderPool::new(vec![provider("a", 1), provider("b", 2)]);
+        pool.record_failure(now);
+        pool.record_failure(now);
+        pool.record_success();
+        assert!(!pool.record_failure(now));
+        assert_eq!(pool.current().name, "a");
+    }
+
+    #[test]
+    fn test_slots_fail_over_to_free_providers_and_back_home() {
+        let now = Instant::now();
+        let mut pools = ProviderPool::for_slots(vec![provider("a", 1), provider("b", 2), provider("c", 3)], 2);
+        assert_eq!((pools[0].current().name.as_str(), pools[1].current().name.as_str()), ("a", "b"));
+
+        // Slot 0 skips "b", which slot 1 is on
+        for _ in 0..FAILOVER_AFTER_FAILURES {
+            pools[0].record_failure(now);
+        }
+        assert_eq!(pools[0].current().name, "c");
+
+        // Slot 1 takes the free "a" and returns to its own "b", not to "a"
+        for _ in 0..FAILOVER_AFTER_FAILURES {
+            pools[1].record_failure(now);
+        }
+        assert_eq!(pools[1].current().name, "a");
+        let later = now + FAILOVER_COOLDOWN + Duration::from_secs(1);
+        assert!(pools[0].maybe_fail_back(later).is_none());
+        assert_eq!(pools[1].maybe_fail_back(later).map(|p| p.name.clone()), Some("b".to_string()));
+        assert_eq!(pools[0].maybe_fail_back(later).map(|p| p.name.clone()), Some("a".to_string()));
+    }
+
+    #[test]
+    fn test_single_provider_never_fails_over() {
+        let now = Instant::now();
+        let mut pool = ProviderPool::new(vec![provider("a", 1)]);
+        for _ in 0..10 {
+            assert!(!pool.record_failure(now));
+        }
+        assert_eq!(pool.current().name, "a");
+    }
+}
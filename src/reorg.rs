@@ -0,0 +1,246 @@
+//! Chain reorg tracking for copied events
+//!
+//! Subscriptions re-send a log with `"removed": true` when the block that
+//! contained it is reorged out. `RecentEvents` remembers what the bot did with
+//! each event for a short window so a removal can be matched to the trade we
+//! copied from it. The same transaction is often re-included in a later block;
+//! that is reported as a restore so an unwind can be called off.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::event_dedup::EventKey;
+
+/// How long events are remembered. Polygon reorgs are a few blocks deep, so
+/// this is generous.
+pub const REORG_WINDOW: Duration = Duration::from_secs(600);
+/// Wait this long after a reorg before unwinding, in case the whale's
+/// transaction lands again in a new block.
+pub const RESTORE_GRACE: Duration = Duration::from_secs(20);
+
+/// What the bot did with an event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CopyOutcome {
+    /// Order still in flight
+    Pending,
+    NotCopied,
+    Copied { shares: f64, price: f64 },
+}
+
+#[derive(Debug, Clone)]
+struct RecentEvent {
+    block_number: u64,
+    token_id: String,
+    side_is_buy: bool,
+    seen_at: Instant,
+    outcome: CopyOutcome,
+    removed: bool,
+    restored: bool,
+}
+
+/// An event whose source log was reorged out, with what we did about it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReorgedEvent {
+    pub key: EventKey,
+    pub block_number: u64,
+    pub token_id: String,
+    pub side_is_buy: bool,
+    pub outcome: CopyOutcome,
+}
+
+impl ReorgedEvent {
+    /// Shares and price we filled, if the event was copied
+    pub fn copied(&self) -> Option<(f64, f64)> {
+        match self.outcome {
+            CopyOutcome::Copied { shares, price } if shares > 0.0 => Some((shares, price)),
+            _ => None,
+        }
+    }
+}
+
+/// Result of a `removed: true` log
+#[derive(Debug, Clone, PartialEq)]
+pub enum Removal {
+    /// Not in the window (too old, or never acted on)
+    Unknown,
+    /// Another provider already delivered this removal
+    AlreadyHandled,
+    /// Our order is still in flight; `set_outcome` will report the reorg
+    Pending,
+    Reorged(ReorgedEvent),
+}
+
+#[derive(Default)]
+pub struct RecentEvents {
+    events: HashMap<EventKey, RecentEvent>,
+}
+
+impl RecentEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember an event the bot is about to act on.
+    pub fn record(&mut self, key: EventKey, block_number: u64, token_id: &str, side_is_buy: bool, now: Instant) {
+        self.prune(now);
+        self.events.entry(key).or_insert_with(|| RecentEvent {
+            block_number,
+            token_id: token_id.to_string(),
+            side_is_buy,
+            seen_at: now,
+            outcome: CopyOutcome::Pending,
+            removed: false,
+            restored: false,
+        });
+    }
+
+    /// Store the order outcome. Returns the event if its log was removed while
+    /// the order was in flight.
+    pub fn set_outcome(&mut self, key: &EventKey, outcome: CopyOutcome) -> Option<ReorgedEvent> {
+        let e = self.events.get_mut(key)?;
+        e.outcome = outcome;
+        (e.removed && !e.restored).then(|| reorged(key, e))
+    }
+
+    /// Store one outcome for every log of a merged trade. Returns the events
+    /// whose log was removed while the order was in flight.
+    pub fn set_outcomes(&mut self, keys: &[EventKey], outcome: CopyOutcome) -> Vec<ReorgedEvent> {
+        keys.iter().filter_map(|k| self.set_outcome(k, outcome)).collect()
+    }
+
+    /// Handle a `removed: true` delivery of `key`.
+    pub fn mark_removed(&mut self, key: &EventKey) -> Removal {
+        let Some(e) = self.events.get_mut(key) else { return Removal::Unknown };
+        if e.removed {
+            return Removal::AlreadyHandled;
+        }
+        e.removed = true;
+        e.restored = false;
+        match e.outcome {
+            CopyOutcome::Pending => Removal::Pending,
+            _ => Removal::Reorged(reorged(key, e)),
+        }
+    }
+
+    /// Handle a removal of every event of `tx_hash` whose log index matches,
+    /// e.g. all fills acted on from one pending transaction.
+    pub fn mark_tx_removed(&mut self, tx_hash: &str, log_index: impl Fn(u64) -> bool) -> Vec<Removal> {
+        let mut keys: Vec<EventKey> = self.events.keys()
+            .filter(|k| k.0.eq_ignore_ascii_case(tx_hash) && log_index(k.1))
+            .cloned()
+            .collect();
+        keys.sort();
+        keys.iter().map(|k| self.mark_removed(k)).collect()
+    }
+
+    /// A live log for `tx_hash` arrived. Returns true if it re-includes a
+    /// transaction whose log had been removed.
+    pub fn mark_restored(&mut self, tx_hash: &str) -> bool {
+        let mut restored = false;
+        for (key, e) in self.events.iter_mut() {
+            if e.removed && !e.restored && key.0.eq_ignore_ascii_case(tx_hash) {
+                e.restored = true;
+                restored = true;
+            }
+        }
+        restored
+    }
+
+    /// True while the event is reorged out and its transaction has not come back
+    pub fn is_reorged(&self, key: &EventKey) -> bool {
+        self.events.get(key).is_some_and(|e| e.removed && !e.restored)
+    }
+
+    fn prune(&mut self, now: Instant) {
+        self.events.retain(|_, e| now.saturating_duration_since(e.seen_at) < REORG_WINDOW);
+    }
+}
+
+fn reorged(key: &EventKey, e: &RecentEvent) -> ReorgedEvent {
+    ReorgedEvent {
+        key: key.clone(),
+        block_number: e.block_number,
+        token_id: e.token_id.clone(),
+        side_is_buy: e.side_is_buy,
+        outcome: e.outcome,
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(tx: &str) -> EventKey {
+        (tx.to_string(), 3)
+    }
+
+    #[test]
+    fn test_reorg_of_copied_trade() {
+        let now = Instant::now();
+        let mut r = RecentEvents::new();
+        r.record(key("0xa"), 100, "123", true, now);
+        assert_eq!(r.set_outcome(&key("0xa"), CopyOutcome::Copied { shares: 10.0, price: 0.5 }), None);
+
+        let Removal::Reorged(ev) = r.mark_removed(&key("0xa")) else { panic!("expected reorg") };
+        assert_eq!(ev.copied(), Some((10.0, 0.5)));
+        assert_eq!(ev.block_number, 100);
+        assert_eq!(r.mark_removed(&key("0xa")), Removal::AlreadyHandled);
+        assert!(r.is_reorged(&key("0xa")));
+
+        assert!(r.mark_restored("0xA"));
+        assert!(!r.is_reorged(&key("0xa")));
+    }
+
+    #[test]
+    fn test_removal_while_order_in_flight() {
+        let now = Instant::now();
+        let mut r = RecentEvents::new();
+        r.record(key("0xb"), 100, "123", false, now);
+        assert_eq!(r.mark_removed(&key("0xb")), Removal::Pending);
+        let ev = r.set_outcome(&key("0xb"), CopyOutcome::NotCopied).unwrap();
+        assert_eq!(ev.copied(), None);
+    }
+
+    #[test]
+    fn test_merged_fills_share_outcome() {
+        let now = Instant::now();
+        let mut r = RecentEvents::new();
+        let keys = [("0xf".to_string(), 3), ("0xf".to_string(), 5)];
+        for k in &keys {
+            r.record(k.clone(), 100, "123", true, now);
+        }
+        assert!(r.set_outcomes(&keys, CopyOutcome::Copied { shares: 30.0, price: 0.5 }).is_empty());
+
+        // Removal of the second log is matched to the copy made from both
+        let Removal::Reorged(ev) = r.mark_removed(&keys[1]) else { panic!("expected reorg") };
+        assert_eq!(ev.copied(), Some((30.0, 0.5)));
+    }
+
+    #[test]
+    fn test_remove_every_matching_event_of_tx() {
+        let now = Instant::now();
+        let mut r = RecentEvents::new();
+        r.record(("0xe".into(), u64::MAX), 0, "1", true, now);
+        r.record(("0xe".into(), u64::MAX - 1), 0, "2", true, now);
+        r.record(key("0xe"), 100, "1", true, now);
+        r.set_outcome(&("0xe".into(), u64::MAX), CopyOutcome::NotCopied);
+
+        let removals = r.mark_tx_removed("0xE", |i| i > 1000);
+        assert!(matches!(removals.as_slice(), [Removal::Pending, Removal::Reorged(_)]));
+        assert!(!r.is_reorged(&key("0xe")));
+    }
+
+    #[test]
+    fn test_unknown_and_expired_events() {
+        let now = Instant::now();
+        let mut r = RecentEvents::new();
+        assert_eq!(r.mark_removed(&key("0xc")), Removal::Unknown);
+        r.record(key("0xc"), 100, "123", true, now);
+        r.record(key("0xd"), 101, "123", true, now + REORG_WINDOW);
+        assert_eq!(r.mark_removed(&key("0xc")), Removal::Unknown);
+    }
+}
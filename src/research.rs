@@ -0,0 +1,905 @@
+//! Trader research
+//!
+//! Builds a candidate trader universe from recent `OrdersFilled` logs on the
+//! monitored exchanges, caches the decoded fills under `trader_data_cache/`, and
+//! computes per-trader performance so the best addresses can be copied.
+//!
+//! P&L is realised-only: a position contributes once the trader sells shares they
+//! bought inside the scanned window (average-cost basis). Sells of inventory
+//! acquired before the window are ignored for P&L but still count as volume.
+
+use anyhow::{Result, anyhow};
+use alloy::primitives::U256;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::audit::fetch_block_timestamps;
+use crate::settings::{MONITORED_ADDRESSES, ORDERS_FILLED_EVENT_SIGNATURE};
+use crate::market_category::{self, OTHER};
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+pub const CACHE_DIR: &str = "trader_data_cache";
+pub const FILLS_CACHE_FILE: &str = "trader_data_cache/fills.csv";
+pub const MARKET_END_CACHE_FILE: &str = "trader_data_cache/market_end_times.json";
+const GAMMA_API_BASE: &str = "https://gamma-api.polymarket.com";
+const GAMMA_BATCH_SIZE: usize = 20;
+const GAMMA_PAGE_SIZE: usize = 500;
+const GAMMA_MAX_PAGES: usize = 20;
+
+/// A trader's first buy in a market counts as "early" inside this leading share of
+/// the market's observed buys.
+pub const EARLY_ENTRY_FRACTION: f64 = 0.25;
+
+/// Blocks per eth_getLogs request; halved on provider "too many results" errors.
+const LOG_CHUNK_BLOCKS: u64 = 500;
+const MIN_LOG_CHUNK_BLOCKS: u64 = 10;
+
+// ============================================================================
+// Fill Records
+// ============================================================================
+
+/// One decoded `OrdersFilled` log, seen from the maker (the trader whose order filled).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fill {
+    pub block: u64,
+    /// Unix seconds, interpolated between the timestamps of the fetched range ends
+    pub timestamp: i64,
+    pub tx_hash: String,
+    pub log_index: u64,
+    pub exchange: String,
+    pub trader: String,
+    pub token_id: String,
+    pub side_is_buy: bool,
+    pub shares: f64,
+    pub usd: f64,
+    pub price: f64,
+}
+
+fn hex_u64(v: &serde_json::Value) -> Option<u64> {
+    let s = v.as_str()?;
+    u64::from_str_radix(s.trim_start_matches("0x"), 16).ok()
+}
+
+fn data_word(data: &str, idx: usize) -> Option<U256> {
+    let hex = data.strip_prefix("0x").unwrap_or(data);
+    let word = hex.get(idx * 64..(idx + 1) * 64)?;
+    U256::from_str_radix(word, 16).ok()
+}
+
+/// Decode an eth_getLogs entry. Returns None for malformed logs or fills that do
+/// not swap USDC against an outcome token (e.g. token-for-token merges).
+pub fn decode_fill_log(log: &serde_json::Value) -> Option<Fill> {
+    let topics = log["topics"].as_array()?;
+    if topics.len() < 3 { return None; }
+    let trader = topics[2].as_str()?;
+    let trader = format!("0x{}", &trader.get(trader.len().checked_sub(40)?..)?).to_lowercase();
+
+    let data = log["data"].as_str()?;
+    let maker_asset = data_word(data, 0)?;
+    let taker_asset = data_word(data, 1)?;
+    let maker_amt: f64 = data_word(data, 2)?.to_string().parse().ok()?;
+    let taker_amt: f64 = data_word(data, 3)?.to_string().parse().ok()?;
+
+    // Same convention as the live parser: maker paying asset id 0 (USDC) is a BUY
+    let (token, side_is_buy, shares, usd) = if maker_asset.is_zero() && !taker_asset.is_zero() {
+        (taker_asset, true, taker_amt / 1e6, maker_amt / 1e6)
+    } else if taker_asset.is_zero() && !maker_asset.is_zero() {
+        (maker_asset, false, maker_amt / 1e6, taker_amt / 1e6)
+    } else {
+        return None;
+    };
+    if shares <= 0.0 { return None; }
+
+    Some(Fill {
+        block: hex_u64(&log["blockNumber"])?,
+        timestamp: 0,
+        tx_hash: log["transactionHash"].as_str().unwrap_or_default().to_string(),
+        log_index: hex_u64(&log["logIndex"]).unwrap_or_default(),
+        exchange: log["address"].as_str().unwrap_or_default().to_lowercase(),
+        trader,
+        token_id: token.to_string(),
+        side_is_buy,
+        shares,
+        usd,
+        price: usd / shares,
+    })
+}
+
+// ============================================================================
+// Log Fetching
+// ============================================================================
+
+pub(crate) async fn rpc_call(client: &reqwest::Client, rpc_url: &str, method: &str, params: serde_json::Value) -> Result<serde_json::Value> {
+    let resp: serde_json::Value = client
+        .post(rpc_url)
+        .json(&serde_json::json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 1 }))
+        .send()
+        .await?
+        .json()
+        .await?;
+    if let Some(err) = resp.get("error") {
+        return Err(anyhow!("{} failed: {}", method, err));
+    }
+    Ok(resp["result"].clone())
+}
+
+pub async fn latest_block(client: &reqwest::Client, rpc_url: &str) -> Result<u64> {
+    let result = rpc_call(client, rpc_url, "eth_blockNumber", serde_json::json!([])).await?;
+    hex_u64(&result).ok_or_else(|| anyhow!("Invalid eth_blockNumber response: {}", result))
+}
+
+/// Fetch and decode all OrdersFilled logs on the monitored exchanges in `[from, to]`.
+pub async fn fetch_fills(client: &reqwest::Client, rpc_url: &str, from: u64, to: u64) -> Result<Vec<Fill>> {
+    let mut fills = Vec::new();
+    let mut start = from;
+    let mut chunk = LOG_CHUNK_BLOCKS;
+
+    while start <= to {
+        let end = (start + chunk - 1).min(to);
+        let params = serde_json::json!([{
+            "fromBlock": format!("0x{:x}", start),
+            "toBlock": format!("0x{:x}", end),
+            "address": MONITORED_ADDRESSES,
+            "topics": [ORDERS_FILLED_EVENT_SIGNATURE],
+        }]);
+
+        match rpc_call(client, rpc_url, "eth_getLogs", params).await {
+            Ok(result) => {
+                let logs = result.as_array().cloned().unwrap_or_default();
+                let mut decoded: Vec<Fill> = logs.iter().filter_map(decode_fill_log).collect();
+                stamp_fills(client, rpc_url, start, end, &mut decoded).await;
+                fills.extend(decoded);
+                print!("\r   Scanned blocks {}..{} ({} fills)", start, end, fills.len());
+                start = end + 1;
+                chunk = LOG_CHUNK_BLOCKS;
+            }
+            Err(_) if chunk > MIN_LOG_CHUNK_BLOCKS => {
+                // Most providers cap results per request - retry with a smaller range
+                chunk /= 2;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    println!();
+    Ok(fills)
+}
+
+/// Fill timestamps by linear interpolation between the range end blocks.
+async fn stamp_fills(client: &reqwest::Client, rpc_url: &str, start: u64, end: u64, fills: &mut [Fill]) {
+    if fills.is_empty() { return; }
+    let times = fetch_block_timestamps(client, rpc_url, &[start, end]).await;
+    let (Some(&t0), Some(&t1)) = (times.get(&start), times.get(&end)) else { return };
+    let span = (end - start).max(1) as f64;
+    for f in fills.iter_mut() {
+        f.timestamp = t0 + (((f.block - start) as f64 / span) * (t1 - t0) as f64) as i64;
+    }
+}
+
+// ============================================================================
+// Cache
+// ============================================================================
+
+pub fn load_fill_cache() -> Result<Vec<Fill>> {
+    if !Path::new(FILLS_CACHE_FILE).exists() {
+        return Ok(Vec::new());
+    }
+    let mut reader = csv::Reader::from_path(FILLS_CACHE_FILE)?;
+    Ok(reader.deserialize::<Fill>().filter_map(|r| r.ok()).collect())
+}
+
+pub fn save_fill_cache(fills: &[Fill]) -> Result<()> {
+    fs::create_dir_all(CACHE_DIR)?;
+    let mut writer = csv::Writer::from_path(FILLS_CACHE_FILE)?;
+    for f in fills {
+        writer.serialize(f)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Bring the fill cache up to the chain head, covering at least the last `blocks`
+/// blocks. Only blocks past the newest cached fill are fetched unless `refresh`
+/// is set. Returns the fills inside the requested window.
+pub async fn sync_fill_cache(client: &reqwest::Client, rpc_url: &str, blocks: u64, refresh: bool) -> Result<Vec<Fill>> {
+    let head = latest_block(client, rpc_url).await?;
+    let window_start = head.saturating_sub(blocks);
+
+    let mut cached = if refresh { Vec::new() } else { load_fill_cache()? };
+    let cached_to = cached.iter().map(|f| f.block).max();
+    let cached_from = cached.iter().map(|f| f.block).min();
+
+    // Refetch everything if the cache does not reach back far enough
+    let fetch_from = match (cached_from, cached_to) {
+        (Some(lo), Some(hi)) if lo <= window_start => hi + 1,
+        _ => {
+            cached.clear();
+            window_start
+        }
+    };
+
+    if fetch_from <= head {
+        println!("📥 Fetching OrdersFilled logs for blocks {}..{}", fetch_from, head);
+        let fresh = fetch_fills(client, rpc_url, fetch_from, head).await?;
+        cached.extend(fresh);
+        cached.sort_by_key(|f| (f.block, f.log_index));
+        save_fill_cache(&cached)?;
+    } else {
+        println!("💾 Fill cache is up to date");
+    }
+
+    Ok(cached.into_iter().filter(|f| f.block >= window_start).collect())
+}
+
+// ============================================================================
+// Trader Metrics
+// ============================================================================
+
+#[derive(Debug, Default, Clone)]
+struct PositionState {
+    shares: f64,
+    cost: f64,
+    realised: f64,
+    closed_any: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TraderStats {
+    pub address: String,
+    pub trades: usize,
+    pub buys: usize,
+    pub sells: usize,
+    pub volume_usd: f64,
+    pub avg_trade_usd: f64,
+    pub realised_pnl: f64,
+    /// Realised P&L over the cost basis of the shares that were sold
+    pub roi: f64,
+    /// Share of positions with at least one sell that closed in profit
+    pub win_rate: f64,
+    pub markets: usize,
+    /// Share of volume in the trader's largest market (1.0 = single market)
+    pub concentration: f64,
+}
+
+pub fn compute_trader_stats(fills: &[Fill]) -> Vec<TraderStats> {
+    let mut by_trader: HashMap<&str, Vec<&Fill>> = HashMap::new();
+    for f in fills {
+        by_trader.entry(f.trader.as_str()).or_default().push(f);
+    }
+
+    by_trader.into_iter().map(|(address, mut trades)| {
+        trades.sort_by_key(|f| (f.block, f.log_index));
+
+        let mut positions: HashMap<&str, PositionState> = HashMap::new();
+        let mut volume_by_market: HashMap<&str, f64> = HashMap::new();
+        let mut closed_cost = 0.0;
+        let (mut buys, mut sells, mut volume) = (0, 0, 0.0);
+
+        for f in &trades {
+            volume += f.usd;
+            *volume_by_market.entry(f.token_id.as_str()).or_insert(0.0) += f.usd;
+            let pos = positions.entry(f.token_id.as_str()).or_default();
+            if f.side_is_buy {
+                buys += 1;
+                pos.shares += f.shares;
+                pos.cost += f.usd;
+            } else {
+                sells += 1;
+                let matched = f.shares.min(pos.shares);
+                if matched > 0.0 {
+                    let avg_cost = pos.cost / pos.shares;
+                    let cost_out = avg_cost * matched;
+                    pos.realised += matched * f.price - cost_out;
+                    pos.shares -= matched;
+                    pos.cost -= cost_out;
+                    pos.closed_any = true;
+                    closed_cost += cost_out;
+                }
+            }
+        }
+
+        let realised_pnl: f64 = positions.values().map(|p| p.realised).sum();
+        let closed: Vec<&PositionState> = positions.values().filter(|p| p.closed_any).collect();
+        let wins = closed.iter().filter(|p| p.realised > 0.0).count();
+        let top_market = volume_by_market.values().cloned().fold(0.0, f64::max);
+
+        TraderStats {
+            address: address.to_string(),
+            trades: trades.len(),
+            buys,
+            sells,
+            volume_usd: volume,
+            avg_trade_usd: volume / trades.len() as f64,
+            realised_pnl,
+            roi: if closed_cost > 0.0 { realised_pnl / closed_cost } else { 0.0 },
+            win_rate: if closed.is_empty() { 0.0 } else { wins as f64 / closed.len() as f64 },
+            markets: volume_by_market.len(),
+            concentration: if volume > 0.0 { top_market / volume } else { 0.0 },
+        }
+    }).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RankBy {
+    Pnl,
+    Roi,
+    WinRate,
+    Volume,
+}
+
+impl RankBy {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "pnl" => Ok(RankBy::Pnl),
+            "roi" => Ok(RankBy::Roi),
+            "winrate" | "win-rate" | "win_rate" => Ok(RankBy::WinRate),
+            "volume" => Ok(RankBy::Volume),
+            _ => Err(anyhow!("Invalid sort key '{}'. Must be one of: pnl, roi, winrate, volume", s)),
+        }
+    }
+}
+
+/// Filter by minimum trade count and sort descending by `rank_by`.
+pub fn rank_traders(mut stats: Vec<TraderStats>, rank_by: RankBy, min_trades: usize) -> Vec<TraderStats> {
+    stats.retain(|s| s.trades >= min_trades);
+    let key = |s: &TraderStats| match rank_by {
+        RankBy::Pnl => s.realised_pnl,
+        RankBy::Roi => s.roi,
+        RankBy::WinRate => s.win_rate,
+        RankBy::Volume => s.volume_usd,
+    };
+    stats.sort_by(|a, b| key(b).partial_cmp(&key(a)).unwrap_or(std::cmp::Ordering::Equal));
+    stats
+}
+
+// ============================================================================
+// Market End Times
+// ============================================================================
+
+fn parse_gamma_time(s: &str) -> Option<i64> {
+    if let Ok(t) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Some(t.timestamp());
+    }
+    // closedTime comes back as "2024-11-06 05:32:29+00"
+    chrono::DateTime::parse_from_str(&format!("{}00", s), "%Y-%m-%d %H:%M:%S%z")
+        .ok()
+        .map(|t| t.timestamp())
+}
+
+/// Resolution time (unix seconds) per token: `closedTime` when the market has
+/// closed, `endDate` otherwise. Cached in MARKET_END_CACHE_FILE; only unknown
+/// tokens hit the Gamma API.
+pub async fn load_market_end_times(client: &reqwest::Client, token_ids: &HashSet<&str>) -> Result<HashMap<String, i64>> {
+    let mut cache: HashMap<String, i64> = fs::read_to_string(MARKET_END_CACHE_FILE)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+
+    let missing: Vec<&str> = token_ids.iter().copied().filter(|t| !cache.contains_key(*t)).collect();
+    for batch in missing.chunks(GAMMA_BATCH_SIZE) {
+        let query: Vec<String> = batch.iter().map(|t| format!("clob_token_ids={}", t)).collect();
+        let url = format!("{}/markets?{}", GAMMA_API_BASE, query.join("&"));
+        let Ok(resp) = client.get(&url).send().await else { continue };
+        let Ok(markets) = resp.json::<Vec<serde_json::Value>>().await else { continue };
+
+        for m in markets {
+            let end = m["closedTime"].as_str().and_then(parse_gamma_time)
+                .or_else(|| m["endDate"].as_str().and_then(parse_gamma_time));
+            let Some(end) = end else { continue };
+            // clobTokenIds is a JSON-encoded string array
+            let ids: Vec<String> = m["clobTokenIds"].as_str()
+                .and_then(|s| serde_json::from_str(s).ok())
+                .unwrap_or_default();
+            for id in ids {
+                cache.insert(id, end);
+            }
+        }
+    }
+
+    if !missing.is_empty() {
+        fs::create_dir_all(CACHE_DIR)?;
+        fs::write(MARKET_END_CACHE_FILE, serde_json::to_string(&cache)?)?;
+    }
+    Ok(cache)
+}
+
+// ============================================================================
+// Risk Metrics
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskStats {
+    pub address: String,
+    pub trades: usize,
+    pub closed_trades: usize,
+    pub realised_pnl: f64,
+    /// Mean / stddev of per-close returns (0 with fewer than two closes)
+    pub sharpe: f64,
+    /// Largest peak-to-trough drop of the cumulative realised P&L curve
+    pub max_drawdown_usd: f64,
+    /// Share-weighted time between entry and exit
+    pub avg_hold_mins: f64,
+    /// Share of volume traded within the late window before resolution
+    pub late_volume_share: f64,
+    /// Average adverse price move from their fill to the next block's fill on the
+    /// same token, in cents. Lower = easier to copy.
+    pub next_block_move_cents: f64,
+}
+
+/// CLI thresholds for FindLowRiskTraders. `None` disables a filter.
+#[derive(Debug, Clone, Default)]
+pub struct RiskFilter {
+    pub min_trades: usize,
+    pub min_sharpe: Option<f64>,
+    pub max_drawdown_usd: Option<f64>,
+    pub min_hold_mins: Option<f64>,
+    pub max_late_share: Option<f64>,
+    pub max_move_cents: Option<f64>,
+}
+
+impl RiskFilter {
+    pub fn passes(&self, s: &RiskStats) -> bool {
+        s.trades >= self.min_trades
+            && self.min_sharpe.is_none_or(|v| s.sharpe >= v)
+            && self.max_drawdown_usd.is_none_or(|v| s.max_drawdown_usd <= v)
+            && self.min_hold_mins.is_none_or(|v| s.avg_hold_mins >= v)
+            && self.max_late_share.is_none_or(|v| s.late_volume_share <= v)
+            && self.max_move_cents.is_none_or(|v| s.next_block_move_cents <= v)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct RiskPosition {
+    shares: f64,
+    cost: f64,
+    /// Sum of shares * entry timestamp, for the share-weighted entry time
+    entry_ts_weight: f64,
+}
+
+/// Price of the first fill on the same token in a later block.
+fn next_block_price(by_token: &HashMap<&str, Vec<(u64, f64)>>, f: &Fill) -> Option<f64> {
+    let series = by_token.get(f.token_id.as_str())?;
+    let idx = series.partition_point(|(b, _)| *b <= f.block);
+    series.get(idx).map(|(_, p)| *p)
+}
+
+pub fn compute_risk_stats(fills: &[Fill], end_times: &HashMap<String, i64>, late_window_secs: i64) -> Vec<RiskStats> {
+    let mut by_token: HashMap<&str, Vec<(u64, f64)>> = HashMap::new();
+    let mut by_trader: HashMap<&str, Vec<&Fill>> = HashMap::new();
+    for f in fills {
+        by_token.entry(f.token_id.as_str()).or_default().push((f.block, f.price));
+        by_trader.entry(f.trader.as_str()).or_default().push(f);
+    }
+    for series in by_token.values_mut() {
+        series.sort_by_key(|(b, _)| *b);
+    }
+
+    by_trader.into_iter().map(|(address, mut trades)| {
+        trades.sort_by_key(|f| (f.block, f.log_index));
+
+        let mut positions: HashMap<&str, RiskPosition> = HashMap::new();
+        let mut returns = Vec::new();
+        let (mut cum_pnl, mut peak, mut max_dd) = (0.0f64, 0.0f64, 0.0f64);
+        let (mut hold_weighted, mut hold_shares) = (0.0, 0.0);
+        let (mut late_usd, mut timed_usd) = (0.0, 0.0);
+        let (mut move_sum, mut move_count) = (0.0, 0usize);
+
+        for f in &trades {
+            if let Some(end) = end_times.get(&f.token_id) {
+                timed_usd += f.usd;
+                let to_end = end - f.timestamp;
+                if (0..=late_window_secs).contains(&to_end) {
+                    late_usd += f.usd;
+                }
+            }
+            if let Some(next) = next_block_price(&by_token, f) {
+                move_sum += if f.side_is_buy { next - f.price } else { f.price - next };
+                move_count += 1;
+            }
+
+            let pos = positions.entry(f.token_id.as_str()).or_default();
+            if f.side_is_buy {
+                pos.shares += f.shares;
+                pos.cost += f.usd;
+                pos.entry_ts_weight += f.shares * f.timestamp as f64;
+                continue;
+            }
+
+            let matched = f.shares.min(pos.shares);
+            if matched <= 0.0 { continue; }
+            let avg_cost = pos.cost / pos.shares;
+            let avg_entry = pos.entry_ts_weight / pos.shares;
+            let pnl = matched * (f.price - avg_cost);
+
+            returns.push((f.price - avg_cost) / avg_cost);
+            hold_weighted += matched * (f.timestamp as f64 - avg_entry);
+            hold_shares += matched;
+
+            pos.cost -= avg_cost * matched;
+            pos.entry_ts_weight -= avg_entry * matched;
+            pos.shares -= matched;
+
+            cum_pnl += pnl;
+            peak = peak.max(cum_pnl);
+            max_dd = max_dd.max(peak - cum_pnl);
+        }
+
+        let sharpe = if returns.len() >= 2 {
+            let n = returns.len() as f64;
+            let mean = returns.iter().sum::<f64>() / n;
+            let var = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            if var > 0.0 { mean / var.sqrt() } else { 0.0 }
+        } else {
+            0.0
+        };
+
+        RiskStats {
+            address: address.to_string(),
+            trades: trades.len(),
+            closed_trades: returns.len(),
+            realised_pnl: cum_pnl,
+            sharpe,
+            max_drawdown_usd: max_dd,
+            avg_hold_mins: if hold_shares > 0.0 { hold_weighted / hold_shares / 60.0 } else { 0.0 },
+            late_volume_share: if timed_usd > 0.0 { late_usd / timed_usd } else { 0.0 },
+            next_block_move_cents: if move_count > 0 { move_sum / move_count as f64 * 100.0 } else { 0.0 },
+        }
+    }).collect()
+}
+
+/// Apply `filter` and sort the survivors by Sharpe-like ratio, best first.
+pub fn screen_traders(mut stats: Vec<RiskStats>, filter: &RiskFilter) -> Vec<RiskStats> {
+    stats.retain(|s| filter.passes(s));
+    stats.sort_by(|a, b| b.sharpe.partial_cmp(&a.sharpe).unwrap_or(std::cmp::Ordering::Equal));
+    stats
+}
+
+// ============================================================================
+// Market Selection
+// ============================================================================
+
+/// How a category of markets is picked for `scan-from-markets`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarketSelector {
+    /// Gamma tag slug (e.g. "nba", "tennis")
+    Tag(String),
+    /// Market or event slug pattern, `*` matches any run of characters
+    Slug(String),
+    /// Tokens the category rules (`CATEGORY_RULES`) put in this category
+    Category(String),
+}
+
+impl MarketSelector {
+    pub fn label(&self) -> String {
+        match self {
+            MarketSelector::Tag(t) => format!("tag:{}", t),
+            MarketSelector::Slug(p) => format!("slug:{}", p),
+            MarketSelector::Category(name) => format!("category:{}", name.to_lowercase()),
+        }
+    }
+
+    /// A category defined in the loaded rules
+    pub fn category_from_str(s: &str) -> Result<Self> {
+        let name = s.trim().to_uppercase();
+        if name != OTHER && market_category::rules().names().any(|n| n == name) {
+            return Ok(MarketSelector::Category(name));
+        }
+        let names: Vec<&str> = market_category::rules().names().filter(|n| *n != OTHER).collect();
+        Err(anyhow!("Invalid category '{}'. Must be one of: {}", s, names.join(", ")))
+    }
+
+    /// One selector per category in the loaded rules
+    pub fn all_categories() -> Vec<Self> {
+        market_category::rules().names()
+            .filter(|n| *n != OTHER)
+            .map(|n| MarketSelector::Category(n.to_string()))
+            .collect()
+    }
+}
+
+/// Glob match with `*` wildcards only (case-insensitive).
+pub fn slug_matches(pattern: &str, slug: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let slug = slug.to_lowercase();
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return slug == pattern;
+    }
+
+    let mut rest = slug.as_str();
+    for (i, part) in parts.iter().enumerate() {
+        if part.is_empty() { continue; }
+        if i == 0 {
+            let Some(r) = rest.strip_prefix(part) else { return false };
+            rest = r;
+        } else if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        } else {
+            let Some(pos) = rest.find(part) else { return false };
+            rest = &rest[pos + part.len()..];
+        }
+    }
+    true
+}
+
+fn market_token_ids(market: &serde_json::Value) -> Vec<String> {
+    market["clobTokenIds"].as_str()
+        .and_then(|s| serde_json::from_str(s).ok())
+        .unwrap_or_default()
+}
+
+/// Token ids of active markets matching a Gamma tag or slug pattern.
+async fn fetch_active_tokens(client: &reqwest::Client, selector: &MarketSelector) -> Result<HashSet<String>> {
+    let mut tokens = HashSet::new();
+    for page in 0..GAMMA_MAX_PAGES {
+        let mut url = format!(
+            "{}/events?active=true&closed=false&limit={}&offset={}",
+            GAMMA_API_BASE, GAMMA_PAGE_SIZE, page * GAMMA_PAGE_SIZE
+        );
+        if let MarketSelector::Tag(tag) = selector {
+            url.push_str(&format!("&tag_slug={}", tag));
+        }
+
+        let events: Vec<serde_json::Value> = client.get(&url).send().await?.json().await?;
+        for event in &events {
+            let event_slug = event["slug"].as_str().unwrap_or_default();
+            for market in event["markets"].as_array().into_iter().flatten() {
+                let keep = match selector {
+                    MarketSelector::Slug(p) => {
+                        slug_matches(p, event_slug) || slug_matches(p, market["slug"].as_str().unwrap_or_default())
+                    }
+                    _ => true,
+                };
+                if keep {
+                    tokens.extend(market_token_ids(market));
+                }
+            }
+        }
+        if events.len() < GAMMA_PAGE_SIZE { break; }
+    }
+    Ok(tokens)
+}
+
+/// Resolve a selector to a token set. Category selectors only see tokens that
+/// appear in `fills`, since categories classify tokens rather than list them.
+pub async fn select_tokens(client: &reqwest::Client, selector: &MarketSelector, fills: &[Fill]) -> Result<HashSet<String>> {
+    match selector {
+        MarketSelector::Category(name) => Ok(fills.iter()
+            .map(|f| f.token_id.as_str())
+            .filter(|t| market_category::category_for_token(t).name == *name)
+            .map(str::to_string)
+            .collect()),
+        _ => fetch_active_tokens(client, selector).await,
+    }
+}
+
+// ============================================================================
+// Market-Centric Scan
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MarketTraderStats {
+    pub category: String,
+    pub address: String,
+    pub markets: usize,
+    /// Markets where their first buy landed in the first EARLY_ENTRY_FRACTION of buys
+    pub early_markets: usize,
+    pub realised_pnl: f64,
+    pub win_rate: f64,
+    pub volume_usd: f64,
+}
+
+impl MarketTraderStats {
+    /// Fraction of markets entered early weighted by win rate - rewards traders
+    /// who are both early and right, across many markets.
+    pub fn consistency(&self) -> f64 {
+        if self.markets == 0 { return 0.0; }
+        (self.early_markets as f64 / self.markets as f64) * self.win_rate
+    }
+}
+
+/// Rank wallets trading the given tokens. Only profitable traders active in at
+/// least `min_markets` markets are returned, most consistent first.
+pub fn scan_category(category: &str, fills: &[Fill], tokens: &HashSet<String>, min_markets: usize) -> Vec<MarketTraderStats> {
+    let selected: Vec<Fill> = fills.iter().filter(|f| tokens.contains(&f.token_id)).cloned().collect();
+
+    // Order of first buys per market
+    let mut first_buys: HashMap<&str, Vec<(u64, u64, &str)>> = HashMap::new();
+    let mut seen: HashSet<(&str, &str)> = HashSet::new();
+    let mut sorted: Vec<&Fill> = selected.iter().filter(|f| f.side_is_buy).collect();
+    sorted.sort_by_key(|f| (f.block, f.log_index));
+    for f in sorted {
+        if seen.insert((f.token_id.as_str(), f.trader.as_str())) {
+            first_buys.entry(f.token_id.as_str()).or_default().push((f.block, f.log_index, f.trader.as_str()));
+        }
+    }
+
+    let mut early: HashMap<&str, usize> = HashMap::new();
+    for entrants in first_buys.values() {
+        let cutoff = ((entrants.len() as f64 * EARLY_ENTRY_FRACTION).ceil() as usize).max(1);
+        for (_, _, trader) in entrants.iter().take(cutoff) {
+            *early.entry(trader).or_insert(0) += 1;
+        }
+    }
+
+    let mut stats: Vec<MarketTraderStats> = compute_trader_stats(&selected).into_iter()
+        .filter(|s| s.markets >= min_markets && s.realised_pnl > 0.0)
+        .map(|s| MarketTraderStats {
+            category: category.to_string(),
+            early_markets: early.get(s.address.as_str()).copied().unwrap_or(0),
+            markets: s.markets,
+            realised_pnl: s.realised_pnl,
+            win_rate: s.win_rate,
+            volume_usd: s.volume_usd,
+            address: s.address,
+        })
+        .collect();
+
+    stats.sort_by(|a, b| {
+        b.consistency().partial_cmp(&a.consistency())
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(b.realised_pnl.partial_cmp(&a.realised_pnl).unwrap_or(std::cmp::Ordering::Equal))
+    });
+    stats
+}
+
+// ============================================================================
+// Export
+// ============================================================================
+
+/// Write ranked traders to CSV plus a sibling `.txt` with one address per line in
+/// TARGET_WHALE_ADDRESS format (40 hex chars, no 0x prefix).
+pub fn export_traders<T: Serialize>(path: &Path, rows: &[T], address_of: impl Fn(&T) -> &str) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+
+    let list: Vec<&str> = rows.iter().map(|r| address_of(r).trim_start_matches("0x")).collect();
+    fs::write(path.with_extension("txt"), list.join("\n") + "\n")?;
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(trader: &str, token: &str, buy: bool, shares: f64, price: f64, block: u64) -> Fill {
+        Fill {
+            block,
+            timestamp: block as i64 * 2,
+            tx_hash: format!("0x{:x}", block),
+            log_index: 0,
+            exchange: MONITORED_ADDRESSES[0].to_lowercase(),
+            trader: trader.into(),
+            token_id: token.into(),
+            side_is_buy: buy,
+            shares,
+            usd: shares * price,
+            price,
+        }
+    }
+
+    #[test]
+    fn test_decode_fill_log_buy() {
+        let word = |v: u64| format!("{:064x}", v);
+        let log = serde_json::json!({
+            "address": MONITORED_ADDRESSES[0],
+            "topics": [ORDERS_FILLED_EVENT_SIGNATURE, format!("0x{}", "11".repeat(32)), format!("0x{:0>64}", "ab".repeat(20)), format!("0x{}", "00".repeat(32))],
+            "data": format!("0x{}{}{}{}{}", word(0), word(42), word(5_000_000), word(10_000_000), word(0)),
+            "blockNumber": "0x10",
+            "transactionHash": "0xdead",
+            "logIndex": "0x3",
+        });
+        let f = decode_fill_log(&log).unwrap();
+        assert_eq!(f.trader, format!("0x{}", "ab".repeat(20)));
+        assert_eq!(f.token_id, "42");
+        assert!(f.side_is_buy);
+        assert_eq!(f.shares, 10.0);
+        assert_eq!(f.usd, 5.0);
+        assert_eq!(f.block, 16);
+        assert_eq!(f.log_index, 3);
+    }
+
+    #[test]
+    fn test_trader_stats_realised_pnl() {
+        let fills = vec![
+            fill("0xa", "1", true, 100.0, 0.40, 1),
+            fill("0xa", "1", false, 50.0, 0.60, 2),
+            fill("0xa", "2", true, 10.0, 0.50, 3),
+            fill("0xa", "2", false, 10.0, 0.30, 4),
+            // Sell of inventory bought before the window: volume only
+            fill("0xa", "3", false, 10.0, 0.90, 5),
+        ];
+        let stats = compute_trader_stats(&fills);
+        let s = &stats[0];
+        assert_eq!(s.trades, 5);
+        assert_eq!(s.markets, 3);
+        // (0.60-0.40)*50 + (0.30-0.50)*10 = 10 - 2
+        assert!((s.realised_pnl - 8.0).abs() < 1e-9);
+        assert!((s.roi - 8.0 / 25.0).abs() < 1e-9);
+        assert!((s.win_rate - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rank_traders_min_trades() {
+        let fills = vec![
+            fill("0xa", "1", true, 10.0, 0.5, 1),
+            fill("0xa", "1", false, 10.0, 0.9, 2),
+            fill("0xb", "1", true, 10.0, 0.5, 1),
+            fill("0xb", "1", false, 10.0, 0.6, 2),
+            fill("0xc", "1", true, 10.0, 0.5, 1),
+        ];
+        let ranked = rank_traders(compute_trader_stats(&fills), RankBy::Pnl, 2);
+        let order: Vec<&str> = ranked.iter().map(|s| s.address.as_str()).collect();
+        assert_eq!(order, vec!["0xa", "0xb"]);
+    }
+
+    #[test]
+    fn test_risk_stats_drawdown_hold_and_move() {
+        let fills = vec![
+            fill("0xa", "1", true, 10.0, 0.50, 10),
+            fill("0xa", "1", false, 5.0, 0.70, 40),   // +1.0
+            fill("0xa", "1", false, 5.0, 0.20, 70),   // -1.5
+            fill("0xb", "1", true, 1.0, 0.55, 11),
+        ];
+        let end_times = HashMap::from([("1".to_string(), 150)]);
+        let stats = compute_risk_stats(&fills, &end_times, 60);
+        let a = stats.iter().find(|s| s.address == "0xa").unwrap();
+
+        assert_eq!(a.closed_trades, 2);
+        assert!((a.realised_pnl + 0.5).abs() < 1e-9);
+        assert!((a.max_drawdown_usd - 1.5).abs() < 1e-9);
+        // Entry at t=20, exits at t=80 and t=140 -> 90s average
+        assert!((a.avg_hold_mins - 1.5).abs() < 1e-9);
+        // Only the last fill (t=140) is inside the 60s window before t=150
+        assert!((a.late_volume_share - 1.0 / 9.5).abs() < 1e-9);
+        // Buy @0.50 -> 0.55 next block (+5c), sell @0.70 -> 0.20 next block (+50c)
+        assert!((a.next_block_move_cents - 27.5).abs() < 1e-9);
+
+        let filter = RiskFilter { min_trades: 1, max_drawdown_usd: Some(1.0), ..Default::default() };
+        assert!(screen_traders(stats, &filter).iter().all(|s| s.address != "0xa"));
+    }
+
+    #[test]
+    fn test_slug_matches() {
+        assert!(slug_matches("atp-*", "atp-sinner-alcaraz-2025"));
+        assert!(slug_matches("*-vs-*-2025", "nba-lakers-vs-celtics-2025"));
+        assert!(slug_matches("NBA-*", "nba-finals"));
+        assert!(!slug_matches("atp-*", "wta-swiatek"));
+        assert!(!slug_matches("nba", "nba-finals"));
+    }
+
+    #[test]
+    fn test_scan_category_early_and_profitable() {
+        let fills = vec![
+            fill("0xa", "1", true, 10.0, 0.40, 1),
+            fill("0xb", "1", true, 10.0, 0.50, 2),
+            fill("0xc", "1", true, 10.0, 0.55, 3),
+            fill("0xd", "1", true, 10.0, 0.60, 4),
+            fill("0xa", "1", false, 10.0, 0.70, 5),
+            fill("0xb", "1", false, 10.0, 0.65, 6),
+            fill("0xa", "2", true, 10.0, 0.30, 7),
+            fill("0xa", "2", false, 10.0, 0.35, 8),
+            // Outside the category
+            fill("0xd", "9", true, 10.0, 0.10, 9),
+            fill("0xd", "9", false, 10.0, 0.90, 10),
+        ];
+        let tokens: HashSet<String> = ["1", "2"].iter().map(|s| s.to_string()).collect();
+        let ranked = scan_category("tag:test", &fills, &tokens, 1);
+
+        let order: Vec<&str> = ranked.iter().map(|s| s.address.as_str()).collect();
+        assert_eq!(order, vec!["0xa", "0xb"]);
+        assert_eq!(ranked[0].early_markets, 2);
+        assert_eq!(ranked[1].early_markets, 0);
+    }
+}
@@ -0,0 +1,204 @@
+//! Gnosis Safe transaction execution
+//!
+//! Polymarket funders are usually 1-of-1 Gnosis Safes owned by the signer EOA.
+//! Anything that must happen *as the Safe* (approvals, redemptions) has to go
+//! through `execTransaction`: the owner signs the Safe transaction hash and the
+//! EOA submits it and pays gas. Several calls can be batched into one Safe
+//! transaction through MultiSendCallOnly.
+
+use anyhow::{Result, anyhow};
+use alloy::primitives::{Address, Bytes, B256, U256};
+use alloy::providers::ProviderBuilder;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::signers::SignerSync;
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use std::str::FromStr;
+
+use crate::approvals::{retry_rpc, wait_for_receipt};
+
+/// Safe v1.3.0 MultiSendCallOnly (same address on every chain)
+pub const MULTI_SEND_CALL_ONLY: &str = "0x40A2aCCbd92BCA938b02010E17A5b8929b49130D";
+
+const OPERATION_CALL: u8 = 0;
+const OPERATION_DELEGATE_CALL: u8 = 1;
+
+sol! {
+    #[sol(rpc)]
+    interface IGnosisSafe {
+        function nonce() external view returns (uint256);
+        function getThreshold() external view returns (uint256);
+        function isOwner(address owner) external view returns (bool);
+        function getTransactionHash(
+            address to,
+            uint256 value,
+            bytes data,
+            uint8 operation,
+            uint256 safeTxGas,
+            uint256 baseGas,
+            uint256 gasPrice,
+            address gasToken,
+            address refundReceiver,
+            uint256 _nonce
+        ) external view returns (bytes32);
+        function execTransaction(
+            address to,
+            uint256 value,
+            bytes data,
+            uint8 operation,
+            uint256 safeTxGas,
+            uint256 baseGas,
+            uint256 gasPrice,
+            address gasToken,
+            address refundReceiver,
+            bytes signatures
+        ) external payable returns (bool success);
+    }
+
+    interface IMultiSend {
+        function multiSend(bytes transactions) external payable;
+    }
+}
+
+/// One call the Safe should make.
+#[derive(Debug, Clone)]
+pub struct SafeCall {
+    pub to: Address,
+    pub value: U256,
+    pub data: Bytes,
+    /// Human readable label for progress output
+    pub description: String,
+}
+
+impl SafeCall {
+    pub fn new(to: Address, data: impl Into<Bytes>, description: impl Into<String>) -> Self {
+        Self { to, value: U256::ZERO, data: data.into(), description: description.into() }
+    }
+}
+
+/// Pack calls in the MultiSend format:
+/// `operation (1) | to (20) | value (32) | data length (32) | data`
+pub fn encode_multisend(calls: &[SafeCall]) -> Bytes {
+    let mut packed = Vec::with_capacity(calls.iter().map(|c| 85 + c.data.len()).sum());
+    for c in calls {
+        packed.push(OPERATION_CALL);
+        packed.extend_from_slice(c.to.as_slice());
+        packed.extend_from_slice(&c.value.to_be_bytes::<32>());
+        packed.extend_from_slice(&U256::from(c.data.len()).to_be_bytes::<32>());
+        packed.extend_from_slice(&c.data);
+    }
+    Bytes::from(packed)
+}
+
+/// Executes transactions from a Safe owned by `signer`.
+pub struct SafeExecutor {
+    safe: Address,
+    signer: PrivateKeySigner,
+    rpc_url: String,
+}
+
+impl SafeExecutor {
+    pub fn new(safe: Address, signer: PrivateKeySigner, rpc_url: &str) -> Self {
+        Self { safe, signer, rpc_url: rpc_url.to_string() }
+    }
+
+    pub fn safe_address(&self) -> Address {
+        self.safe
+    }
+
+    /// Fail early unless the signer alone can execute Safe transactions.
+    pub async fn check_owner(&self) -> Result<()> {
+        let provider = ProviderBuilder::new().connect_http(self.rpc_url.parse()?);
+        let safe = IGnosisSafe::new(self.safe, provider);
+
+        let is_owner = safe.isOwner(self.signer.address()).call().await
+            .map_err(|e| anyhow!("{} does not look like a Gnosis Safe: {}", self.safe, e))?;
+        if !is_owner {
+            return Err(anyhow!(
+                "Signer {} is not an owner of Safe {}. Use the private key of a Safe owner.",
+                self.signer.address(), self.safe
+            ));
+        }
+
+        let threshold = safe.getThreshold().call().await?;
+        if threshold > U256::from(1u64) {
+            return Err(anyhow!(
+                "Safe {} requires {} signatures; only 1-of-N Safes can be executed automatically. \
+                Approve through https://app.safe.global/ instead.",
+                self.safe, threshold
+            ));
+        }
+        Ok(())
+    }
+
+    /// Execute `calls` as the Safe. With `batch` and more than one call, all calls
+    /// go out in a single MultiSend transaction; otherwise one Safe transaction per
+    /// call. Returns the transaction hashes in submission order.
+    pub async fn execute(&self, calls: &[SafeCall], batch: bool) -> Result<Vec<B256>> {
+        if batch && calls.len() > 1 {
+            let multisend = Address::from_str(MULTI_SEND_CALL_ONLY)?;
+            let data = IMultiSend::multiSendCall { transactions: encode_multisend(calls) }.abi_encode();
+            let hash = self.exec_transaction(multisend, U256::ZERO, data.into(), OPERATION_DELEGATE_CALL).await?;
+            return Ok(vec![hash]);
+        }
+
+        let mut hashes = Vec::with_capacity(calls.len());
+        for c in calls {
+            hashes.push(self.exec_transaction(c.to, c.value, c.data.clone(), OPERATION_CALL).await?);
+        }
+        Ok(hashes)
+    }
+
+    async fn exec_transaction(&self, to: Address, value: U256, data: Bytes, operation: u8) -> Result<B256> {
+        let provider = ProviderBuilder::new()
+            .wallet(self.signer.clone())
+            .connect_http(self.rpc_url.parse()?);
+        let safe = IGnosisSafe::new(self.safe, provider);
+
+        // No gas refund: safeTxGas/baseGas/gasPrice = 0, gas paid by the submitting EOA
+        let nonce = retry_rpc("Safe nonce", || async { Ok(safe.nonce().call().await?) }).await?;
+        let safe_tx_hash = retry_rpc("Safe transaction hash", || async {
+            Ok(safe
+                .getTransactionHash(to, value, data.clone(), operation, U256::ZERO, U256::ZERO, U256::ZERO, Address::ZERO, Address::ZERO, nonce)
+                .call()
+                .await?)
+        }).await?;
+
+        // Plain ECDSA signature over the Safe tx hash: r | s | v (27/28)
+        let signature = self.signer.sign_hash_sync(&safe_tx_hash)?;
+        let signatures = Bytes::from(signature.as_bytes().to_vec());
+
+        let pending = safe
+            .execTransaction(to, value, data, operation, U256::ZERO, U256::ZERO, U256::ZERO, Address::ZERO, Address::ZERO, signatures)
+            .send()
+            .await?;
+        wait_for_receipt(&self.rpc_url, *pending.tx_hash(), "Safe transaction").await
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_multisend_layout() {
+        let to = Address::from_str("0x4d97dcd97ec945f40cf65f87097ace5ea0476045").unwrap();
+        let calls = vec![
+            SafeCall::new(to, vec![0xaa, 0xbb], "first"),
+            SafeCall::new(to, Vec::new(), "second"),
+        ];
+        let packed = encode_multisend(&calls);
+
+        assert_eq!(packed.len(), (85 + 2) + 85);
+        assert_eq!(packed[0], OPERATION_CALL);
+        assert_eq!(&packed[1..21], to.as_slice());
+        assert!(packed[21..53].iter().all(|b| *b == 0));
+        assert_eq!(packed[84], 2);
+        assert_eq!(&packed[85..87], &[0xaa, 0xbb]);
+        assert_eq!(packed[87], OPERATION_CALL);
+    }
+}
@@ -1,13 +1,17 @@
 /// Settings and configuration management
 /// Handles environment variable loading and validation
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow};
+use std::collections::HashMap;
 use std::env;
 use std::path::Path;
 use std::time::Duration;
+use crate::market_category::{self, CategoryRules};
+use crate::mempool;
+use crate::preflight;
+use crate::providers::{self, ProviderConfig};
 use crate::risk_guard;
-use crate::tennis_markets;
-use crate::soccer_markets;
+use crate::slippage::ThinBookAction;
 
 // ============================================================================
 // Blockchain Constants
@@ -171,12 +175,30 @@ pub fn get_tier_params(whale_shares: f64, side_is_buy: bool, token_id: &str) ->
         (PRICE_BUFFER, "FAK", 1.0)  // Small buys use FAK (Fill and Kill)
     };
 
-    // Apply sport-specific price adjustments
-    let tennis_buffer = tennis_markets::get_tennis_token_buffer(token_id);
-    let soccer_buffer = soccer_markets::get_soccer_token_buffer(token_id);
-    let total_buffer = base_buffer + tennis_buffer + soccer_buffer;
+    // Category price and order type adjustments (see market_category);
+    // the category size multiplier is applied in `sizing::copy_shares`
+    let category = market_category::category_for_token(token_id);
+    (base_buffer + category.buffer, category.order_type.unwrap_or(order_action), size_multiplier)
+}
 
-    (total_buffer, order_action, size_multiplier)
+/// Tier params for a fill, taking the whale's role into account.
+/// Returns None when passive fills are ignored.
+#[inline]
+pub fn get_fill_tier_params(
+    whale_shares: f64,
+    side_is_buy: bool,
+    whale_is_maker: bool,
+    passive_fills: PassiveFillMode,
+    token_id: &str,
+) -> Option<(f64, &'static str, f64)> {
+    if !whale_is_maker {
+        return Some(get_tier_params(whale_shares, side_is_buy, token_id));
+    }
+    match passive_fills {
+        PassiveFillMode::Copy => Some(get_tier_params(whale_shares, side_is_buy, token_id)),
+        PassiveFillMode::LimitOnly => Some((0.0, "GTD", 1.0)),
+        PassiveFillMode::Ignore => None,
+    }
 }
 
 // ============================================================================
@@ -201,14 +223,63 @@ impl CopyStrategy {
     }
 }
 
+/// How fills where the whale was the resting maker are copied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassiveFillMode {
+    /// Same tiers as aggressive fills
+    Copy,
+    /// Limit order at the whale's price, never crossing the spread
+    LimitOnly,
+    Ignore,
+}
+
+impl PassiveFillMode {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_uppercase().as_str() {
+            "COPY" => Ok(PassiveFillMode::Copy),
+            "LIMIT_ONLY" => Ok(PassiveFillMode::LimitOnly),
+            "IGNORE" => Ok(PassiveFillMode::Ignore),
+            _ => anyhow::bail!("Invalid PASSIVE_FILLS. Must be COPY, LIMIT_ONLY or IGNORE"),
+        }
+    }
+}
+
+/// Where whale trades are detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventSource {
+    /// Confirmed `OrdersFilled` logs
+    Logs,
+    /// Pending transactions, reconciled against the confirmed logs
+    Mempool,
+}
+
+impl EventSource {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_uppercase().as_str() {
+            "LOGS" => Ok(EventSource::Logs),
+            "MEMPOOL" => Ok(EventSource::Mempool),
+            _ => anyhow::bail!("Invalid EVENT_SOURCE. Must be LOGS or MEMPOOL"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config {
     // Credentials
     pub private_key: String,
     pub funder_address: String,
     
-    // WebSocket
-    pub wss_url: String,
+    // WebSocket providers with a wss endpoint, preferred first
+    pub ws_providers: Vec<ProviderConfig>,
+    // Simultaneous log subscriptions (deduplicated), capped at the provider count
+    pub ws_subscriptions: usize,
+    // Late (backfilled) events older than this are logged but not copied
+    pub backfill_max_age_secs: u64,
+    // Sell back / buy back copied trades whose source log was reorged out
+    pub reorg_unwind: bool,
+    pub event_source: EventSource,
+    // Fills of one whale trade (same tx and token) are merged for this long before copying
+    pub aggregation_window_ms: u64,
     
     // Trading flags
     pub enable_trading: bool,
@@ -222,12 +293,27 @@ pub struct Config {
     pub adaptive_max_percent: f64,  // For ADAPTIVE strategy
     pub adaptive_threshold_usd: f64,  // For ADAPTIVE strategy
     pub tiered_multipliers: Option<String>,  // Optional tiered multipliers (e.g., "1-10:2.0,10-100:1.0,100-500:0.5,500+:0.2")
+    pub passive_fills: PassiveFillMode,  // Fills where the whale was the resting maker
+    pub passive_size_multiplier: f64,  // Applied to passive fills on top of the strategy size
     
     // Risk limits
     pub max_order_size_usd: f64,
     pub min_order_size_usd: f64,
     pub max_position_size_usd: Option<f64>,  // Optional position cap
     pub max_daily_volume_usd: Option<f64>,  // Optional daily volume cap
+    pub max_open_exposure_usd: Option<f64>,  // Marked value of all open positions
+    pub max_event_exposure_usd: Option<f64>,  // Marked value per event (all outcomes)
+    pub max_open_positions: Option<usize>,
+    pub max_daily_loss_usd: Option<f64>,  // Realized + marked-to-market loss since UTC midnight
+    
+    // Kill switch (halt persists until `main clear-halt`)
+    pub kill_daily_loss_usd: Option<f64>,
+    pub kill_min_usdc_balance: Option<f64>,
+    
+    // Slippage guard (taking orders only)
+    pub slippage_max_ticks: Option<u32>,
+    pub slippage_max_pct: Option<f64>,
+    pub slippage_thin_book: Option<ThinBookAction>,
     
     // Circuit breaker
     pub cb_large_trade_shares: f64,
@@ -235,6 +321,58 @@ pub struct Config {
     pub cb_sequence_window_secs: u64,
     pub cb_min_depth_usd: f64,
     pub cb_trip_duration_secs: u64,
+    /// Per-category overrides from `CB_<CATEGORY>_*`; missing fields use the values above
+    pub cb_profiles: HashMap<String, BreakerOverride>,
+}
+
+/// Circuit breaker settings overridden for one market category
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BreakerOverride {
+    pub large_trade_shares: Option<f64>,
+    pub consecutive_trigger: Option<u8>,
+    pub sequence_window_secs: Option<u64>,
+    pub min_depth_usd: Option<f64>,
+    pub trip_duration_secs: Option<u64>,
+}
+
+impl BreakerOverride {
+    /// Read `CB_<CATEGORY>_LARGE_TRADE_SHARES` etc. from the environment
+    pub fn from_env(category: &str) -> Result<Self> {
+        Self::from_lookup(category, |k| env::var(k).ok())
+    }
+
+    /// Read `CB_<CATEGORY>_LARGE_TRADE_SHARES` etc. through `get`. A value that
+    /// is set but invalid is an error, like the other optional limits.
+    fn from_lookup(category: &str, get: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let key = |name: &str| format!("CB_{}_{}", category, name);
+        let field = |name: &str| (key(name), get(&key(name)));
+        let (k, raw) = field("LARGE_TRADE_SHARES");
+        let large_trade_shares = parse_opt(&k, raw, "a positive number of shares", |v: &f64| *v > 0.0)?;
+        let (k, raw) = field("CONSECUTIVE_TRIGGER");
+        let consecutive_trigger = parse_opt(&k, raw, "a whole number from 1 to 255", |v: &u8| *v > 0)?;
+        let (k, raw) = field("SEQUENCE_WINDOW_SECS");
+        let sequence_window_secs = parse_opt(&k, raw, "a positive number of seconds", |v: &u64| *v > 0)?;
+        let (k, raw) = field("MIN_DEPTH_USD");
+        let min_depth_usd = parse_opt(&k, raw, "a non-negative USD amount", |v: &f64| *v >= 0.0)?;
+        let (k, raw) = field("TRIP_DURATION_SECS");
+        let trip_duration_secs = parse_opt(&k, raw, "a positive number of seconds", |v: &u64| *v > 0)?;
+        Ok(Self { large_trade_shares, consecutive_trigger, sequence_window_secs, min_depth_usd, trip_duration_secs })
+    }
+}
+
+/// Categories with at least one `CB_<CATEGORY>_*` override
+fn breaker_profiles<'a>(
+    categories: impl Iterator<Item = &'a str>,
+    get: impl Fn(&str) -> Option<String>,
+) -> Result<HashMap<String, BreakerOverride>> {
+    let mut profiles = HashMap::new();
+    for c in categories {
+        let o = BreakerOverride::from_lookup(c, &get)?;
+        if o != BreakerOverride::default() {
+            profiles.insert(c.to_string(), o);
+        }
+    }
+    Ok(profiles)
 }
 
 impl Config {
@@ -264,115 +402,36 @@ impl Config {
             .context("PRIVATE_KEY env var is required. Add it to your .env file.\n\
                      Format: 64-character hex string (no 0x prefix)\n\
                      Example: 0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef")?;
-        
-        // Validate private key format
-        let key_clean = private_key.trim().strip_prefix("0x").unwrap_or(private_key.trim());
-        if key_clean.len() != 64 {
-            anyhow::bail!(
-                "PRIVATE_KEY must be exactly 64 hex characters (found {}).\n\
-                Remove any '0x' prefix. Current value starts with: {}",
-                key_clean.len(),
-                if key_clean.len() > 10 { format!("{}...", &key_clean[..10]) } else { key_clean.to_string() }
-            );
-        }
-        if !key_clean.chars().all(|c| c.is_ascii_hexdigit()) {
-            anyhow::bail!("PRIVATE_KEY contains invalid characters. Must be hexadecimal (0-9, a-f, A-F).");
-        }
+        preflight::validate_private_key(&private_key)?;
         
         let funder_address = env::var("FUNDER_ADDRESS")
             .context("FUNDER_ADDRESS env var is required. Add it to your .env file.\n\
                      Format: 40-character hex address (can include 0x prefix)\n\
                      This should match the wallet from your PRIVATE_KEY")?;
+        preflight::validate_address("FUNDER_ADDRESS", &funder_address)?;
         
-        // Validate funder address format
-        let addr_clean = funder_address.trim().strip_prefix("0x").unwrap_or(funder_address.trim());
-        if addr_clean.len() != 40 {
-            anyhow::bail!(
-                "FUNDER_ADDRESS must be exactly 40 hex characters (found {}).\n\
-                Current value: {}",
-                addr_clean.len(),
-                if addr_clean.len() > 20 { format!("{}...", &addr_clean[..20]) } else { addr_clean.to_string() }
-            );
-        }
-        if !addr_clean.chars().all(|c| c.is_ascii_hexdigit()) {
-            anyhow::bail!("FUNDER_ADDRESS contains invalid characters. Must be hexadecimal (0-9, a-f, A-F).");
+        // WebSocket providers from RPC_PROVIDERS and the legacy API keys
+        let ws_providers = providers::ws_providers_from_env()?;
+        let ws_subscriptions = env_parse("WS_SUBSCRIPTIONS", 2usize).clamp(1, ws_providers.len());
+
+        let event_source = EventSource::from_str(&env::var("EVENT_SOURCE").unwrap_or_else(|_| "LOGS".to_string()))?;
+        if event_source == EventSource::Mempool && mempool::mempool_providers(&ws_providers).is_empty() {
+            anyhow::bail!("EVENT_SOURCE=MEMPOOL needs an Alchemy WebSocket provider (alchemy_pendingTransactions)");
         }
         
-        // WebSocket URL from either provider
-        let wss_url = if let Ok(key) = env::var("ALCHEMY_API_KEY") {
-            let key = key.trim();
-            if key.is_empty() || key == "your_alchemy_api_key_here" {
-                anyhow::bail!(
-                    "ALCHEMY_API_KEY is set but has placeholder value.\n\
-                    Get your API key from https://www.alchemy.com/ (free tier available)\n\
-                    Then add it to your .env file"
-                );
-            }
-            format!("wss://polygon-mainnet.g.alchemy.com/v2/{}", key)
-        } else if let Ok(key) = env::var("CHAINSTACK_API_KEY") {
-            let key = key.trim();
-            if key.is_empty() || key == "your_chainstack_api_key_here" {
-                anyhow::bail!(
-                    "CHAINSTACK_API_KEY is set but has placeholder value.\n\
-                    Get your API key from https://chainstack.com/ (free tier available)\n\
-                    Or use ALCHEMY_API_KEY instead (recommended for beginners)"
-                );
-            }
-            format!("wss://polygon-mainnet.core.chainstack.com/{}", key)
-        } else {
-            anyhow::bail!(
-                "WebSocket API key required!\n\
-                \n\
-                Set either ALCHEMY_API_KEY or CHAINSTACK_API_KEY in your .env file.\n\
-                \n\
-                Recommended (beginners): ALCHEMY_API_KEY\n\
-                1. Sign up at https://www.alchemy.com/\n\
-                2. Create app (Polygon Mainnet)\n\
-                3. Copy API key to .env file\n\
-                \n\
-                Alternative: CHAINSTACK_API_KEY\n\
-                1. Sign up at https://chainstack.com/\n\
-                2. Create Polygon node\n\
-                3. Copy API key to .env file\n\
-                \n\
-                Run 'cargo run --release --bin check_config' to validate your setup"
-            );
-        };
-        
         // Validate TARGET_WHALE_ADDRESS (used by TARGET_TOPIC_HEX lazy static)
         let target_whale = env::var("TARGET_WHALE_ADDRESS")
             .context("TARGET_WHALE_ADDRESS env var is required. Add it to your .env file.\n\
                      Format: 40-character hex address (no 0x prefix)\n\
                      This is the whale address you want to copy trades from.\n\
                      Find whale addresses on Polymarket leaderboards")?;
-        
-        let whale_clean = target_whale.trim().strip_prefix("0x").unwrap_or(target_whale.trim());
-        if whale_clean.is_empty() || whale_clean == "target_whale_address_here" {
-            anyhow::bail!(
-                "TARGET_WHALE_ADDRESS is set but has placeholder value.\n\
-                Replace 'target_whale_address_here' with the actual whale address you want to copy.\n\
-                Find whale addresses on Polymarket leaderboards or from successful traders."
-            );
-        }
-        if whale_clean.len() != 40 {
-            anyhow::bail!(
-                "TARGET_WHALE_ADDRESS must be exactly 40 hex characters (found {}).\n\
-                Remove '0x' prefix if present. Current value: {}",
-                whale_clean.len(),
-                if whale_clean.len() > 20 { format!("{}...", &whale_clean[..20]) } else { whale_clean.to_string() }
-            );
-        }
-        if !whale_clean.chars().all(|c| c.is_ascii_hexdigit()) {
-            anyhow::bail!("TARGET_WHALE_ADDRESS contains invalid characters. Must be hexadecimal (0-9, a-f, A-F).");
+        preflight::validate_address("TARGET_WHALE_ADDRESS", &target_whale)?;
+        if event_source == EventSource::Mempool {
+            mempool::check_whale_filter()?;
         }
         
-        let enable_trading = env::var("ENABLE_TRADING")
-            .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
-            .unwrap_or(true);
-        
-        let mock_trading = env::var("MOCK_TRADING")
-            .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
-            .unwrap_or(false);
+        let enable_trading = preflight::env_flag("ENABLE_TRADING", true);
+        let mock_trading = preflight::env_flag("MOCK_TRADING", false);
         
         // Trading strategy configuration
         let copy_strategy_str = env::var("COPY_STRATEGY")
@@ -390,6 +449,8 @@ impl Config {
         
         // Tiered multipliers (optional)
         let tiered_multipliers = env::var("TIERED_MULTIPLIERS").ok();
+
+        let passive_fills = PassiveFillMode::from_str(&env::var("PASSIVE_FILLS").unwrap_or_else(|_| "COPY".to_string()))?;
         
         // Risk limits
         let max_order_size_usd = env_parse("MAX_ORDER_SIZE_USD", 100.0);
@@ -401,10 +462,27 @@ impl Config {
             .ok()
             .and_then(|v| v.parse().ok());
         
+        // A broken rules file is a config error rather than a silent fallback
+        CategoryRules::load_configured()?;
+
+        let slippage_thin_book = env::var("SLIPPAGE_THIN_BOOK").ok()
+            .map(|v| ThinBookAction::from_str(&v))
+            .transpose()?;
+
+        let (limits, mut errors) = OptionalLimits::parse(|k| env::var(k).ok());
+        if !errors.is_empty() {
+            return Err(errors.swap_remove(0).1);
+        }
+        
         Ok(Self {
             private_key,
             funder_address,
-            wss_url,
+            ws_providers,
+            ws_subscriptions,
+            backfill_max_age_secs: env_parse("BACKFILL_MAX_AGE_SECS", 30),
+            reorg_unwind: preflight::env_flag("REORG_UNWIND", false),
+            event_source,
+            aggregation_window_ms: env_parse("AGGREGATION_WINDOW_MS", 200),
             enable_trading,
             mock_trading,
             copy_strategy,
@@ -414,15 +492,27 @@ impl Config {
             adaptive_max_percent,
             adaptive_threshold_usd,
             tiered_multipliers,
+            passive_fills,
+            passive_size_multiplier: env_parse("PASSIVE_SIZE_MULTIPLIER", 1.0),
             max_order_size_usd,
             min_order_size_usd,
             max_position_size_usd,
             max_daily_volume_usd,
+            max_open_exposure_usd: limits.max_open_exposure_usd,
+            max_event_exposure_usd: limits.max_event_exposure_usd,
+            max_open_positions: limits.max_open_positions,
+            max_daily_loss_usd: limits.max_daily_loss_usd,
+            kill_daily_loss_usd: limits.kill_daily_loss_usd,
+            kill_min_usdc_balance: limits.kill_min_usdc_balance,
+            slippage_max_ticks: limits.slippage_max_ticks,
+            slippage_max_pct: limits.slippage_max_pct,
+            slippage_thin_book,
             cb_large_trade_shares: env_parse("CB_LARGE_TRADE_SHARES", 1500.0),
             cb_consecutive_trigger: env_parse("CB_CONSECUTIVE_TRIGGER", 2u8),
             cb_sequence_window_secs: env_parse("CB_SEQUENCE_WINDOW_SECS", 30),
             cb_min_depth_usd: env_parse("CB_MIN_DEPTH_USD", 200.0),
             cb_trip_duration_secs: env_parse("CB_TRIP_DURATION_SECS", 120),
+            cb_profiles: breaker_profiles(market_category::rules().names(), |k| env::var(k).ok())?,
         })
     }
     
@@ -436,6 +526,18 @@ impl Config {
             trip_duration: Duration::from_secs(self.cb_trip_duration_secs),
         }
     }
+
+    /// RiskGuardConfig for one market category, falling back to the global CB_* values
+    pub fn risk_guard_config_for(&self, category: &str) -> risk_guard::RiskGuardConfig {
+        let o = self.cb_profiles.get(category).cloned().unwrap_or_default();
+        risk_guard::RiskGuardConfig {
+            large_trade_shares: o.large_trade_shares.unwrap_or(self.cb_large_trade_shares),
+            consecutive_trigger: o.consecutive_trigger.unwrap_or(self.cb_consecutive_trigger),
+            sequence_window: Duration::from_secs(o.sequence_window_secs.unwrap_or(self.cb_sequence_window_secs)),
+            min_depth_beyond_usd: o.min_depth_usd.unwrap_or(self.cb_min_depth_usd),
+            trip_duration: Duration::from_secs(o.trip_duration_secs.unwrap_or(self.cb_trip_duration_secs)),
+        }
+    }
 }
 
 /// Parse env var with default fallback
@@ -446,6 +548,72 @@ fn env_parse<T: std::str::FromStr>(key: &str, default: T) -> T {
         .unwrap_or(default)
 }
 
+/// Parse an optional value; a value that is set but unparsable or fails
+/// `valid` is an error rather than silently unset
+fn parse_opt<T: std::str::FromStr>(key: &str, raw: Option<String>, expected: &str, valid: impl Fn(&T) -> bool) -> Result<Option<T>> {
+    let Some(raw) = raw else { return Ok(None) };
+    match raw.trim().parse::<T>() {
+        Ok(v) if valid(&v) => Ok(Some(v)),
+        _ => Err(anyhow!("Invalid {}: {} (expected {})", key, raw, expected)),
+    }
+}
+
+// ============================================================================
+// Optional Limits
+// ============================================================================
+
+/// Portfolio, kill switch and slippage limits. Unset disables a limit; a set
+/// but invalid value is an error rather than a silent default.
+#[derive(Debug, Default)]
+struct OptionalLimits {
+    max_open_exposure_usd: Option<f64>,
+    max_event_exposure_usd: Option<f64>,
+    max_open_positions: Option<usize>,
+    max_daily_loss_usd: Option<f64>,
+    kill_daily_loss_usd: Option<f64>,
+    kill_min_usdc_balance: Option<f64>,
+    slippage_max_ticks: Option<u32>,
+    slippage_max_pct: Option<f64>,
+}
+
+/// Collects every invalid key instead of stopping at the first
+struct OptParser<F> {
+    get: F,
+    errors: Vec<(&'static str, anyhow::Error)>,
+}
+
+impl<F: Fn(&str) -> Option<String>> OptParser<F> {
+    fn opt<T: std::str::FromStr>(&mut self, key: &'static str, expected: &str, valid: impl Fn(&T) -> bool) -> Option<T> {
+        parse_opt(key, (self.get)(key), expected, valid).unwrap_or_else(|e| {
+            self.errors.push((key, e));
+            None
+        })
+    }
+}
+
+impl OptionalLimits {
+    fn parse(get: impl Fn(&str) -> Option<String>) -> (Self, Vec<(&'static str, anyhow::Error)>) {
+        let mut p = OptParser { get, errors: Vec::new() };
+        let limits = Self {
+            max_open_exposure_usd: p.opt("MAX_OPEN_EXPOSURE_USD", "a positive USD amount", |v: &f64| *v > 0.0),
+            max_event_exposure_usd: p.opt("MAX_EVENT_EXPOSURE_USD", "a positive USD amount", |v: &f64| *v > 0.0),
+            max_open_positions: p.opt("MAX_OPEN_POSITIONS", "a positive whole number", |v: &usize| *v > 0),
+            max_daily_loss_usd: p.opt("MAX_DAILY_LOSS_USD", "a positive USD amount", |v: &f64| *v > 0.0),
+            kill_daily_loss_usd: p.opt("KILL_DAILY_LOSS_USD", "a positive USD amount", |v: &f64| *v > 0.0),
+            kill_min_usdc_balance: p.opt("KILL_MIN_USDC_BALANCE", "a non-negative USD amount", |v: &f64| *v >= 0.0),
+            slippage_max_ticks: p.opt("SLIPPAGE_MAX_TICKS", "a whole number of ticks", |_: &u32| true),
+            slippage_max_pct: p.opt("SLIPPAGE_MAX_PCT", "a positive percent", |v: &f64| *v > 0.0),
+        };
+        (limits, p.errors)
+    }
+}
+
+/// Optional limits in the environment that `Config::from_env` rejects, with
+/// their keys. Preflight reports all of them.
+pub fn optional_limit_errors() -> Vec<(&'static str, anyhow::Error)> {
+    OptionalLimits::parse(|k| env::var(k).ok()).1
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -500,20 +668,17 @@ mod tests {
 
     // -------------------------------------------------------------------------
     // Test 2b: ATP token at small size (100 shares)
-    // ATP tokens should ALWAYS get +0.01 buffer, even for small trades
-    // Base buffer 0.00 + ATP buffer 0.01 = 0.01 total
+    // Tennis markets ALWAYS get +0.01 buffer from their category, even for small trades
+    // Base buffer 0.00 + category buffer 0.01 = 0.01 total
     // -------------------------------------------------------------------------
     #[test]
     fn test_small_size_atp_gets_buffer() {
-        // Verify tennis market buffer logic:
-        // Even for small trades, tennis tokens get additional 0.01 buffer
-        //
-        // Expected: base_buffer (0.00 for <500) + tennis_buffer (0.01) = 0.01 total
-        let tennis_buffer = tennis_markets::get_tennis_token_buffer("fake_non_tennis_token");
-        assert_eq!(tennis_buffer, 0.0, "Non-tennis token should have 0 buffer");
-
-        // If token IS in tennis cache, it adds 0.01 buffer
-        // Example: 100-share tennis trade = 0.00 (base) + 0.01 (tennis) = 0.01 total
+        let rules = CategoryRules::builtin();
+        let atp = market_category::MarketFacts { slug: Some("atp-sinner-alcaraz-2026-10-18"), ..Default::default() };
+        assert_eq!(rules.classify(&atp).buffer, 0.01, "Tennis category should add 0.01 buffer");
+
+        let other = market_category::MarketFacts { slug: Some("fed-decision-in-december"), ..Default::default() };
+        assert_eq!(rules.classify(&other).buffer, 0.0, "Unmatched markets should have 0 buffer");
     }
 
     // -------------------------------------------------------------------------
@@ -624,6 +789,26 @@ mod tests {
         assert_eq!(mult, 1.0);
     }
 
+    // -------------------------------------------------------------------------
+    // Test: Passive (whale-as-maker) fills follow PASSIVE_FILLS
+    // -------------------------------------------------------------------------
+    #[test]
+    fn test_passive_fill_modes() {
+        let token_id = "fake_token";
+
+        // Aggressive fills ignore the mode
+        let taker = get_fill_tier_params(4000.0, true, false, PassiveFillMode::Ignore, token_id);
+        assert_eq!(taker, Some(get_tier_params(4000.0, true, token_id)));
+
+        let copy = get_fill_tier_params(4000.0, true, true, PassiveFillMode::Copy, token_id);
+        assert_eq!(copy, Some(get_tier_params(4000.0, true, token_id)));
+        assert_eq!(get_fill_tier_params(4000.0, true, true, PassiveFillMode::LimitOnly, token_id), Some((0.0, "GTD", 1.0)));
+        assert_eq!(get_fill_tier_params(4000.0, true, true, PassiveFillMode::Ignore, token_id), None);
+
+        assert_eq!(PassiveFillMode::from_str("limit_only").unwrap(), PassiveFillMode::LimitOnly);
+        assert!(PassiveFillMode::from_str("maybe").is_err());
+    }
+
     // -------------------------------------------------------------------------
     // Test: Resubmit params for different sizes
     // Current config:
@@ -730,4 +915,45 @@ mod tests {
         assert_eq!(buf, PRICE_BUFFER);
         assert_eq!(mult, 1.0);
     }
+
+    #[test]
+    fn test_breaker_profiles_from_env() {
+        let vars: HashMap<&str, &str> = [
+            ("CB_CRYPTO_LARGE_TRADE_SHARES", "5000"),
+            ("CB_CRYPTO_TRIP_DURATION_SECS", "30"),
+        ].into_iter().collect();
+        let rules = CategoryRules::builtin();
+        let profiles = breaker_profiles(rules.names(), |k| vars.get(k).map(|v| v.to_string())).unwrap();
+
+        assert_eq!(profiles.len(), 1);
+        let crypto = &profiles["CRYPTO"];
+        assert_eq!(crypto.large_trade_shares, Some(5000.0));
+        assert_eq!(crypto.trip_duration_secs, Some(30));
+        assert_eq!(crypto.consecutive_trigger, None);
+
+        // A bad override is an error, not a silent fallback to the global value
+        let bad: HashMap<&str, &str> = [("CB_TENNIS_MIN_DEPTH_USD", "not-a-number")].into();
+        let err = breaker_profiles(rules.names(), |k| bad.get(k).map(|v| v.to_string())).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid CB_TENNIS_MIN_DEPTH_USD: not-a-number (expected a non-negative USD amount)");
+        let zero: HashMap<&str, &str> = [("CB_CRYPTO_CONSECUTIVE_TRIGGER", "0")].into();
+        assert!(breaker_profiles(rules.names(), |k| zero.get(k).map(|v| v.to_string())).is_err());
+    }
+
+    #[test]
+    fn test_invalid_optional_limits_rejected() {
+        let positive = |v: &f64| *v > 0.0;
+        assert_eq!(parse_opt("KILL_DAILY_LOSS_USD", None, "", positive).unwrap(), None);
+        assert_eq!(parse_opt("KILL_DAILY_LOSS_USD", Some(" 50 ".into()), "", positive).unwrap(), Some(50.0));
+        let err = parse_opt("KILL_DAILY_LOSS_USD", Some("50usd".into()), "a positive USD amount", positive).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid KILL_DAILY_LOSS_USD: 50usd (expected a positive USD amount)");
+        assert!(parse_opt("KILL_DAILY_LOSS_USD", Some("-5".into()), "", positive).is_err());
+        assert!(parse_opt("MAX_OPEN_POSITIONS", Some("0".into()), "", |v: &usize| *v > 0).is_err());
+
+        // Every invalid key is reported, valid ones still parse
+        let env: HashMap<&str, &str> = [("MAX_OPEN_POSITIONS", "0"), ("SLIPPAGE_MAX_PCT", "2"), ("KILL_MIN_USDC_BALANCE", "-1")].into();
+        let (limits, errors) = OptionalLimits::parse(|k| env.get(k).map(|v| v.to_string()));
+        assert_eq!(limits.slippage_max_pct, Some(2.0));
+        let keys: Vec<&str> = errors.iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, vec!["MAX_OPEN_POSITIONS", "KILL_MIN_USDC_BALANCE"]);
+    }
 }
@@ -0,0 +1,114 @@
+//! Copy order sizing
+//!
+//! The price, order type and size of a copy, from the whale's fill and the
+//! bot's settings. `process_order` and the audit replay both size through
+//! here, so the audit simulates exactly what the bot would have sent. The
+//! risk guard, slippage check and portfolio limits come on top and are not
+//! part of sizing.
+//!
+//! Errors are the order status the bot reports for the skip
+//! (`SKIPPED_PASSIVE`, `SKIPPED_CATEGORY:...`, `SKIPPED_BELOW_MIN ...`).
+
+use crate::market_category::Category;
+use crate::settings::{get_fill_tier_params, Config, CopyStrategy};
+use crate::strategy;
+
+/// The whale fill being copied
+#[derive(Debug, Clone, Copy)]
+pub struct WhaleFill<'a> {
+    pub token_id: &'a str,
+    pub side_is_buy: bool,
+    pub shares: f64,
+    pub price: f64,
+    /// The whale's order was resting and someone else took it
+    pub passive: bool,
+    pub is_live: Option<bool>,
+}
+
+/// Limit price and order type of the copy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrderTerms {
+    pub limit_price: f64,
+    pub order_action: &'static str,
+}
+
+/// Tier buffer and order type, after the passive-fill policy and the
+/// category's live-market rules.
+pub fn order_terms(config: &Config, category: &Category, fill: &WhaleFill) -> Result<OrderTerms, String> {
+    let Some((buffer, order_action, _tier_size_multiplier)) =
+        get_fill_tier_params(fill.shares, fill.side_is_buy, fill.passive, config.passive_fills, fill.token_id)
+    else {
+        return Err("SKIPPED_PASSIVE".into());
+    };
+
+    // Category live-market behaviour applies to new exposure only
+    if fill.side_is_buy && !category.allows_live(fill.is_live) {
+        let why = if fill.is_live == Some(true) { "LIVE" } else { "NOT_LIVE" };
+        return Err(format!("SKIPPED_CATEGORY:{}_{}", category.name, why));
+    }
+    let buffer = if fill.side_is_buy && fill.is_live == Some(true) { buffer + category.live_buffer } else { buffer };
+
+    // Polymarket valid price range: 0.01 to 0.99
+    let limit_price_raw = if fill.side_is_buy {
+        (fill.price + buffer).min(0.99)
+    } else {
+        (fill.price - buffer).max(0.01)
+    };
+    // Round price to 3 decimal places (tick size 0.001)
+    let limit_price = ((limit_price_raw * 1000.0).round() / 1000.0).clamp(0.01, 0.99);
+
+    Ok(OrderTerms { limit_price, order_action })
+}
+
+/// Copy size in shares: the copy strategy and every multiplier, within
+/// `MAX_ORDER_SIZE_USD` and `MIN_ORDER_SIZE_USD`. The category size multiplier
+/// scales new exposure only; sells follow the whale out at the full size so a
+/// smaller category size never leaves shares behind.
+pub fn copy_shares(config: &Config, category: &Category, fill: &WhaleFill, limit_price: f64) -> Result<f64, String> {
+    // Passive fills can be sized down (or up) separately, before the order size limits
+    let passive_multiplier = if fill.passive { config.passive_size_multiplier } else { 1.0 };
+    let trader_order_size_usd = fill.shares * fill.price;
+
+    // For sell orders: calculate as percentage of shares (not USD)
+    // For buy orders: calculate as percentage of USD value, then convert to shares
+    let shares = if !fill.side_is_buy {
+        let base_shares = match config.copy_strategy {
+            CopyStrategy::Percentage => fill.shares * (config.copy_size / 100.0),
+            // COPY_SIZE is a number of shares for sells (we sell shares we already own)
+            CopyStrategy::Fixed => config.copy_size,
+            CopyStrategy::Adaptive => {
+                // Effective % from the trader's order size in USD, applied to their share count
+                let effective_percent = strategy::calculate_adaptive_percent_for_display(config, trader_order_size_usd);
+                fill.shares * (effective_percent / 100.0)
+            }
+        };
+
+        let mut shares = base_shares * config.trade_multiplier * passive_multiplier;
+        if let Some(tiered_mult) = strategy::get_tiered_multiplier(&config.tiered_multipliers, trader_order_size_usd) {
+            shares *= tiered_mult;
+        }
+
+        // MAX_ORDER_SIZE_USD and MIN_ORDER_SIZE_USD in shares
+        shares = shares.min(config.max_order_size_usd / limit_price);
+        let min_shares = config.min_order_size_usd / limit_price;
+        if shares < min_shares {
+            return Err(format!("SKIPPED_BELOW_MIN ({:.2} shares < {:.2} min)", shares, min_shares));
+        }
+        shares
+    } else {
+        let base_order_usd = strategy::calculate_base_order_size(config, trader_order_size_usd, fill.price)
+            * passive_multiplier
+            * category.size_multiplier;
+        // Applies the multipliers and MAX_ORDER_SIZE_USD
+        let final_order_usd = strategy::calculate_final_order_size(config, base_order_usd, trader_order_size_usd);
+        if final_order_usd < config.min_order_size_usd {
+            return Err(format!("SKIPPED_BELOW_MIN (${:.2} < ${:.2})", final_order_usd, config.min_order_size_usd));
+        }
+        strategy::usd_to_shares(final_order_usd, limit_price)
+    };
+
+    if shares <= 0.0 {
+        return Err("SKIPPED_INVALID_SIZE".into());
+    }
+    Ok(shares)
+}
@@ -0,0 +1,178 @@
+//! Pre-trade slippage guard
+//!
+//! The copy limit is `whale_price + buffer`, but by the time we fire the book
+//! may have moved away from the whale. Before a taking (FAK) order goes out the
+//! live book is checked: if the best price has moved against us by more than
+//! `SLIPPAGE_MAX_TICKS` (in the market's own tick size) or `SLIPPAGE_MAX_PCT`
+//! (percent of the whale price) the copy is skipped, and if the
+//! depth inside our limit is smaller than our size the order is either resized
+//! to that depth or skipped (`SLIPPAGE_THIN_BOOK`).
+
+use anyhow::{Result, anyhow};
+
+use crate::settings::Config;
+
+/// Tick assumed for `SLIPPAGE_MAX_TICKS` when the market's tick size is unknown,
+/// the CLOB default
+pub const DEFAULT_TICK_SIZE: f64 = 0.01;
+
+/// What to do when the book inside our limit is thinner than our size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThinBookAction {
+    /// Shrink the order to the available depth
+    Resize,
+    Skip,
+}
+
+impl ThinBookAction {
+    pub fn from_str(s: &str) -> Result<Self> {
+        match s.to_uppercase().as_str() {
+            "RESIZE" => Ok(ThinBookAction::Resize),
+            "SKIP" => Ok(ThinBookAction::Skip),
+            _ => Err(anyhow!("Invalid SLIPPAGE_THIN_BOOK: {} (expected RESIZE or SKIP)", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlippageLimits {
+    pub max_ticks: Option<u32>,
+    /// Percent of the whale price
+    pub max_pct: Option<f64>,
+    /// None leaves depth unchecked
+    pub thin_book: Option<ThinBookAction>,
+}
+
+impl SlippageLimits {
+    pub fn from_config(cfg: &Config) -> Self {
+        Self {
+            max_ticks: cfg.slippage_max_ticks,
+            max_pct: cfg.slippage_max_pct,
+            thin_book: cfg.slippage_thin_book,
+        }
+    }
+
+    /// Whether the book needs fetching at all
+    pub fn any(&self) -> bool {
+        self.max_ticks.is_some() || self.max_pct.is_some() || self.thin_book.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlippageBlock {
+    /// Best price moved past the tick/percent limit
+    PriceMoved,
+    /// Not enough size inside our limit
+    ThinBook,
+    /// Nothing on our side of the book
+    EmptyBook,
+}
+
+impl SlippageBlock {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SlippageBlock::PriceMoved => "PRICE_MOVED",
+            SlippageBlock::ThinBook => "THIN_BOOK",
+            SlippageBlock::EmptyBook => "EMPTY_BOOK",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SlippageCheck {
+    Ok,
+    /// Go ahead with this many shares instead
+    Resize(f64),
+    Skip(SlippageBlock),
+}
+
+/// Check our order against the side of the book it would take from.
+/// `levels` are `(price, size)` in any order: asks for a buy, bids for a sell.
+/// `tick_size` is the market's minimum price increment.
+pub fn check(
+    limits: &SlippageLimits,
+    side_is_buy: bool,
+    whale_price: f64,
+    limit_price: f64,
+    shares: f64,
+    levels: &[(f64, f64)],
+    tick_size: f64,
+) -> SlippageCheck {
+    let prices = levels.iter().map(|&(p, _)| p);
+    let best = if side_is_buy { prices.reduce(f64::min) } else { prices.reduce(f64::max) };
+    let Some(best) = best else {
+        return SlippageCheck::Skip(SlippageBlock::EmptyBook);
+    };
+
+    // Only moves against us count; a better book is fine
+    let adverse = if side_is_buy { best - whale_price } else { whale_price - best };
+    // Half a tick of slack for float noise in the book prices
+    let too_many_ticks = limits.max_ticks.is_some_and(|t| adverse > (t as f64 + 0.5) * tick_size);
+    let too_far_pct = limits.max_pct.is_some_and(|pct| whale_price > 0.0 && adverse / whale_price * 100.0 > pct);
+    if too_many_ticks || too_far_pct {
+        return SlippageCheck::Skip(SlippageBlock::PriceMoved);
+    }
+
+    let Some(action) = limits.thin_book else {
+        return SlippageCheck::Ok;
+    };
+    let depth: f64 = levels.iter()
+        .filter(|&&(p, _)| if side_is_buy { p <= limit_price } else { p >= limit_price })
+        .map(|&(_, s)| s)
+        .sum();
+    if depth >= shares {
+        SlippageCheck::Ok
+    } else if action == ThinBookAction::Resize && depth > 0.0 {
+        SlippageCheck::Resize(depth)
+    } else {
+        SlippageCheck::Skip(SlippageBlock::ThinBook)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max_ticks: Option<u32>, max_pct: Option<f64>, thin_book: Option<ThinBookAction>) -> SlippageLimits {
+        SlippageLimits { max_ticks, max_pct, thin_book }
+    }
+
+    #[test]
+    fn test_price_moved() {
+        // Asks as the CLOB returns them, worst first
+        let asks = [(0.56, 100.0), (0.53, 100.0), (0.52, 100.0)];
+        let ticks = limits(Some(2), None, None);
+        assert_eq!(check(&ticks, true, 0.50, 0.51, 10.0, &asks, 0.01), SlippageCheck::Ok);
+        assert_eq!(check(&ticks, true, 0.49, 0.50, 10.0, &asks, 0.01), SlippageCheck::Skip(SlippageBlock::PriceMoved));
+        // Two ticks of a finer market is a smaller move
+        assert_eq!(check(&ticks, true, 0.50, 0.51, 10.0, &asks, 0.001), SlippageCheck::Skip(SlippageBlock::PriceMoved));
+
+        let pct = limits(None, Some(5.0), None);
+        assert_eq!(check(&pct, true, 0.50, 0.51, 10.0, &asks, 0.01), SlippageCheck::Ok);
+        assert_eq!(check(&pct, true, 0.45, 0.46, 10.0, &asks, 0.01), SlippageCheck::Skip(SlippageBlock::PriceMoved));
+
+        // A sell is only hurt by bids falling below the whale
+        let bids = [(0.40, 50.0), (0.60, 50.0)];
+        assert_eq!(check(&ticks, false, 0.55, 0.54, 10.0, &bids, 0.01), SlippageCheck::Ok);
+        assert_eq!(check(&ticks, false, 0.65, 0.64, 10.0, &bids, 0.01), SlippageCheck::Skip(SlippageBlock::PriceMoved));
+        assert_eq!(check(&ticks, false, 0.65, 0.64, 10.0, &[], 0.01), SlippageCheck::Skip(SlippageBlock::EmptyBook));
+    }
+
+    #[test]
+    fn test_thin_book() {
+        let asks = [(0.60, 500.0), (0.52, 30.0), (0.51, 20.0)];
+        let resize = limits(None, None, Some(ThinBookAction::Resize));
+        assert_eq!(check(&resize, true, 0.50, 0.52, 40.0, &asks, DEFAULT_TICK_SIZE), SlippageCheck::Ok);
+        assert_eq!(check(&resize, true, 0.50, 0.52, 80.0, &asks, DEFAULT_TICK_SIZE), SlippageCheck::Resize(50.0));
+        assert_eq!(check(&resize, true, 0.50, 0.505, 80.0, &asks, DEFAULT_TICK_SIZE), SlippageCheck::Skip(SlippageBlock::ThinBook));
+
+        let skip = limits(None, None, Some(ThinBookAction::Skip));
+        assert_eq!(check(&skip, true, 0.50, 0.52, 80.0, &asks, DEFAULT_TICK_SIZE), SlippageCheck::Skip(SlippageBlock::ThinBook));
+        // Depth unchecked when not configured
+        assert_eq!(check(&limits(None, None, None), true, 0.50, 0.52, 80.0, &asks, DEFAULT_TICK_SIZE), SlippageCheck::Ok);
+    }
+}
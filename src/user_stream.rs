@@ -0,0 +1,326 @@
+//! Authenticated CLOB user channel
+//!
+//! Streams our own order and trade events using the API credentials derived
+//! into `.clob_creds.json`: order placements, updates (partial fills) and
+//! cancellations, and trades as they move from MATCHED through MINED to
+//! CONFIRMED, or to FAILED. Fills are booked into the position ledger from
+//! here rather than inferred from the synchronous order response.
+
+use anyhow::Result;
+use futures::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+
+use crate::ApiCreds;
+
+pub const USER_WS_URL: &str = "wss://ws-subscriptions-clob.polymarket.com/ws/user";
+
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+// ============================================================================
+// Events
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderEventKind {
+    Placement,
+    /// Partial or full fill of a resting order
+    Update,
+    Cancellation,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderUpdate {
+    pub order_id: String,
+    pub asset_id: String,
+    pub side_is_buy: bool,
+    pub price: f64,
+    pub original_size: f64,
+    pub size_matched: f64,
+    pub kind: OrderEventKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeStatus {
+    Matched,
+    Mined,
+    Confirmed,
+    Retrying,
+    Failed,
+}
+
+impl TradeStatus {
+    fn parse(s: &str) -> Option<Self> {
+        match s.to_uppercase().as_str() {
+            "MATCHED" => Some(TradeStatus::Matched),
+            "MINED" => Some(TradeStatus::Mined),
+            "CONFIRMED" => Some(TradeStatus::Confirmed),
+            "RETRYING" => Some(TradeStatus::Retrying),
+            "FAILED" => Some(TradeStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Our side of one trade. When we were a maker, only our own maker orders count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TradeUpdate {
+    pub trade_id: String,
+    pub asset_id: String,
+    pub side_is_buy: bool,
+    pub shares: f64,
+    /// Average price of our part of the trade
+    pub price: f64,
+    pub status: TradeStatus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UserEvent {
+    Order(OrderUpdate),
+    Trade(TradeUpdate),
+}
+
+fn num(v: &Value) -> Option<f64> {
+    v.as_str().and_then(|s| s.parse().ok()).or_else(|| v.as_f64())
+}
+
+fn is_buy(v: &Value) -> bool {
+    v.as_str().is_some_and(|s| s.eq_ignore_ascii_case("BUY"))
+}
+
+/// Parse one frame. `api_key` identifies our maker orders inside a trade.
+pub fn parse_user_message(text: &str, api_key: &str) -> Vec<UserEvent> {
+    let Ok(val) = serde_json::from_str::<Value>(text) else {
+        return Vec::new();
+    };
+    let events = match val {
+        Value::Array(events) => events,
+        event => vec![event],
+    };
+    events.iter().filter_map(|e| match e["event_type"].as_str()? {
+        "order" => parse_order(e).map(UserEvent::Order),
+        "trade" => parse_trade(e, api_key).map(UserEvent::Trade),
+        _ => None,
+    }).collect()
+}
+
+fn parse_order(e: &Value) -> Option<OrderUpdate> {
+    let kind = match e["type"].as_str()?.to_uppercase().as_str() {
+        "PLACEMENT" => OrderEventKind::Placement,
+        "UPDATE" => OrderEventKind::Update,
+        "CANCELLATION" => OrderEventKind::Cancellation,
+        _ => return None,
+    };
+    Some(OrderUpdate {
+        order_id: e["id"].as_str()?.to_string(),
+        asset_id: e["asset_id"].as_str()?.to_string(),
+        side_is_buy: is_buy(&e["side"]),
+        price: num(&e["price"]).unwrap_or(0.0),
+        original_size: num(&e["original_size"]).unwrap_or(0.0),
+        size_matched: num(&e["size_matched"]).unwrap_or(0.0),
+        kind,
+    })
+}
+
+fn parse_trade(e: &Value, api_key: &str) -> Option<TradeUpdate> {
+    let status = TradeStatus::parse(e["status"].as_str()?)?;
+    let taker_is_buy = is_buy(&e["side"]);
+
+    let (asset_id, side_is_buy, shares, price) = if e["trader_side"].as_str() == Some("MAKER") {
+        // Our resting orders. On the taker's token they trade the opposite side;
+        // on the complementary token they trade the same side as the taker.
+        let ours: Vec<&Value> = e["maker_orders"].as_array()?.iter()
+            .filter(|m| m["owner"].as_str() == Some(api_key))
+            .collect();
+        let first = ours.first()?;
+        let shares: f64 = ours.iter().filter_map(|m| num(&m["matched_amount"])).sum();
+        let usd: f64 = ours.iter()
+            .filter_map(|m| Some(num(&m["matched_amount"])? * num(&m["price"])?))
+            .sum();
+        let trade_asset = e["asset_id"].as_str();
+        let asset = first["asset_id"].as_str().or(trade_asset)?;
+        let side_is_buy = if Some(asset) != trade_asset {
+            taker_is_buy
+        } else {
+            first.get("side").map(is_buy).unwrap_or(!taker_is_buy)
+        };
+        (asset.to_string(), side_is_buy, shares, if shares > 0.0 { usd / shares } else { 0.0 })
+    } else {
+        (e["asset_id"].as_str()?.to_string(), taker_is_buy, num(&e["size"])?, num(&e["price"])?)
+    };
+
+    Some(TradeUpdate {
+        trade_id: e["id"].as_str()?.to_string(),
+        asset_id,
+        side_is_buy,
+        shares,
+        price,
+        status,
+    })
+}
+
+// ============================================================================
+// Ledger Bookkeeping
+// ============================================================================
+
+/// What a trade update means for the position ledger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedgerAction {
+    /// Book the fill, first time a trade is seen alive
+    Apply,
+    /// Undo a booked fill whose settlement failed
+    Reverse,
+}
+
+/// Books each trade once, on its first MATCHED (or later) update, and
+/// reverses it if it ends FAILED. Trades move through several statuses and the
+/// channel may repeat them after a reconnect.
+#[derive(Default)]
+pub struct TradeTracker {
+    booked: HashMap<String, bool>,
+}
+
+impl TradeTracker {
+    pub fn on_trade(&mut self, trade: &TradeUpdate) -> Option<LedgerAction> {
+        let booked = self.booked.get(&trade.trade_id).copied();
+        match (trade.status, booked) {
+            (TradeStatus::Failed, Some(true)) => {
+                self.booked.insert(trade.trade_id.clone(), false);
+                Some(LedgerAction::Reverse)
+            }
+            (TradeStatus::Failed, None) => {
+                self.booked.insert(trade.trade_id.clone(), false);
+                None
+            }
+            (TradeStatus::Failed, Some(false)) => None,
+            (_, None) => {
+                self.booked.insert(trade.trade_id.clone(), true);
+                Some(LedgerAction::Apply)
+            }
+            // Already booked, or failed and not coming back
+            (_, Some(_)) => None,
+        }
+    }
+}
+
+// ============================================================================
+// Stream Task
+// ============================================================================
+
+/// Stream user events into `tx` forever, reconnecting on errors
+pub fn spawn_user_stream(creds: ApiCreds, tx: mpsc::UnboundedSender<UserEvent>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = run_user_stream(&creds, &tx).await {
+                eprintln!("⚠️ User stream: {} - reconnecting", e);
+            }
+            if tx.is_closed() {
+                return;
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    })
+}
+
+async fn run_user_stream(creds: &ApiCreds, tx: &mpsc::UnboundedSender<UserEvent>) -> Result<()> {
+    let (ws, _) = connect_async(USER_WS_URL).await?;
+    let (mut write, mut read) = ws.split();
+    let subscribe = json!({
+        "auth": {
+            "apiKey": creds.api_key,
+            "secret": creds.api_secret,
+            "passphrase": creds.api_passphrase,
+        },
+        "type": "user",
+        "markets": [],
+    });
+    write.send(Message::Text(subscribe.to_string())).await?;
+
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    loop {
+        tokio::select! {
+            msg = read.next() => {
+                let text = match msg {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => anyhow::bail!("connection closed"),
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                };
+                for event in parse_user_message(&text, &creds.api_key) {
+                    if tx.send(event).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+            _ = ping.tick() => {
+                write.send(Message::Text("PING".into())).await?;
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_order_and_trades() {
+        let order = r#"{"event_type":"order","type":"UPDATE","id":"0xo1","asset_id":"7","side":"BUY",
+            "price":"0.5","original_size":"100","size_matched":"40"}"#;
+        assert_eq!(parse_user_message(order, "key"), vec![UserEvent::Order(OrderUpdate {
+            order_id: "0xo1".into(), asset_id: "7".into(), side_is_buy: true, price: 0.5,
+            original_size: 100.0, size_matched: 40.0, kind: OrderEventKind::Update,
+        })]);
+
+        let taker = r#"{"event_type":"trade","id":"t1","asset_id":"7","side":"BUY","size":"25","price":"0.5",
+            "status":"MATCHED","trader_side":"TAKER","maker_orders":[]}"#;
+        let events: [UserEvent; 1] = parse_user_message(taker, "key").try_into().unwrap();
+        let [UserEvent::Trade(t)] = events else { panic!("expected a trade") };
+        assert_eq!((t.side_is_buy, t.shares, t.price, t.status), (true, 25.0, 0.5, TradeStatus::Matched));
+
+        // As maker, only our orders count and we trade against the taker's side
+        let maker = r#"[{"event_type":"trade","id":"t2","asset_id":"7","side":"BUY","size":"60","price":"0.5",
+            "status":"CONFIRMED","trader_side":"MAKER","maker_orders":[
+                {"order_id":"a","owner":"key","asset_id":"7","matched_amount":"30","price":"0.5"},
+                {"order_id":"b","owner":"other","asset_id":"7","matched_amount":"20","price":"0.5"},
+                {"order_id":"c","owner":"key","asset_id":"7","matched_amount":"10","price":"0.75"}]}]"#;
+        let events: [UserEvent; 1] = parse_user_message(maker, "key").try_into().unwrap();
+        let [UserEvent::Trade(t)] = events else { panic!("expected a trade") };
+        assert_eq!((t.side_is_buy, t.shares, t.price), (false, 40.0, 0.5625));
+
+        // Our buy of the other outcome matched against a taker buying this one
+        let complementary = r#"{"event_type":"trade","id":"t3","asset_id":"7","side":"BUY","size":"10","price":"0.75",
+            "status":"MATCHED","trader_side":"MAKER","maker_orders":[
+                {"order_id":"d","owner":"key","asset_id":"8","matched_amount":"10","price":"0.25"}]}"#;
+        let events: [UserEvent; 1] = parse_user_message(complementary, "key").try_into().unwrap();
+        let [UserEvent::Trade(t)] = events else { panic!("expected a trade") };
+        assert_eq!((t.asset_id.as_str(), t.side_is_buy, t.price), ("8", true, 0.25));
+    }
+
+    #[test]
+    fn test_trade_booked_once_and_reversed_on_failure() {
+        let mut tracker = TradeTracker::default();
+        let mut trade = TradeUpdate {
+            trade_id: "t1".into(), asset_id: "7".into(), side_is_buy: true,
+            shares: 10.0, price: 0.5, status: TradeStatus::Matched,
+        };
+        assert_eq!(tracker.on_trade(&trade), Some(LedgerAction::Apply));
+        trade.status = TradeStatus::Mined;
+        assert_eq!(tracker.on_trade(&trade), None);
+        trade.status = TradeStatus::Failed;
+        assert_eq!(tracker.on_trade(&trade), Some(LedgerAction::Reverse));
+        assert_eq!(tracker.on_trade(&trade), None);
+
+        // Failed before we ever saw it alive: nothing to book
+        trade.trade_id = "t2".into();
+        assert_eq!(tracker.on_trade(&trade), None);
+    }
+}
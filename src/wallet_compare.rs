@@ -0,0 +1,297 @@
+//! Side-by-side wallet comparison
+//!
+//! Pulls open positions and recent trades for two addresses from the Polymarket
+//! data API and measures how closely one tracks the other. The typical use is
+//! our funder against the whale we copy.
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::research::{compute_trader_stats, Fill};
+
+const DATA_API_BASE: &str = "https://data-api.polymarket.com";
+const PAGE_SIZE: usize = 500;
+const MAX_PAGES: usize = 20;
+
+// ============================================================================
+// Data API Records
+// ============================================================================
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenPosition {
+    /// ERC1155 token id
+    pub asset: String,
+    pub size: f64,
+    #[serde(default)]
+    pub avg_price: f64,
+    #[serde(default)]
+    pub cur_price: f64,
+    #[serde(default)]
+    pub current_value: f64,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub outcome: String,
+    #[serde(default)]
+    pub event_slug: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ActivityTrade {
+    timestamp: i64,
+    asset: String,
+    side: String,
+    size: f64,
+    usdc_size: f64,
+    price: f64,
+    #[serde(default)]
+    transaction_hash: String,
+}
+
+async fn get_paged<T: for<'de> Deserialize<'de>>(client: &reqwest::Client, base_url: &str) -> Result<Vec<T>> {
+    let mut out = Vec::new();
+    for page in 0..MAX_PAGES {
+        let url = format!("{}&limit={}&offset={}", base_url, PAGE_SIZE, page * PAGE_SIZE);
+        let resp = client.get(&url).send().await?;
+        if !resp.status().is_success() {
+            return Err(anyhow!("Data API error {} for {}", resp.status(), url));
+        }
+        let batch: Vec<T> = resp.json().await?;
+        let done = batch.len() < PAGE_SIZE;
+        out.extend(batch);
+        if done { break; }
+    }
+    Ok(out)
+}
+
+pub async fn fetch_open_positions(client: &reqwest::Client, address: &str) -> Result<Vec<OpenPosition>> {
+    let url = format!("{}/positions?user={}&sizeThreshold=0.01", DATA_API_BASE, address);
+    get_paged(client, &url).await
+}
+
+/// Trades since `since` (unix seconds), converted to research fills so the same
+/// realised P&L logic applies.
+pub async fn fetch_trades_since(client: &reqwest::Client, address: &str, since: i64) -> Result<Vec<Fill>> {
+    let url = format!("{}/activity?user={}&type=TRADE&start={}", DATA_API_BASE, address, since);
+    let trades: Vec<ActivityTrade> = get_paged(client, &url).await?;
+    Ok(trades.into_iter().enumerate().map(|(i, t)| Fill {
+        block: 0,
+        timestamp: t.timestamp,
+        tx_hash: t.transaction_hash,
+        log_index: i as u64,
+        exchange: String::new(),
+        trader: address.to_lowercase(),
+        token_id: t.asset,
+        side_is_buy: t.side.eq_ignore_ascii_case("BUY"),
+        shares: t.size,
+        usd: t.usdc_size,
+        price: t.price,
+    }).collect())
+}
+
+// ============================================================================
+// Snapshot
+// ============================================================================
+
+#[derive(Debug, Clone, Default)]
+pub struct WalletSnapshot {
+    pub address: String,
+    pub usdc_balance: f64,
+    pub positions: Vec<OpenPosition>,
+    pub volume_7d: f64,
+    pub volume_30d: f64,
+    /// Realised P&L over the 30 day trade window (average-cost basis)
+    pub realised_pnl_30d: f64,
+    pub trades_30d: usize,
+}
+
+impl WalletSnapshot {
+    pub fn mark_value(&self) -> f64 {
+        self.positions.iter().map(|p| p.current_value).sum()
+    }
+
+    /// Build from fetched data. `trades` must cover at least 30 days before `now`.
+    pub fn from_parts(address: &str, usdc_balance: f64, positions: Vec<OpenPosition>, trades: &[Fill], now: i64) -> Self {
+        let cutoff_7d = now - 7 * 86_400;
+        let cutoff_30d = now - 30 * 86_400;
+        let mut recent: Vec<Fill> = trades.iter().filter(|t| t.timestamp >= cutoff_30d).cloned().collect();
+        // Data API returns newest first; P&L needs chronological order
+        recent.sort_by_key(|t| t.timestamp);
+        for (i, t) in recent.iter_mut().enumerate() {
+            t.log_index = i as u64;
+        }
+
+        WalletSnapshot {
+            address: address.to_string(),
+            usdc_balance,
+            positions,
+            volume_7d: recent.iter().filter(|t| t.timestamp >= cutoff_7d).map(|t| t.usd).sum(),
+            volume_30d: recent.iter().map(|t| t.usd).sum(),
+            realised_pnl_30d: compute_trader_stats(&recent).first().map(|s| s.realised_pnl).unwrap_or(0.0),
+            trades_30d: recent.len(),
+        }
+    }
+}
+
+// ============================================================================
+// Comparison
+// ============================================================================
+
+#[derive(Debug, Clone)]
+pub struct OverlapRow {
+    pub token_id: String,
+    pub title: String,
+    pub outcome: String,
+    pub size_a: f64,
+    pub size_b: f64,
+    pub avg_price_a: f64,
+    pub avg_price_b: f64,
+    pub value_a: f64,
+    pub value_b: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub overlaps: Vec<OverlapRow>,
+    pub only_a: usize,
+    pub only_b: usize,
+    /// Share of B's open mark value in tokens A also holds
+    pub coverage_of_b: f64,
+    /// L2 distance between the two portfolios' value weights (0 = identical mix, sqrt(2) = disjoint)
+    pub tracking_error: f64,
+    /// Median size ratio A/B across overlapping tokens
+    pub median_size_ratio: Option<f64>,
+}
+
+fn weights(positions: &[OpenPosition]) -> HashMap<&str, f64> {
+    let total: f64 = positions.iter().map(|p| p.current_value).sum();
+    let mut w: HashMap<&str, f64> = HashMap::new();
+    if total <= 0.0 { return w; }
+    for p in positions {
+        *w.entry(p.asset.as_str()).or_insert(0.0) += p.current_value / total;
+    }
+    w
+}
+
+/// Compare wallet `a` (usually ours) against `b` (usually the whale).
+pub fn compare(a: &WalletSnapshot, b: &WalletSnapshot) -> Comparison {
+    let pos_a: HashMap<&str, &OpenPosition> = a.positions.iter().map(|p| (p.asset.as_str(), p)).collect();
+    let pos_b: HashMap<&str, &OpenPosition> = b.positions.iter().map(|p| (p.asset.as_str(), p)).collect();
+
+    let mut overlaps: Vec<OverlapRow> = pos_a.iter()
+        .filter_map(|(token, pa)| {
+            let pb = pos_b.get(token)?;
+            Some(OverlapRow {
+                token_id: token.to_string(),
+                title: pb.title.clone(),
+                outcome: pb.outcome.clone(),
+                size_a: pa.size,
+                size_b: pb.size,
+                avg_price_a: pa.avg_price,
+                avg_price_b: pb.avg_price,
+                value_a: pa.current_value,
+                value_b: pb.current_value,
+            })
+        })
+        .collect();
+    overlaps.sort_by(|x, y| y.value_b.partial_cmp(&x.value_b).unwrap_or(std::cmp::Ordering::Equal));
+
+    let b_total = b.mark_value();
+    let covered: f64 = overlaps.iter().map(|o| o.value_b).sum();
+
+    let (wa, wb) = (weights(&a.positions), weights(&b.positions));
+    let tokens: HashSet<&str> = wa.keys().chain(wb.keys()).copied().collect();
+    let tracking_error = tokens.iter()
+        .map(|t| (wa.get(t).unwrap_or(&0.0) - wb.get(t).unwrap_or(&0.0)).powi(2))
+        .sum::<f64>()
+        .sqrt();
+
+    let mut ratios: Vec<f64> = overlaps.iter().filter(|o| o.size_b > 0.0).map(|o| o.size_a / o.size_b).collect();
+    ratios.sort_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal));
+    let median_size_ratio = ratios.get(ratios.len() / 2).copied();
+
+    Comparison {
+        only_a: pos_a.len() - overlaps.len(),
+        only_b: pos_b.len() - overlaps.len(),
+        coverage_of_b: if b_total > 0.0 { covered / b_total } else { 0.0 },
+        tracking_error,
+        median_size_ratio,
+        overlaps,
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(asset: &str, size: f64, price: f64) -> OpenPosition {
+        OpenPosition {
+            asset: asset.into(),
+            size,
+            avg_price: price,
+            cur_price: price,
+            current_value: size * price,
+            title: String::new(),
+            outcome: String::new(),
+            event_slug: String::new(),
+        }
+    }
+
+    fn wallet(positions: Vec<OpenPosition>) -> WalletSnapshot {
+        WalletSnapshot { positions, ..Default::default() }
+    }
+
+    #[test]
+    fn test_compare_identical_mix_has_zero_tracking_error() {
+        let ours = wallet(vec![pos("1", 10.0, 0.5), pos("2", 10.0, 0.5)]);
+        let whale = wallet(vec![pos("1", 1000.0, 0.5), pos("2", 1000.0, 0.5)]);
+        let c = compare(&ours, &whale);
+        assert_eq!(c.overlaps.len(), 2);
+        assert!(c.tracking_error < 1e-9);
+        assert!((c.coverage_of_b - 1.0).abs() < 1e-9);
+        assert_eq!(c.median_size_ratio, Some(0.01));
+    }
+
+    #[test]
+    fn test_compare_partial_overlap() {
+        let ours = wallet(vec![pos("1", 10.0, 0.5), pos("3", 10.0, 0.5)]);
+        let whale = wallet(vec![pos("1", 100.0, 0.5), pos("2", 100.0, 0.5)]);
+        let c = compare(&ours, &whale);
+        assert_eq!((c.only_a, c.only_b), (1, 1));
+        assert!((c.coverage_of_b - 0.5).abs() < 1e-9);
+        // weights: ours {1: .5, 3: .5}, whale {1: .5, 2: .5}
+        assert!((c.tracking_error - 0.5f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_snapshot_volume_windows() {
+        let now = 100 * 86_400;
+        let trade = |days_ago: i64, buy: bool, usd: f64, price: f64| Fill {
+            block: 0,
+            timestamp: now - days_ago * 86_400,
+            tx_hash: String::new(),
+            log_index: 0,
+            exchange: String::new(),
+            trader: "0xa".into(),
+            token_id: "1".into(),
+            side_is_buy: buy,
+            shares: usd / price,
+            usd,
+            price,
+        };
+        // Newest first, like the data API
+        let trades = vec![trade(1, false, 6.0, 0.6), trade(10, true, 4.0, 0.4), trade(40, true, 100.0, 0.5)];
+        let s = WalletSnapshot::from_parts("0xa", 0.0, Vec::new(), &trades, now);
+        assert_eq!(s.trades_30d, 2);
+        assert!((s.volume_7d - 6.0).abs() < 1e-9);
+        assert!((s.volume_30d - 10.0).abs() < 1e-9);
+        assert!((s.realised_pnl_30d - 2.0).abs() < 1e-9);
+    }
+}